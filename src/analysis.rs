@@ -0,0 +1,265 @@
+use crate::rules::Rule;
+use crate::Gol;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+// What a pattern settles into when run forward.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    // every cell is dead from this generation on
+    Dies {
+        generation: usize,
+    },
+    // unchanging from generation preperiod on
+    Still {
+        preperiod: usize,
+    },
+    // state at generation preperiod + period repeats the one at preperiod
+    Oscillator {
+        period: usize,
+        preperiod: usize,
+    },
+    // state at generation preperiod + period is the one at preperiod moved by
+    // displacement (x, y), with y growing downwards
+    Spaceship {
+        period: usize,
+        preperiod: usize,
+        displacement: (isize, isize),
+    },
+    // no repetition within the generations that were run
+    Unresolved {
+        generations: usize,
+    },
+}
+
+impl Outcome {
+    pub fn period(&self) -> Option<usize> {
+        match self {
+            Outcome::Dies { .. } | Outcome::Still { .. } => Some(1),
+            Outcome::Oscillator { period, .. } | Outcome::Spaceship { period, .. } => Some(*period),
+            Outcome::Unresolved { .. } => None,
+        }
+    }
+
+    pub fn velocity(&self) -> Option<Velocity> {
+        match self {
+            Outcome::Spaceship {
+                period,
+                displacement,
+                ..
+            } => Some(Velocity {
+                dx: displacement.0,
+                dy: displacement.1,
+                period: *period,
+            }),
+            _ => None,
+        }
+    }
+}
+
+// Displacement per period, printed in the usual c/n notation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Velocity {
+    pub dx: isize,
+    pub dy: isize,
+    pub period: usize,
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl Display for Velocity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (x, y) = (self.dx.unsigned_abs(), self.dy.unsigned_abs());
+        if x != 0 && y != 0 && x != y {
+            // oblique ships give both components
+            return write!(f, "({},{})c/{}", x.max(y), x.min(y), self.period);
+        }
+
+        let speed = x.max(y);
+        let divisor = gcd(speed, self.period).max(1);
+        let (speed, period) = (speed / divisor, self.period / divisor);
+        if speed == 1 {
+            write!(f, "c/{}", period)?;
+        } else {
+            write!(f, "{}c/{}", speed, period)?;
+        }
+        if x == y {
+            write!(f, " diagonal")
+        } else {
+            write!(f, " orthogonal")
+        }
+    }
+}
+
+// Lines just after the longest runs of empty lines along one axis, counting
+// runs that wrap around the edge. A pattern touching every line of the axis
+// can't be placed along it, so it is left where it is.
+fn gap_ends(occupied: &[bool]) -> Vec<usize> {
+    let size = occupied.len();
+
+    let mut runs = Vec::new();
+    let mut longest = 0;
+    for start in 0..size {
+        // only look at runs that begin right after an occupied line
+        if occupied[start] || !occupied[(start + size - 1) % size] {
+            continue;
+        }
+        let mut len = 0;
+        while !occupied[(start + len) % size] {
+            len += 1;
+        }
+        longest = longest.max(len);
+        runs.push((len, (start + len) % size));
+    }
+
+    if longest == 0 {
+        return vec![0];
+    }
+    runs.into_iter()
+        .filter(|(len, _)| *len == longest)
+        .map(|(_, end)| end)
+        .collect()
+}
+
+pub(crate) struct Normalized {
+    pub origin: (usize, usize),
+    // (y, x) pairs, sorted
+    pub cells: Vec<(usize, usize)>,
+}
+
+// Live cells relative to the pattern's top left corner on the torus, plus
+// where that corner is. The corner sits just after the largest empty gap in
+// each axis, ties are settled by picking the smallest resulting cell list so
+// translated copies of a pattern always normalize to the same cells.
+pub(crate) fn normalize(size: usize, cells: &[bool]) -> Normalized {
+    let mut columns = vec![false; size];
+    let mut rows = vec![false; size];
+    let live: Vec<(usize, usize)> = cells
+        .iter()
+        .enumerate()
+        .filter(|(_, alive)| **alive)
+        .map(|(i, _)| (i % size, i / size))
+        .collect();
+    for (x, y) in &live {
+        columns[*x] = true;
+        rows[*y] = true;
+    }
+
+    let mut best: Option<Normalized> = None;
+    for x0 in &gap_ends(&columns) {
+        for y0 in &gap_ends(&rows) {
+            let mut shifted: Vec<(usize, usize)> = live
+                .iter()
+                .map(|(x, y)| ((y + size - y0) % size, (x + size - x0) % size))
+                .collect();
+            shifted.sort_unstable();
+            if best.as_ref().is_none_or(|best| shifted < best.cells) {
+                best = Some(Normalized {
+                    origin: (*x0, *y0),
+                    cells: shifted,
+                });
+            }
+        }
+    }
+    best.unwrap()
+}
+
+// Every cell's state, with the state it had the generation before in the
+// high byte for second order rules, which need both to go on.
+fn cell_values(game: &dyn Gol) -> Vec<u16> {
+    let states = game.to_states();
+    let previous = match game.rule() {
+        Rule::SecondOrder(_) => game.step_back().map(|back| back.to_states()),
+        _ => None,
+    };
+    match previous {
+        Some(previous) => states
+            .iter()
+            .zip(previous)
+            .map(|(state, previous)| *state as u16 | (previous as u16) << 8)
+            .collect(),
+        None => states.iter().map(|state| *state as u16).collect(),
+    }
+}
+
+// Normalized (y, x, value) of the cells that aren't 0.
+type StateCells = Vec<(usize, usize, u16)>;
+
+// Shortest signed distance from a to b on a ring of the given size.
+fn wrapped_delta(a: usize, b: usize, size: usize) -> isize {
+    let forward = (b + size - a) % size;
+    if forward > size / 2 {
+        forward as isize - size as isize
+    } else {
+        forward as isize
+    }
+}
+
+// Run the game forward until a state repeats, possibly translated, or
+// max_generations have passed. Cells in any state other than 0 are part of
+// the pattern.
+pub fn classify(game: &dyn Gol, max_generations: usize) -> Outcome {
+    let size = game.size();
+    let mut seen: HashMap<StateCells, (usize, (usize, usize))> = HashMap::new();
+    let mut current: Option<Box<dyn Gol>> = None;
+
+    for generation in 0..=max_generations {
+        if generation > 0 {
+            current = Some(match &current {
+                Some(state) => state.to_next(),
+                None => game.to_next(),
+            });
+        }
+        let values = cell_values(current.as_deref().unwrap_or(game));
+
+        let occupied: Vec<bool> = values.iter().map(|value| *value != 0).collect();
+        if !occupied.contains(&true) {
+            return Outcome::Dies { generation };
+        }
+
+        let Normalized { origin, cells } = normalize(size, &occupied);
+        let cells: StateCells = cells
+            .into_iter()
+            .map(|(y, x)| {
+                let i = ((y + origin.1) % size) * size + (x + origin.0) % size;
+                (y, x, values[i])
+            })
+            .collect();
+        if let Some((first, first_origin)) = seen.insert(cells, (generation, origin)) {
+            let period = generation - first;
+            let displacement = (
+                wrapped_delta(first_origin.0, origin.0, size),
+                wrapped_delta(first_origin.1, origin.1, size),
+            );
+            return if displacement != (0, 0) {
+                Outcome::Spaceship {
+                    period,
+                    preperiod: first,
+                    displacement,
+                }
+            } else if period == 1 {
+                Outcome::Still { preperiod: first }
+            } else {
+                Outcome::Oscillator {
+                    period,
+                    preperiod: first,
+                }
+            };
+        }
+    }
+
+    Outcome::Unresolved {
+        generations: max_generations,
+    }
+}
+
+// Period of the cycle the pattern ends up in, None if it did not settle.
+pub fn period(game: &dyn Gol, max_generations: usize) -> Option<usize> {
+    classify(game, max_generations).period()
+}
@@ -0,0 +1,330 @@
+use crate::game_impls::compute;
+use crate::objects::{common_name, Classifier};
+use crate::{Gol, Region, VulkanContext};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+pub struct CensusOptions {
+    // side of the random square placed in the middle of the board
+    pub soup_size: usize,
+    // side of the board the soup runs on
+    pub board_size: usize,
+    // chance of each soup cell starting alive
+    pub density: f64,
+    // soups still changing after this many generations are counted as unsettled
+    pub max_generations: usize,
+    // longest population cycle accepted as settled
+    pub max_period: usize,
+    // passed on to objects::separate
+    pub distance: usize,
+}
+
+impl Default for CensusOptions {
+    fn default() -> Self {
+        CensusOptions {
+            soup_size: 16,
+            board_size: 64,
+            density: 0.5,
+            max_generations: 4000,
+            max_period: 60,
+            distance: 1,
+        }
+    }
+}
+
+// The board for one soup. The same seed always gives the same soup.
+pub fn soup(seed: u64, options: &CensusOptions) -> Vec<bool> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let size = options.board_size;
+    let soup_size = options.soup_size.min(size);
+    let region = Region {
+        x: (size - soup_size) / 2,
+        y: (size - soup_size) / 2,
+        width: soup_size,
+        height: soup_size,
+    };
+
+    let mut board = vec![false; size * size];
+    for y in 0..region.height {
+        for x in 0..region.width {
+            board[region.index(size, x, y)] = rng.gen_bool(options.density);
+        }
+    }
+    board
+}
+
+// Decides when a soup has settled: its population has been repeating with a
+// short period for long enough. Moving objects don't change the population,
+// so soups that only emit spaceships settle too.
+struct Settling {
+    populations: Vec<usize>,
+    max_period: usize,
+}
+
+impl Settling {
+    fn new(max_period: usize) -> Settling {
+        Settling {
+            populations: Vec::new(),
+            max_period,
+        }
+    }
+
+    fn push(&mut self, cells: &[bool]) -> bool {
+        self.push_population(cells.iter().filter(|alive| **alive).count())
+    }
+
+    fn push_population(&mut self, population: usize) -> bool {
+        self.populations.push(population);
+
+        // the last cycle has to be seen repeating at least twice
+        let window = 2 * self.max_period;
+        let len = self.populations.len();
+        if len < window + self.max_period {
+            return false;
+        }
+        let recent = &self.populations[len - window..];
+        (1..=self.max_period).any(|period| {
+            let earlier = &self.populations[len - window - period..len - period];
+            recent == earlier
+        })
+    }
+}
+
+// Object counts over a number of soups.
+#[derive(Clone, Debug, Default)]
+pub struct Census {
+    pub soups: usize,
+    pub unsettled: usize,
+    pub counts: BTreeMap<String, u64>,
+}
+
+impl Census {
+    // Separate and classify the objects of a settled board.
+    pub fn add(
+        &mut self,
+        classifier: &mut Classifier,
+        size: usize,
+        cells: &[bool],
+        distance: usize,
+    ) {
+        self.soups += 1;
+        for object in classifier.objects(size, cells, distance) {
+            *self.counts.entry(object.apgcode).or_default() += 1;
+        }
+    }
+
+    pub fn add_unsettled(&mut self) {
+        self.soups += 1;
+        self.unsettled += 1;
+    }
+
+    pub fn merge(&mut self, other: &Census) {
+        self.soups += other.soups;
+        self.unsettled += other.unsettled;
+        for (code, count) in &other.counts {
+            *self.counts.entry(code.clone()).or_default() += count;
+        }
+    }
+
+    // Most common objects first.
+    pub fn table(&self) -> Vec<(&str, u64)> {
+        let mut table: Vec<(&str, u64)> = self
+            .counts
+            .iter()
+            .map(|(code, count)| (code.as_str(), *count))
+            .collect();
+        table.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        table
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("apgcode,name,count\n");
+        for (code, count) in self.table() {
+            writeln!(
+                out,
+                "{},{},{}",
+                code,
+                common_name(code).unwrap_or(""),
+                count
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let objects: Vec<String> = self
+            .table()
+            .iter()
+            .map(|(code, count)| {
+                let name = match common_name(code) {
+                    Some(name) => format!("\"{}\"", json_escape(name)),
+                    None => "null".to_string(),
+                };
+                format!(
+                    "{{\"apgcode\":\"{}\",\"name\":{},\"count\":{}}}",
+                    json_escape(code),
+                    name,
+                    count
+                )
+            })
+            .collect();
+        format!(
+            "{{\"soups\":{},\"unsettled\":{},\"objects\":[{}]}}",
+            self.soups,
+            self.unsettled,
+            objects.join(",")
+        )
+    }
+}
+
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Run one soup per seed on backend T and census the results.
+pub fn run<T: Gol + 'static>(
+    options: &CensusOptions,
+    seeds: impl IntoIterator<Item = u64>,
+) -> Census {
+    let mut census = Census::default();
+    let mut classifier = Classifier::default();
+
+    for seed in seeds {
+        let mut state: Box<dyn Gol> =
+            Box::new(T::from_slice(options.board_size, &soup(seed, options)));
+        let mut settling = Settling::new(options.max_period);
+        let mut settled = None;
+
+        for _ in 0..=options.max_generations {
+            let cells = state.to_vec();
+            if settling.push(&cells) {
+                settled = Some(cells);
+                break;
+            }
+            state = state.to_next();
+        }
+
+        match settled {
+            Some(cells) => census.add(
+                &mut classifier,
+                options.board_size,
+                &cells,
+                options.distance,
+            ),
+            None => census.add_unsettled(),
+        }
+    }
+    census
+}
+
+// Run the soups on the GPU, batch_size soups side by side in one image so a
+// single dispatch advances all of them. Only the tile populations are read
+// back each generation, the whole board only when a soup settles.
+pub fn run_compute(
+    context: Arc<VulkanContext>,
+    options: &CensusOptions,
+    seeds: &[u64],
+    batch_size: usize,
+) -> Census {
+    let mut census = Census::default();
+    let mut classifier = Classifier::default();
+
+    for batch in seeds.chunks(batch_size.max(1)) {
+        let soups: Vec<Vec<bool>> = batch.iter().map(|seed| soup(*seed, options)).collect();
+        let mut state = compute::GameState::from_tiles(context.clone(), options.board_size, &soups);
+        let mut settling: Vec<Settling> = batch
+            .iter()
+            .map(|_| Settling::new(options.max_period))
+            .collect();
+        let mut settled: Vec<Option<Vec<bool>>> = vec![None; batch.len()];
+
+        for _ in 0..=options.max_generations {
+            let populations = state.tile_populations();
+            let newly_settled: Vec<usize> = (0..batch.len())
+                .filter(|i| settled[*i].is_none())
+                .filter(|i| settling[*i].push_population(populations[*i] as usize))
+                .collect();
+            if !newly_settled.is_empty() {
+                let mut tiles =
+                    compute::split_tiles(state.size(), options.board_size, &state.to_vec());
+                for i in newly_settled {
+                    settled[i] = Some(std::mem::take(&mut tiles[i]));
+                }
+            }
+            if settled.iter().all(|s| s.is_some()) {
+                break;
+            }
+            state = state.next();
+        }
+
+        for cells in settled {
+            match cells {
+                Some(cells) => census.add(
+                    &mut classifier,
+                    options.board_size,
+                    &cells,
+                    options.distance,
+                ),
+                None => census.add_unsettled(),
+            }
+        }
+    }
+    census
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_impls::cpu;
+
+    #[test]
+    fn census_is_reproducible() {
+        let options = CensusOptions {
+            board_size: 48,
+            ..Default::default()
+        };
+        let census = run::<cpu::GameState>(&options, 0..4);
+        assert_eq!(census.soups, 4);
+        assert!(!census.counts.is_empty());
+        assert_eq!(
+            census.to_json(),
+            run::<cpu::GameState>(&options, 0..4).to_json()
+        );
+
+        let csv = census.to_csv();
+        assert!(csv.starts_with("apgcode,name,count\n"));
+        assert_eq!(csv.lines().count(), census.counts.len() + 1);
+    }
+
+    #[test]
+    fn compute_census_matches_cpu() {
+        let options = CensusOptions {
+            board_size: 32,
+            ..Default::default()
+        };
+        let seeds: Vec<u64> = (0..5).collect();
+        let census = run::<cpu::GameState>(&options, seeds.iter().copied());
+
+        // batches of 3 soups leave the last of the 2x2 tiles empty, and the
+        // second batch only fills two of them
+        let context = Arc::new(VulkanContext::try_create().unwrap());
+        let batched = run_compute(context, &options, &seeds, 3);
+        assert_eq!(batched.soups, census.soups);
+        assert_eq!(batched.unsettled, census.unsettled);
+        assert_eq!(batched.counts, census.counts);
+    }
+}
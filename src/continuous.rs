@@ -0,0 +1,323 @@
+use crate::Gol;
+
+pub mod compute;
+pub mod cpu;
+
+// Lenia with one or more concentric rings in its kernel and a Gaussian growth
+// function. Every step moves each cell towards growth * dt and clamps it to
+// 0..=1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lenia {
+    pub radius: u32,
+    // relative heights of the kernel's rings, from the inside out
+    pub peaks: Vec<f32>,
+    // the potential that grows cells the most, and how far around it they grow
+    pub mu: f32,
+    pub sigma: f32,
+    pub dt: f32,
+}
+
+// Orbium, the glider found in the first Lenia paper
+impl Default for Lenia {
+    fn default() -> Self {
+        Lenia {
+            radius: 13,
+            peaks: vec![1.0],
+            mu: 0.15,
+            sigma: 0.015,
+            dt: 0.1,
+        }
+    }
+}
+
+impl Lenia {
+    // One bump per ring of the kernel, 0 at the edges of the ring.
+    fn weight(&self, distance: f32) -> f32 {
+        let r = distance / self.radius as f32;
+        if r >= 1.0 {
+            return 0.0;
+        }
+        let ring = r * self.peaks.len() as f32;
+        let f = ring.fract();
+        if f <= 0.0 {
+            return 0.0;
+        }
+        self.peaks[ring as usize] * f32::exp(4.0 - 1.0 / (f * (1.0 - f)))
+    }
+
+    fn next(&self, state: f32, potential: f32) -> f32 {
+        let growth =
+            2.0 * f32::exp(-(potential - self.mu).powi(2) / (2.0 * self.sigma.powi(2))) - 1.0;
+        (state + self.dt * growth).clamp(0.0, 1.0)
+    }
+}
+
+// SmoothLife: the filling of the inner disk and of the ring around it decide
+// the next state through smooth versions of the birth and survival intervals.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SmoothLife {
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    // ring fillings that give a birth, and that keep a cell alive
+    pub birth: (f32, f32),
+    pub survival: (f32, f32),
+    // widths of the steps at the ends of the intervals and between them
+    pub alpha_n: f32,
+    pub alpha_m: f32,
+    // 1 replaces the state every step, smaller steps move it towards 0 or 1
+    pub dt: f32,
+}
+
+// Rafler's parameters
+impl Default for SmoothLife {
+    fn default() -> Self {
+        SmoothLife {
+            inner_radius: 4.0,
+            outer_radius: 12.0,
+            birth: (0.278, 0.365),
+            survival: (0.267, 0.445),
+            alpha_n: 0.028,
+            alpha_m: 0.147,
+            dt: 1.0,
+        }
+    }
+}
+
+impl SmoothLife {
+    // The disk and the ring, with a one cell wide antialiased edge.
+    fn weights(&self, distance: f32) -> [f32; 2] {
+        let inner = (self.inner_radius + 0.5 - distance).clamp(0.0, 1.0);
+        let outer = (self.outer_radius + 0.5 - distance).clamp(0.0, 1.0);
+        [inner, outer - inner]
+    }
+
+    fn next(&self, state: f32, m: f32, n: f32) -> f32 {
+        let step = |x: f32, a: f32, alpha: f32| 1.0 / (1.0 + f32::exp(-(x - a) * 4.0 / alpha));
+        let interval =
+            |x: f32, a: f32, b: f32| step(x, a, self.alpha_n) * (1.0 - step(x, b, self.alpha_n));
+        let alive = step(m, 0.5, self.alpha_m);
+        let mix = |birth: f32, survival: f32| birth * (1.0 - alive) + survival * alive;
+        let s = interval(
+            n,
+            mix(self.birth.0, self.survival.0),
+            mix(self.birth.1, self.survival.1),
+        );
+        if self.dt >= 1.0 {
+            s
+        } else {
+            (state + self.dt * (2.0 * s - 1.0)).clamp(0.0, 1.0)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContinuousRule {
+    Lenia(Lenia),
+    SmoothLife(SmoothLife),
+}
+
+impl Default for ContinuousRule {
+    fn default() -> Self {
+        ContinuousRule::Lenia(Lenia::default())
+    }
+}
+
+// A cell of the kernel. Lenia only uses the first weight, SmoothLife has the
+// inner disk first and the ring second. Each weight sums to 1 over all taps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tap {
+    pub dx: i32,
+    pub dy: i32,
+    pub weights: [f32; 2],
+}
+
+impl ContinuousRule {
+    pub fn taps(&self) -> Vec<Tap> {
+        let reach = match self {
+            ContinuousRule::Lenia(rule) => rule.radius as i32,
+            ContinuousRule::SmoothLife(rule) => (rule.outer_radius + 0.5).ceil() as i32,
+        };
+        let mut taps = Vec::new();
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                let weights = match self {
+                    ContinuousRule::Lenia(rule) => [rule.weight(distance), 0.0],
+                    ContinuousRule::SmoothLife(rule) => rule.weights(distance),
+                };
+                if weights != [0.0; 2] {
+                    taps.push(Tap { dx, dy, weights });
+                }
+            }
+        }
+
+        for i in 0..2 {
+            let total: f32 = taps.iter().map(|tap| tap.weights[i]).sum();
+            if total > 0.0 {
+                taps.iter_mut().for_each(|tap| tap.weights[i] /= total);
+            }
+        }
+        taps
+    }
+
+    // potentials are the sums of the states under each weight of the taps
+    pub fn next(&self, state: f32, potentials: [f32; 2]) -> f32 {
+        match self {
+            ContinuousRule::Lenia(rule) => rule.next(state, potentials[0]),
+            ContinuousRule::SmoothLife(rule) => rule.next(state, potentials[0], potentials[1]),
+        }
+    }
+}
+
+// A size * size board of cells from 0 to 1, the continuous counterpart of Gol.
+pub trait Continuous {
+    fn from_values(size: usize, values: &[f32]) -> Self
+    where
+        Self: Sized;
+    fn to_values(&self) -> Vec<f32>;
+    fn rule(&self) -> &ContinuousRule;
+    fn set_rule(&mut self, rule: ContinuousRule);
+    fn size(&self) -> usize;
+    fn to_next(&self) -> Box<dyn Continuous>;
+}
+
+// The values rounded to states 0 to levels - 1, for the print and image
+// outputs.
+pub fn quantized<T: Gol>(game: &dyn Continuous, levels: u8) -> T {
+    let top = (levels.max(2) - 1) as f32;
+    let states: Vec<u8> = game
+        .to_values()
+        .iter()
+        .map(|value| (value.clamp(0.0, 1.0) * top).round() as u8)
+        .collect();
+    T::from_states(game.size(), &states)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_impls;
+
+    // cpu
+    #[test]
+    fn cpu_uniform_growth() {
+        uniform_growth_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_convolution() {
+        convolution_tester::<cpu::GameState>();
+    }
+
+    // compute
+    #[test]
+    fn compute_uniform_growth() {
+        uniform_growth_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_convolution() {
+        convolution_tester::<compute::GameState>();
+    }
+
+    fn rules() -> [ContinuousRule; 4] {
+        [
+            ContinuousRule::default(),
+            ContinuousRule::Lenia(Lenia {
+                radius: 6,
+                peaks: vec![0.5, 1.0, 0.25],
+                ..Default::default()
+            }),
+            ContinuousRule::SmoothLife(SmoothLife {
+                inner_radius: 2.0,
+                outer_radius: 6.0,
+                ..Default::default()
+            }),
+            ContinuousRule::SmoothLife(SmoothLife {
+                dt: 0.2,
+                ..Default::default()
+            }),
+        ]
+    }
+
+    fn assert_close(values: &[f32], expected: &[f32]) {
+        assert_eq!(values.len(), expected.len());
+        for (i, (value, expected)) in values.iter().zip(expected).enumerate() {
+            assert!(
+                (value - expected).abs() < 1e-4,
+                "{}: {} {}",
+                i,
+                value,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn kernels_are_normalized() {
+        for rule in rules() {
+            let taps = rule.taps();
+            let inner: f32 = taps.iter().map(|tap| tap.weights[0]).sum();
+            assert!((inner - 1.0).abs() < 1e-5);
+            assert!(taps.iter().all(|tap| tap.weights.iter().all(|w| *w >= 0.0)));
+        }
+
+        // a single ring peaks halfway out
+        let lenia = Lenia::default();
+        assert_eq!(lenia.weight(6.5), 1.0);
+        assert_eq!(lenia.weight(0.0), 0.0);
+        assert_eq!(lenia.weight(13.0), 0.0);
+    }
+
+    fn uniform_growth_tester<T: Continuous>() {
+        // every potential of an even board is its value
+        let size = 32;
+        for rule in rules() {
+            for value in [0.0, 0.15, 0.3, 0.7] {
+                let mut state = T::from_values(size, &vec![value; size * size]);
+                state.set_rule(rule.clone());
+                let next = rule.next(value, [value; 2]);
+                assert_close(&state.to_next().to_values(), &vec![next; size * size]);
+            }
+        }
+    }
+
+    fn convolution_tester<T: Continuous>() {
+        let size = 32;
+        let values: Vec<f32> = (0..size * size)
+            .map(|i| ((i * 37 % 101) as f32 / 100.0).powi(2))
+            .collect();
+        for rule in rules() {
+            let mut state: Box<dyn Continuous> = Box::new(T::from_values(size, &values));
+            state.set_rule(rule.clone());
+            let mut expected = values.clone();
+            for _ in 0..3 {
+                state = state.to_next();
+                expected = step(size, &rule, &expected);
+                assert_close(&state.to_values(), &expected);
+            }
+        }
+
+        let board: game_impls::cpu::GameState =
+            quantized(T::from_values(size, &values).to_next().as_ref(), 10);
+        assert!(board.to_states().iter().all(|state| *state < 10));
+    }
+
+    // one step adding up every tap of every cell
+    fn step(size: usize, rule: &ContinuousRule, values: &[f32]) -> Vec<f32> {
+        let taps = rule.taps();
+        let wrap = |c: usize, d: i32| (c as i32 + d).rem_euclid(size as i32) as usize;
+        (0..values.len())
+            .map(|i| {
+                let (x, y) = (i % size, i / size);
+                let mut potentials = [0.0; 2];
+                for tap in &taps {
+                    let value = values[wrap(y, tap.dy) * size + wrap(x, tap.dx)];
+                    potentials[0] += tap.weights[0] * value;
+                    potentials[1] += tap.weights[1] * value;
+                }
+                rule.next(values[i], potentials)
+            })
+            .collect()
+    }
+}
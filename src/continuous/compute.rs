@@ -0,0 +1,305 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::Subbuffer,
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    descriptor_set::{layout::DescriptorSetLayout, PersistentDescriptorSet, WriteDescriptorSet},
+    format::Format,
+    image::{view::ImageView, Image},
+    pipeline::{
+        compute::ComputePipelineCreateInfo, layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+};
+
+use super::{Continuous, ContinuousRule};
+use crate::VulkanContext;
+
+// The board is an R32_SFLOAT image, the kernel's taps go to the shader in a
+// storage buffer.
+pub struct GameState {
+    size: usize,
+    context: Arc<VulkanContext>,
+    board: Arc<Image>,
+    compute_pipeline: Arc<ComputePipeline>,
+    descriptor_set_layout: Arc<DescriptorSetLayout>,
+    params_buffer: Subbuffer<[u32]>,
+    taps_buffer: Subbuffer<[u32]>,
+    rule: ContinuousRule,
+}
+
+impl GameState {
+    pub fn from_random(context: Arc<VulkanContext>, size: usize) -> GameState {
+        let board = context.image_from_iter_with_format(
+            [size as u32, size as u32, 1],
+            Format::R32_SFLOAT,
+            (0..size * size).map(|_| rand::random::<f32>()),
+        );
+
+        Self::from_image(context, board, size)
+    }
+
+    fn from_image(context: Arc<VulkanContext>, board: Arc<Image>, size: usize) -> GameState {
+        let rule = ContinuousRule::default();
+        let (params_buffer, taps_buffer) = Self::rule_buffers(&context, size, &rule);
+        let (compute_pipeline, descriptor_set_layout) = Self::create_pipeline(context.clone());
+
+        GameState {
+            size,
+            context,
+            board,
+            compute_pipeline,
+            descriptor_set_layout,
+            params_buffer,
+            taps_buffer,
+            rule,
+        }
+    }
+
+    // Laid out like field_params and kernel in the shader, floats are passed
+    // as their bits.
+    fn rule_buffers(
+        context: &VulkanContext,
+        size: usize,
+        rule: &ContinuousRule,
+    ) -> (Subbuffer<[u32]>, Subbuffer<[u32]>) {
+        let taps = rule.taps();
+        let mut params = vec![size as u32, taps.len() as u32];
+        let floats = match rule {
+            ContinuousRule::Lenia(rule) => {
+                params.push(0);
+                [rule.dt, rule.mu, rule.sigma, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+            }
+            ContinuousRule::SmoothLife(rule) => {
+                params.push(1);
+                [
+                    rule.dt,
+                    0.0,
+                    0.0,
+                    rule.birth.0,
+                    rule.birth.1,
+                    rule.survival.0,
+                    rule.survival.1,
+                    rule.alpha_n,
+                    rule.alpha_m,
+                ]
+            }
+        };
+        params.extend(floats.iter().map(|float| float.to_bits()));
+        let params = context.uniform_buffer_from_iter(params.into_iter());
+
+        let taps: Vec<u32> = taps
+            .iter()
+            .flat_map(|tap| [tap.dx as f32, tap.dy as f32, tap.weights[0], tap.weights[1]])
+            .map(|float| float.to_bits())
+            .collect();
+        let taps = context.storage_buffer_from_iter(taps.into_iter());
+        (params, taps)
+    }
+
+    fn create_pipeline(
+        context: Arc<VulkanContext>,
+    ) -> (Arc<ComputePipeline>, Arc<DescriptorSetLayout>) {
+        let shader = cs::load(context.device.clone()).expect("failed to create shader module");
+        let cs = shader.entry_point("main").unwrap();
+        let stage = PipelineShaderStageCreateInfo::new(cs);
+        let layout = PipelineLayout::new(
+            context.device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(context.device.clone())
+                .unwrap(),
+        )
+        .unwrap();
+
+        let descriptor_set_layout = layout.set_layouts().first().unwrap().clone();
+
+        (
+            ComputePipeline::new(
+                context.device.clone(),
+                None,
+                ComputePipelineCreateInfo::stage_layout(stage, layout.clone()),
+            )
+            .expect("failed to create compute pipeline"),
+            descriptor_set_layout,
+        )
+    }
+}
+
+impl Continuous for GameState {
+    fn from_values(size: usize, values: &[f32]) -> Self
+    where
+        Self: Sized,
+    {
+        let context = Arc::new(VulkanContext::try_create().unwrap());
+        let board = context.image_from_iter_with_format(
+            [size as u32, size as u32, 1],
+            Format::R32_SFLOAT,
+            values.iter().copied(),
+        );
+
+        Self::from_image(context, board, size)
+    }
+
+    fn to_values(&self) -> Vec<f32> {
+        let buffer_content = self.context.texels_from_image::<f32>(&self.board);
+        let binding = buffer_content.read().unwrap();
+        binding.to_vec()
+    }
+
+    fn rule(&self) -> &ContinuousRule {
+        &self.rule
+    }
+
+    fn set_rule(&mut self, rule: ContinuousRule) {
+        (self.params_buffer, self.taps_buffer) =
+            Self::rule_buffers(&self.context, self.size, &rule);
+        self.rule = rule;
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn to_next(&self) -> Box<dyn Continuous> {
+        let next_board = self.context.uninitialized_image_with_format(
+            [self.size as u32, self.size as u32, 1],
+            Format::R32_SFLOAT,
+        );
+
+        let view_previous = ImageView::new_default(self.board.clone()).unwrap();
+        let view_next = ImageView::new_default(next_board.clone()).unwrap();
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.context.descriptor_set_allocator,
+            self.descriptor_set_layout.clone(),
+            [
+                WriteDescriptorSet::buffer(0, self.params_buffer.clone()),
+                WriteDescriptorSet::image_view(1, view_previous),
+                WriteDescriptorSet::image_view(2, view_next),
+                WriteDescriptorSet::buffer(3, self.taps_buffer.clone()),
+            ],
+            [],
+        )
+        .unwrap();
+
+        let mut command_buffer_builder = AutoCommandBufferBuilder::primary(
+            &self.context.command_buffer_allocator,
+            self.context.compute_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        let groups = (self.size as u32).div_ceil(8);
+        command_buffer_builder
+            .bind_pipeline_compute(self.compute_pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                self.compute_pipeline.layout().clone(),
+                0,
+                descriptor_set,
+            )
+            .unwrap()
+            .dispatch([groups, groups, 1])
+            .unwrap();
+
+        self.context
+            .submit_compute(command_buffer_builder.build().unwrap());
+
+        Box::new(GameState {
+            size: self.size,
+            context: self.context.clone(),
+            board: next_board,
+            compute_pipeline: self.compute_pipeline.clone(),
+            descriptor_set_layout: self.descriptor_set_layout.clone(),
+            params_buffer: self.params_buffer.clone(),
+            taps_buffer: self.taps_buffer.clone(),
+            rule: self.rule.clone(),
+        })
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform field_params {
+                uint size;
+                uint taps;
+                // 0 for Lenia, 1 for SmoothLife
+                uint smooth_life;
+                float dt;
+                // Lenia's growth
+                float mu;
+                float sigma;
+                // SmoothLife's intervals and step widths
+                float birth_low;
+                float birth_high;
+                float survival_low;
+                float survival_high;
+                float alpha_n;
+                float alpha_m;
+            } params;
+
+            layout(set = 0, binding = 1, r32f) uniform readonly image2D src;
+
+            layout(set = 0, binding = 2, r32f) uniform writeonly image2D dest;
+
+            // dx, dy and the two weights of each cell of the kernel
+            layout(set = 0, binding = 3) readonly buffer kernel {
+                vec4 taps[];
+            } kernel;
+
+            float smooth_step(float x, float a, float alpha) {
+                return 1.0 / (1.0 + exp(-(x - a) * 4.0 / alpha));
+            }
+
+            float smooth_life(float state, float m, float n) {
+                float alive = smooth_step(m, 0.5, params.alpha_m);
+                float low = mix(params.birth_low, params.survival_low, alive);
+                float high = mix(params.birth_high, params.survival_high, alive);
+                float s = smooth_step(n, low, params.alpha_n)
+                    * (1.0 - smooth_step(n, high, params.alpha_n));
+                if(params.dt >= 1.0)
+                {
+                    return s;
+                }
+                return clamp(state + params.dt * (2.0 * s - 1.0), 0.0, 1.0);
+            }
+
+            float lenia(float state, float potential) {
+                float distance = potential - params.mu;
+                float growth = 2.0 * exp(-distance * distance / (2.0 * params.sigma * params.sigma)) - 1.0;
+                return clamp(state + params.dt * growth, 0.0, 1.0);
+            }
+
+            void main() {
+                ivec2 cell = ivec2(gl_GlobalInvocationID.xy);
+                int size = int(params.size);
+                if(cell.x >= size || cell.y >= size)
+                {
+                    return;
+                }
+
+                vec2 potentials = vec2(0.0);
+                for(uint i = 0; i < params.taps; i++)
+                {
+                    vec4 tap = kernel.taps[i];
+                    ivec2 neighbour = ((cell + ivec2(tap.xy)) % ivec2(size) + size) % ivec2(size);
+                    potentials += tap.zw * imageLoad(src, neighbour).x;
+                }
+
+                float state = imageLoad(src, cell).x;
+                float next = params.smooth_life == 1
+                    ? smooth_life(state, potentials.x, potentials.y)
+                    : lenia(state, potentials.x);
+                imageStore(dest, cell, vec4(next));
+            }
+        ",
+    }
+}
@@ -0,0 +1,83 @@
+use super::{Continuous, ContinuousRule, Tap};
+use ndarray::prelude::*;
+use ndarray::Array;
+
+pub struct GameState {
+    // indexed [y, x]
+    state: Array<f32, Ix2>,
+    taps: Vec<Tap>,
+    rule: ContinuousRule,
+}
+
+impl Continuous for GameState {
+    fn from_values(size: usize, values: &[f32]) -> GameState {
+        debug_assert!(size * size == values.len());
+        let rule = ContinuousRule::default();
+        GameState {
+            state: Array::from_shape_vec((size, size), values.to_vec()).unwrap(),
+            taps: rule.taps(),
+            rule,
+        }
+    }
+
+    fn to_values(&self) -> Vec<f32> {
+        self.state.iter().copied().collect()
+    }
+
+    fn rule(&self) -> &ContinuousRule {
+        &self.rule
+    }
+
+    fn set_rule(&mut self, rule: ContinuousRule) {
+        self.taps = rule.taps();
+        self.rule = rule;
+    }
+
+    fn size(&self) -> usize {
+        self.state.dim().0
+    }
+
+    fn to_next(&self) -> Box<dyn Continuous> {
+        let potentials = self.potentials();
+        let mut next = self.state.clone();
+        for ((y, x), next) in next.indexed_iter_mut() {
+            *next = self
+                .rule
+                .next(*next, [potentials[[0, y, x]], potentials[[1, y, x]]]);
+        }
+
+        Box::new(GameState {
+            state: next,
+            taps: self.taps.clone(),
+            rule: self.rule.clone(),
+        })
+    }
+}
+
+impl GameState {
+    pub fn from_random(size: usize) -> Box<dyn Continuous> {
+        let values: Vec<f32> = (0..size * size).map(|_| rand::random::<f32>()).collect();
+        Box::new(GameState::from_values(size, &values))
+    }
+
+    // Both weights of the kernel convolved with the board, indexed
+    // [weight, y, x]. Each tap adds the whole board shifted by its offset.
+    fn potentials(&self) -> Array<f32, Ix3> {
+        let size = self.size();
+        let mut potentials = Array::<f32, _>::zeros((2, size, size));
+        for tap in &self.taps {
+            let dx = tap.dx.rem_euclid(size as i32) as usize;
+            let dy = tap.dy.rem_euclid(size as i32) as usize;
+            for (i, weight) in tap.weights.iter().enumerate() {
+                if *weight == 0.0 {
+                    continue;
+                }
+                let mut potential = potentials.index_axis_mut(Axis(0), i);
+                for ((y, x), potential) in potential.indexed_iter_mut() {
+                    *potential += weight * self.state[[(y + dy) % size, (x + dx) % size]];
+                }
+            }
+        }
+        potentials
+    }
+}
@@ -0,0 +1,236 @@
+use crate::rules::ParseRuleError;
+use crate::Gol;
+
+pub mod compute;
+pub mod cpu;
+
+// Rule of a one dimensional automaton. Rows wrap around their ends like the
+// 2D boards do.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Rule1d {
+    // Wolfram's number for a 2 state rule on a cell and the cells either side
+    Elementary(u8),
+    Totalistic(Totalistic),
+}
+
+// k state rule where the next state only depends on the sum of the states of
+// the cell and the range cells on either side.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Totalistic {
+    pub states: u8,
+    pub range: u32,
+    // next state for each sum, from 0 to (2 * range + 1) * (states - 1)
+    pub outputs: Vec<u8>,
+}
+
+impl Totalistic {
+    // Wolfram's code: digit n of the code in base states is the next state
+    // for a sum of n.
+    pub fn from_code(states: u8, range: u32, code: u64) -> Result<Totalistic, ParseRuleError> {
+        if states < 2 {
+            return Err(ParseRuleError {
+                rule: code.to_string(),
+                reason: "totalistic rules need at least 2 states",
+            });
+        }
+        let sums = (2 * range as usize + 1) * (states as usize - 1) + 1;
+        let mut code = code;
+        let outputs = (0..sums)
+            .map(|_| {
+                let digit = (code % states as u64) as u8;
+                code /= states as u64;
+                digit
+            })
+            .collect();
+        Ok(Totalistic {
+            states,
+            range,
+            outputs,
+        })
+    }
+}
+
+impl Rule1d {
+    pub fn states(&self) -> u8 {
+        match self {
+            Rule1d::Elementary(_) => 2,
+            Rule1d::Totalistic(rule) => rule.states,
+        }
+    }
+
+    // cells on either side of a cell that affect it
+    pub fn range(&self) -> u32 {
+        match self {
+            Rule1d::Elementary(_) => 1,
+            Rule1d::Totalistic(rule) => rule.range,
+        }
+    }
+
+    // Next states, indexed by the window of 2 * range + 1 cells read as a
+    // number in base states for elementary rules, or by its sum for
+    // totalistic ones.
+    pub fn table(&self) -> Vec<u8> {
+        match self {
+            Rule1d::Elementary(number) => (0..8).map(|i| number >> i & 1).collect(),
+            Rule1d::Totalistic(rule) => rule.outputs.clone(),
+        }
+    }
+
+    pub fn next(&self, window: &[u8]) -> u8 {
+        match self {
+            Rule1d::Elementary(number) => {
+                let index = window
+                    .iter()
+                    .fold(0, |index, cell| index * 2 + *cell as u32);
+                number.checked_shr(index).unwrap_or(0) & 1
+            }
+            Rule1d::Totalistic(rule) => {
+                let sum: usize = window.iter().map(|cell| *cell as usize).sum();
+                rule.outputs.get(sum).copied().unwrap_or(0)
+            }
+        }
+    }
+}
+
+impl Default for Rule1d {
+    fn default() -> Self {
+        Rule1d::Elementary(30)
+    }
+}
+
+// A row of cells, the 1D counterpart of Gol.
+pub trait Automaton {
+    fn from_slice(width: usize, vec: &[bool]) -> Self
+    where
+        Self: Sized,
+    {
+        let states: Vec<u8> = vec.iter().map(|alive| *alive as u8).collect();
+        Self::from_states(width, &states)
+    }
+    fn from_states(width: usize, states: &[u8]) -> Self
+    where
+        Self: Sized;
+    fn to_states(&self) -> Vec<u8>;
+    fn rule(&self) -> &Rule1d;
+    fn set_rule(&mut self, rule: Rule1d);
+    fn width(&self) -> usize;
+    fn to_next(&self) -> Box<dyn Automaton>;
+}
+
+// One live cell in the middle, the usual start for rules like 30 and 110.
+pub fn single_cell(width: usize) -> Vec<bool> {
+    let mut row = vec![false; width];
+    if width > 0 {
+        row[width / 2] = true;
+    }
+    row
+}
+
+// Space-time diagram as a square board for the print and image outputs: the
+// automaton's row at the top and each following generation below it.
+pub fn space_time<T: Gol>(automaton: Box<dyn Automaton>) -> T {
+    let width = automaton.width();
+    let mut automaton = automaton;
+    let mut states = Vec::with_capacity(width * width);
+    for y in 0..width {
+        states.extend(automaton.to_states());
+        if y + 1 < width {
+            automaton = automaton.to_next();
+        }
+    }
+    T::from_states(width, &states)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_impls;
+
+    // cpu
+    #[test]
+    fn cpu_rule_30() {
+        rule_30_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_rule_110() {
+        rule_110_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_totalistic() {
+        totalistic_tester::<cpu::GameState>();
+    }
+
+    // compute
+    #[test]
+    fn compute_rule_30() {
+        rule_30_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_rule_110() {
+        rule_110_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_totalistic() {
+        totalistic_tester::<compute::GameState>();
+    }
+
+    fn rule_30_tester<T: Automaton + 'static>() {
+        let start = T::from_slice(9, &single_cell(9));
+        let diagram: game_impls::cpu::GameState = space_time(Box::new(start));
+        diagram.print();
+        assert!(diagram.to_text().starts_with(
+            "000010000\n\
+             000111000\n\
+             001100100\n\
+             011011110\n\
+             110010001\n"
+        ));
+    }
+
+    fn rule_110_tester<T: Automaton + 'static>() {
+        let mut start = T::from_slice(8, &single_cell(8));
+        start.set_rule(Rule1d::Elementary(110));
+        let diagram: game_impls::cpu::GameState = space_time(Box::new(start));
+        diagram.print();
+        assert!(diagram.to_text().starts_with(
+            "00001000\n\
+             00011000\n\
+             00111000\n\
+             01101000\n\
+             11111000\n"
+        ));
+    }
+
+    fn totalistic_tester<T: Automaton + 'static>() {
+        // the parity of the three cells is rule 150
+        let row: Vec<bool> = (0..16).map(|i| i * 7 % 5 < 2).collect();
+        let mut parity = T::from_slice(16, &row);
+        parity.set_rule(Rule1d::Totalistic(
+            Totalistic::from_code(2, 1, 0b1010).unwrap(),
+        ));
+        let mut rule_150 = T::from_slice(16, &row);
+        rule_150.set_rule(Rule1d::Elementary(150));
+
+        let mut parity: Box<dyn Automaton> = Box::new(parity);
+        let mut rule_150: Box<dyn Automaton> = Box::new(rule_150);
+        for _ in 0..5 {
+            parity = parity.to_next();
+            rule_150 = rule_150.to_next();
+            assert_eq!(parity.to_states(), rule_150.to_states());
+        }
+
+        // 3 states, range 1, code 777 is 0 1 2 1 0 0 1 for sums 0 to 6
+        let mut code_777 = T::from_states(5, &[0, 0, 2, 0, 0]);
+        code_777.set_rule(Rule1d::Totalistic(
+            Totalistic::from_code(3, 1, 777).unwrap(),
+        ));
+        assert_eq!(code_777.to_next().to_states(), vec![0, 2, 2, 2, 0]);
+
+        assert!(Totalistic::from_code(0, 1, 0).is_err());
+        assert!(Totalistic::from_code(1, 1, 0).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::Subbuffer,
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    descriptor_set::{layout::DescriptorSetLayout, PersistentDescriptorSet, WriteDescriptorSet},
+    image::{view::ImageView, Image},
+    pipeline::{
+        compute::ComputePipelineCreateInfo, layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+};
+
+use super::{Automaton, Rule1d};
+use crate::VulkanContext;
+
+// The row is a width * 1 image, the rule's table goes to the shader in a
+// storage buffer.
+pub struct GameState {
+    width: usize,
+    context: Arc<VulkanContext>,
+    row: Arc<Image>,
+    compute_pipeline: Arc<ComputePipeline>,
+    descriptor_set_layout: Arc<DescriptorSetLayout>,
+    params_buffer: Subbuffer<[u32]>,
+    table_buffer: Subbuffer<[u32]>,
+    rule: Rule1d,
+}
+
+impl GameState {
+    pub fn from_random(context: Arc<VulkanContext>, width: usize) -> GameState {
+        let row = context.image_from_iter(
+            [width as u32, 1, 1],
+            (0..width).map(|_| rand::random::<u8>() & 1),
+        );
+
+        Self::from_image(context, row, width)
+    }
+
+    fn from_image(context: Arc<VulkanContext>, row: Arc<Image>, width: usize) -> GameState {
+        let rule = Rule1d::default();
+        let (params_buffer, table_buffer) = Self::rule_buffers(&context, width, &rule);
+        let (compute_pipeline, descriptor_set_layout) = Self::create_pipeline(context.clone());
+
+        GameState {
+            width,
+            context,
+            row,
+            compute_pipeline,
+            descriptor_set_layout,
+            params_buffer,
+            table_buffer,
+            rule,
+        }
+    }
+
+    // Laid out like row_params and rule_table in the shader.
+    fn rule_buffers(
+        context: &VulkanContext,
+        width: usize,
+        rule: &Rule1d,
+    ) -> (Subbuffer<[u32]>, Subbuffer<[u32]>) {
+        let totalistic = matches!(rule, Rule1d::Totalistic(_));
+        let table = rule.table();
+        let params = context.uniform_buffer_from_iter(
+            [
+                width as u32,
+                rule.range(),
+                rule.states() as u32,
+                totalistic as u32,
+                table.len() as u32,
+            ]
+            .into_iter(),
+        );
+        let table = context.storage_buffer_from_iter(table.iter().map(|state| *state as u32));
+        (params, table)
+    }
+
+    fn create_pipeline(
+        context: Arc<VulkanContext>,
+    ) -> (Arc<ComputePipeline>, Arc<DescriptorSetLayout>) {
+        let shader = cs::load(context.device.clone()).expect("failed to create shader module");
+        let cs = shader.entry_point("main").unwrap();
+        let stage = PipelineShaderStageCreateInfo::new(cs);
+        let layout = PipelineLayout::new(
+            context.device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(context.device.clone())
+                .unwrap(),
+        )
+        .unwrap();
+
+        let descriptor_set_layout = layout.set_layouts().first().unwrap().clone();
+
+        (
+            ComputePipeline::new(
+                context.device.clone(),
+                None,
+                ComputePipelineCreateInfo::stage_layout(stage, layout.clone()),
+            )
+            .expect("failed to create compute pipeline"),
+            descriptor_set_layout,
+        )
+    }
+}
+
+impl Automaton for GameState {
+    fn from_states(width: usize, states: &[u8]) -> Self
+    where
+        Self: Sized,
+    {
+        let context = Arc::new(VulkanContext::try_create().unwrap());
+        let row = context.image_from_iter([width as u32, 1, 1], states.iter().copied());
+
+        Self::from_image(context, row, width)
+    }
+
+    fn to_states(&self) -> Vec<u8> {
+        let buffer_content = self.context.buffer_from_image(&self.row);
+        let binding = buffer_content.read().unwrap();
+        binding.to_vec()
+    }
+
+    fn rule(&self) -> &Rule1d {
+        &self.rule
+    }
+
+    fn set_rule(&mut self, rule: Rule1d) {
+        (self.params_buffer, self.table_buffer) =
+            Self::rule_buffers(&self.context, self.width, &rule);
+        self.rule = rule;
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn to_next(&self) -> Box<dyn Automaton> {
+        let next_row = self.context.uninitialized_image([self.width as u32, 1, 1]);
+
+        let view_previous = ImageView::new_default(self.row.clone()).unwrap();
+        let view_next = ImageView::new_default(next_row.clone()).unwrap();
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.context.descriptor_set_allocator,
+            self.descriptor_set_layout.clone(),
+            [
+                WriteDescriptorSet::buffer(0, self.params_buffer.clone()),
+                WriteDescriptorSet::image_view(1, view_previous),
+                WriteDescriptorSet::image_view(2, view_next),
+                WriteDescriptorSet::buffer(3, self.table_buffer.clone()),
+            ],
+            [],
+        )
+        .unwrap();
+
+        let mut command_buffer_builder = AutoCommandBufferBuilder::primary(
+            &self.context.command_buffer_allocator,
+            self.context.compute_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        command_buffer_builder
+            .bind_pipeline_compute(self.compute_pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                self.compute_pipeline.layout().clone(),
+                0,
+                descriptor_set,
+            )
+            .unwrap()
+            .dispatch([(self.width as u32).div_ceil(64), 1, 1])
+            .unwrap();
+
+        self.context
+            .submit_compute(command_buffer_builder.build().unwrap());
+
+        Box::new(GameState {
+            width: self.width,
+            context: self.context.clone(),
+            row: next_row,
+            compute_pipeline: self.compute_pipeline.clone(),
+            descriptor_set_layout: self.descriptor_set_layout.clone(),
+            params_buffer: self.params_buffer.clone(),
+            table_buffer: self.table_buffer.clone(),
+            rule: self.rule.clone(),
+        })
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform row_params {
+                uint width;
+                // cells on either side of a cell that affect it
+                uint range;
+                uint states;
+                // 1 when the table is indexed by the sum of the window
+                uint totalistic;
+                // entries in the rule table
+                uint outputs;
+            } params;
+
+            layout(set = 0, binding = 1, r8ui) uniform readonly uimage2D src;
+
+            layout(set = 0, binding = 2, r8ui) uniform writeonly uimage2D dest;
+
+            layout(set = 0, binding = 3) readonly buffer rule_table {
+                uint outputs[];
+            } table;
+
+            void main() {
+                if(gl_GlobalInvocationID.x >= params.width)
+                {
+                    return;
+                }
+
+                int x = int(gl_GlobalInvocationID.x);
+                int range = int(params.range);
+                int width = int(params.width);
+
+                // the window as a number in base states, or its sum
+                uint index = 0;
+                for(int dx = -range; dx <= range; dx++)
+                {
+                    int neighbour = ((x + dx) % width + width) % width;
+                    uint state = imageLoad(src, ivec2(neighbour, 0)).x;
+                    index = params.totalistic == 1 ? index + state : index * params.states + state;
+                }
+
+                // windows the table has no output for stay dead
+                uint next = index < params.outputs ? table.outputs[index] : 0;
+                imageStore(dest, ivec2(x, 0), uvec4(next));
+            }
+        ",
+    }
+}
@@ -0,0 +1,66 @@
+use super::{Automaton, Rule1d};
+
+pub struct GameState {
+    width: usize,
+    state: Vec<u8>,
+    rule: Rule1d,
+}
+
+impl Automaton for GameState {
+    fn from_states(width: usize, states: &[u8]) -> GameState {
+        debug_assert!(width == states.len());
+        GameState {
+            width,
+            state: states.to_owned(),
+            rule: Rule1d::default(),
+        }
+    }
+
+    fn to_states(&self) -> Vec<u8> {
+        self.state.to_vec()
+    }
+
+    fn rule(&self) -> &Rule1d {
+        &self.rule
+    }
+
+    fn set_rule(&mut self, rule: Rule1d) {
+        self.rule = rule;
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn to_next(&self) -> Box<dyn Automaton> {
+        let range = self.rule.range() as i64;
+        let width = self.width as i64;
+        let mut window = Vec::with_capacity(2 * range as usize + 1);
+        let state = (0..width)
+            .map(|x| {
+                window.clear();
+                for dx in -range..=range {
+                    window.push(self.state[(x + dx).rem_euclid(width) as usize]);
+                }
+                self.rule.next(&window)
+            })
+            .collect();
+
+        Box::new(GameState {
+            width: self.width,
+            state,
+            rule: self.rule.clone(),
+        })
+    }
+}
+
+impl GameState {
+    pub fn from_random(width: usize) -> Box<dyn Automaton> {
+        let state = (0..width).map(|_| rand::random::<u8>() & 1).collect();
+        Box::new(GameState {
+            width,
+            state,
+            rule: Rule1d::default(),
+        })
+    }
+}
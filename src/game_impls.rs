@@ -5,7 +5,17 @@ pub mod cpu_ndarray;
 #[cfg(test)]
 mod tests {
     use super::{compute, cpu, cpu_ndarray};
-    use crate::Gol;
+    use crate::layers::Layer;
+    use crate::patterns::rle::Rle;
+    use crate::patterns::{self, Orientation, PasteMode, Pattern};
+    use crate::rules::history::{self, History};
+    use crate::rules::larger::{LargerThanLife, Shape};
+    use crate::rules::margolus::Margolus;
+    use crate::rules::schedule::Schedule;
+    use crate::rules::stochastic::{self, Noise, Probability, Stochastic};
+    use crate::rules::{table::RuleTable, LifeLike, Neighbourhood, Rule};
+    use crate::{analysis, Gol, Region};
+    use std::sync::Arc;
 
     // cpu
     #[test]
@@ -33,6 +43,101 @@ mod tests {
         structure_beacon_tester::<cpu::GameState>();
     }
 
+    #[test]
+    fn cpu_period_pentadecathlon() {
+        period_pentadecathlon_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_spaceship_glider() {
+        spaceship_glider_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_spaceship_lwss() {
+        spaceship_lwss_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_spaceship_mwss() {
+        spaceship_mwss_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_spaceship_hwss() {
+        spaceship_hwss_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_copy_paste_wrapped() {
+        copy_paste_wrapped_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_generations_brians_brain() {
+        generations_brians_brain_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_wireworld() {
+        wireworld_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_larger_than_life() {
+        larger_than_life_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_isotropic() {
+        isotropic_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_neighbourhoods() {
+        neighbourhoods_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_margolus() {
+        margolus_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_margolus_odd_size() {
+        margolus_odd_size_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_second_order() {
+        second_order_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_stochastic() {
+        stochastic_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_noise() {
+        noise_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_schedule() {
+        schedule_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_layers() {
+        layers_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_history() {
+        history_tester::<cpu::GameState>();
+    }
+
     // cpu_ndarray
     #[test]
     fn cpu_ndarray_structure_tub() {
@@ -59,6 +164,101 @@ mod tests {
         structure_beacon_tester::<cpu_ndarray::GameState>();
     }
 
+    #[test]
+    fn cpu_ndarray_period_pentadecathlon() {
+        period_pentadecathlon_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_spaceship_glider() {
+        spaceship_glider_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_spaceship_lwss() {
+        spaceship_lwss_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_spaceship_mwss() {
+        spaceship_mwss_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_spaceship_hwss() {
+        spaceship_hwss_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_copy_paste_wrapped() {
+        copy_paste_wrapped_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_generations_brians_brain() {
+        generations_brians_brain_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_wireworld() {
+        wireworld_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_larger_than_life() {
+        larger_than_life_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_isotropic() {
+        isotropic_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_neighbourhoods() {
+        neighbourhoods_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_margolus() {
+        margolus_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_margolus_odd_size() {
+        margolus_odd_size_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_second_order() {
+        second_order_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_stochastic() {
+        stochastic_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_noise() {
+        noise_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_schedule() {
+        schedule_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_layers() {
+        layers_tester::<cpu_ndarray::GameState>();
+    }
+
+    #[test]
+    fn cpu_ndarray_history() {
+        history_tester::<cpu_ndarray::GameState>();
+    }
+
     // compute
     #[test]
     fn compute_structure_tub() {
@@ -85,112 +285,776 @@ mod tests {
         structure_beacon_tester::<compute::GameState>();
     }
 
-    fn structure_tub_tester<T: Gol>() {
-        let start = vec![
-            false, false, false, false, false, //
-            false, false, true, false, false, //
-            false, true, false, true, false, //
-            false, false, true, false, false, //
-            false, false, false, false, false,
-        ];
+    #[test]
+    fn compute_period_pentadecathlon() {
+        period_pentadecathlon_tester::<compute::GameState>();
+    }
 
-        let state1 = T::from_slice(5, &start);
-        state1.print();
-        let state2 = state1.to_next();
-        state2.print();
+    #[test]
+    fn compute_spaceship_glider() {
+        spaceship_glider_tester::<compute::GameState>();
+    }
 
-        assert!(state2.to_vec() == start);
+    #[test]
+    fn compute_spaceship_lwss() {
+        spaceship_lwss_tester::<compute::GameState>();
     }
 
-    fn structure_box_tester<T: Gol>() {
-        let start = vec![
-            false, false, false, false, //
-            false, true, true, false, //
-            false, true, true, false, //
-            false, false, false, false,
-        ];
-
-        let state1 = T::from_slice(4, &start);
-        state1.print();
-        let state2 = state1.to_next();
-        state2.print();
+    #[test]
+    fn compute_spaceship_mwss() {
+        spaceship_mwss_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_spaceship_hwss() {
+        spaceship_hwss_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_copy_paste_wrapped() {
+        copy_paste_wrapped_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_generations_brians_brain() {
+        generations_brians_brain_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_wireworld() {
+        wireworld_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_larger_than_life() {
+        larger_than_life_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_isotropic() {
+        isotropic_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_neighbourhoods() {
+        neighbourhoods_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_margolus() {
+        margolus_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_margolus_odd_size() {
+        margolus_odd_size_tester::<compute::GameState>();
+    }
 
-        assert!(state2.to_vec() == start);
+    #[test]
+    fn compute_second_order() {
+        second_order_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_stochastic() {
+        stochastic_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_noise() {
+        noise_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_schedule() {
+        schedule_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_layers() {
+        layers_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_history() {
+        history_tester::<compute::GameState>();
+    }
+
+    fn structure_tub_tester<T: Gol>() {
+        let tub = patterns::tub();
+        let start = tub.board(5, 1, 1, Orientation::Identity);
+        assert_structure::<T>(5, &start, tub.period.unwrap());
+    }
+
+    fn structure_box_tester<T: Gol>() {
+        let block = patterns::block();
+        let start = block.board(4, 1, 1, Orientation::Identity);
+        assert_structure::<T>(4, &start, block.period.unwrap());
     }
 
     // same as box test, but in the game corner to test wrapping behavior.
     fn structure_box_wrapped_tester<T: Gol>() {
-        let start = vec![
-            true, false, false, true, //
-            false, false, false, false, //
-            false, false, false, false, //
-            true, false, false, true,
-        ];
-
-        let state1 = T::from_slice(4, &start);
-        state1.print();
-        let state2 = state1.to_next();
-        state2.print();
-
-        assert!(state2.to_vec() == start);
+        let block = patterns::block();
+        let start = block.board(4, 3, 3, Orientation::Identity);
+        assert_structure::<T>(4, &start, block.period.unwrap());
     }
 
     fn structure_blinker_tester<T: Gol>() {
-        let start = vec![
-            false, false, false, false, false, //
-            false, false, true, false, false, //
-            false, false, true, false, false, //
-            false, false, true, false, false, //
-            false, false, false, false, false,
-        ];
-        let expected_mid = vec![
-            false, false, false, false, false, //
-            false, false, false, false, false, //
-            false, true, true, true, false, //
-            false, false, false, false, false, //
-            false, false, false, false, false,
-        ];
+        let blinker = patterns::blinker();
+        let start = blinker.board(5, 2, 1, Orientation::Rotate90);
+        let expected_mid = blinker.board(5, 1, 2, Orientation::Identity);
 
         let state1 = T::from_slice(5, &start);
-        state1.print();
         let state2 = state1.to_next();
         state2.print();
-        let state3 = state2.to_next();
-        state3.print();
 
         assert!(state2.to_vec() == expected_mid);
-        // verify that it repeats in 2 cycles
-        assert!(state3.to_vec() == start);
+        assert_structure::<T>(5, &start, blinker.period.unwrap());
     }
 
     fn structure_beacon_tester<T: Gol>() {
-        let start = vec![
-            false, false, false, false, false, false, //
-            false, true, true, false, false, false, //
-            false, true, true, false, false, false, //
-            false, false, false, true, true, false, //
-            false, false, false, true, true, false, //
-            false, false, false, false, false, false,
-        ];
+        let beacon = patterns::beacon();
+        let start = beacon.board(6, 1, 1, Orientation::Identity);
         // the middle two blink
-        let expected_mid = vec![
-            false, false, false, false, false, false, //
-            false, true, true, false, false, false, //
-            false, true, false, false, false, false, //
-            false, false, false, false, true, false, //
-            false, false, false, true, true, false, //
-            false, false, false, false, false, false,
-        ];
+        let expected_mid = board_from_rows(
+            6,
+            1,
+            1,
+            &[
+                "OO..", //
+                "O...", "...O", "..OO",
+            ],
+        );
 
         let state1 = T::from_slice(6, &start);
-        state1.print();
         let state2 = state1.to_next();
         state2.print();
-        let state3 = state2.to_next();
-        state3.print();
 
         assert!(state2.to_vec() == expected_mid);
-        // verify that it repeats in 2 cycles
-        assert!(state3.to_vec() == start);
+        assert_structure::<T>(6, &start, beacon.period.unwrap());
+    }
+
+    fn period_pentadecathlon_tester<T: Gol>() {
+        let pentadecathlon = patterns::pentadecathlon();
+        let start = pentadecathlon.board(20, 5, 8, Orientation::Identity);
+        assert_period::<T>(20, &start, pentadecathlon.period.unwrap());
+    }
+
+    // starts straddling the corner to check displacement across the wrap
+    fn spaceship_glider_tester<T: Gol>() {
+        let start = patterns::glider().board(12, 10, 11, Orientation::Identity);
+        assert_spaceship::<T>(12, &start, 4, (1, 1), "c/4 diagonal");
+    }
+
+    fn spaceship_lwss_tester<T: Gol>() {
+        let start = patterns::lwss().board(16, 6, 6, Orientation::Identity);
+        assert_spaceship::<T>(16, &start, 4, (-2, 0), "c/2 orthogonal");
+    }
+
+    fn spaceship_mwss_tester<T: Gol>() {
+        let start = patterns::mwss().board(16, 6, 5, Orientation::Identity);
+        assert_spaceship::<T>(16, &start, 4, (-2, 0), "c/2 orthogonal");
+    }
+
+    fn spaceship_hwss_tester<T: Gol>() {
+        let start = patterns::hwss().board(16, 5, 5, Orientation::Identity);
+        assert_spaceship::<T>(16, &start, 4, (-2, 0), "c/2 orthogonal");
+    }
+
+    // region copies and pastes that straddle the corner
+    fn copy_paste_wrapped_tester<T: Gol>() {
+        let glider = patterns::glider();
+        let state = T::from_slice(8, &glider.board(8, 7, 7, Orientation::Identity));
+        let region = Region {
+            x: 7,
+            y: 7,
+            width: 3,
+            height: 3,
+        };
+        assert_eq!(state.copy_region(region).cells, glider.cells);
+
+        let moved = state
+            .paste(&Pattern::empty(3, 3), 7, 7, PasteMode::Copy)
+            .paste(&glider, 2, 2, PasteMode::Or);
+        assert!(moved.to_vec() == glider.board(8, 2, 2, Orientation::Identity));
+        let cleared = moved.paste(&glider, 2, 2, PasteMode::Xor);
+        assert!(!cleared.to_vec().contains(&true));
+    }
+
+    fn generations_brians_brain_tester<T: Gol>() {
+        let mut state = T::from_slice(8, &board_from_rows(8, 2, 2, &["OO"]));
+        state.set_rule("/2/3".parse().unwrap()).unwrap();
+
+        // live cells always start dying, two neighbours give birth
+        let state = state.to_next();
+        state.print();
+        assert_eq!(
+            state.to_states(),
+            states_from_rows(8, &["", "..11", "..22", "..11"])
+        );
+        let state = state.to_next();
+        state.print();
+        assert_eq!(
+            state.to_states(),
+            states_from_rows(8, &["..11", "..22", ".1..1", "..22", "..11"])
+        );
+    }
+
+    fn wireworld_tester<T: Gol>() {
+        let mut state = T::from_states(8, &states_from_rows(8, &["", ".21333", "", ".3"]));
+        state
+            .set_rule(Rule::Table(Arc::new(RuleTable::wireworld())))
+            .unwrap();
+
+        // the electron moves one cell along the wire, the lone conductor stays
+        let state = state.to_next();
+        state.print();
+        assert_eq!(
+            state.to_states(),
+            states_from_rows(8, &["", ".32133", "", ".3"])
+        );
+        let state = state.to_next();
+        assert_eq!(
+            state.to_states(),
+            states_from_rows(8, &["", ".33213", "", ".3"])
+        );
+    }
+
+    fn neighbourhoods_tester<T: Gol>() {
+        // a lone cell gives birth to exactly its neighbours
+        for (rule, neighbourhood) in [
+            ("B1/S", Neighbourhood::Moore),
+            ("B1/SH", Neighbourhood::Hexagonal),
+            ("B1/SV", Neighbourhood::VonNeumann),
+        ] {
+            let mut state = T::from_slice(8, &board_from_rows(8, 3, 3, &["O"]));
+            state.set_rule(rule.parse().unwrap()).unwrap();
+            let mut expected = vec![false; 64];
+            for (dx, dy) in neighbourhood.offsets() {
+                expected[((3 + dy) * 8 + 3 + dx) as usize] = true;
+            }
+            assert_eq!(state.to_next().to_vec(), expected, "{}", rule);
+        }
+
+        let mut hex = T::from_slice(3, &board_from_rows(3, 1, 0, &["O", "O", "O"]));
+        hex.set_rule("B2/S34H".parse().unwrap()).unwrap();
+        assert_eq!(hex.to_text(), "  0 1 0\n 0 1 0\n0 1 0\n");
+    }
+
+    fn isotropic_tester<T: Gol>() {
+        let rule: Rule = "B2-a/S12".parse().unwrap();
+
+        // 2a blocks the births the two cells would give in B2/S12
+        let mut pair = T::from_slice(8, &board_from_rows(8, 2, 2, &["OO"]));
+        pair.set_rule(rule.clone()).unwrap();
+        assert_eq!(pair.to_next().to_vec(), pair.to_vec());
+
+        // 2i and 2c births, the lone cells die
+        let mut apart = T::from_slice(8, &board_from_rows(8, 2, 2, &["O.O"]));
+        apart.set_rule(rule).unwrap();
+        let next = apart.to_next();
+        next.print();
+        assert_eq!(next.to_vec(), board_from_rows(8, 3, 1, &["O", "O", "O"]));
+
+        // written out as an isotropic rule Life runs the same
+        let mut glider =
+            T::from_slice(8, &patterns::glider().board(8, 1, 1, Orientation::Identity));
+        glider
+            .set_rule(Rule::Isotropic("B3/S23".parse().unwrap()))
+            .unwrap();
+        let mut state: Box<dyn Gol> = Box::new(glider);
+        for _ in 0..4 {
+            state = state.to_next();
+        }
+        assert_eq!(
+            state.to_vec(),
+            patterns::glider().board(8, 2, 2, Orientation::Identity)
+        );
+    }
+
+    fn larger_than_life_tester<T: Gol>() {
+        let size = 12;
+        let start: Vec<u8> = (0..size * size)
+            .map(|i| ((i * 7 + i / 5) % 3 == 0) as u8)
+            .collect();
+
+        for rule in [
+            "R1,C0,M0,S2..3,B3..3,NM",
+            "R5,C0,M1,S34..58,B34..45,NM",
+            "R2,C3,M1,S3..6,B4..5,NN",
+            "R3,C0,M0,S6..14,B7..9,NC",
+        ] {
+            let rule: LargerThanLife = rule.parse().unwrap();
+            let mut state: Box<dyn Gol> = Box::new(T::from_states(size, &start));
+            state.set_rule(Rule::LargerThanLife(rule.clone())).unwrap();
+            let mut expected = start.clone();
+            for _ in 0..3 {
+                state = state.to_next();
+                expected = larger_than_life_step(size, &rule, &expected);
+                assert_eq!(state.to_states(), expected, "{}", rule);
+            }
+        }
+    }
+
+    fn margolus_odd_size_tester<T: Gol>() {
+        let mut state = T::from_slice(7, &[false; 7 * 7]);
+        let error = state
+            .set_rule(Rule::Margolus(Margolus::critters()))
+            .unwrap_err();
+        assert_eq!(error.size, 7);
+        assert_eq!(state.rule(), &Rule::life());
+    }
+
+    fn margolus_tester<T: Gol>() {
+        // a lone ball crosses its block every generation, a grain falls a row
+        let size = 8;
+        for (rule, step) in [
+            (Margolus::billiard_ball_machine(), (1, 1)),
+            (Margolus::sand(), (0, 1)),
+        ] {
+            let mut start = vec![false; size * size];
+            start[2 * size + 2] = true;
+            let mut state: Box<dyn Gol> = Box::new(T::from_slice(size, &start));
+            state.set_rule(Rule::Margolus(rule)).unwrap();
+            for generation in 1..=size {
+                state = state.to_next();
+                let (x, y) = (
+                    (2 + step.0 * generation) % size,
+                    (2 + step.1 * generation) % size,
+                );
+                let mut expected = vec![false; size * size];
+                expected[y * size + x] = true;
+                assert_eq!(state.to_vec(), expected, "generation {}", generation);
+            }
+        }
+
+        let start: Vec<u8> = (0..size * size)
+            .map(|i| ((i * 5 + i / 3) % 4 == 0) as u8)
+            .collect();
+        for rule in [
+            Margolus::critters(),
+            Margolus::tron(),
+            Margolus::billiard_ball_machine(),
+            Margolus::sand(),
+        ] {
+            let mut state: Box<dyn Gol> = Box::new(T::from_states(size, &start));
+            state.set_rule(Rule::Margolus(rule.clone())).unwrap();
+            let mut expected = start.clone();
+            for generation in 0..5 {
+                state = state.to_next();
+                expected = margolus_step(size, &rule, &expected, generation);
+                assert_eq!(state.to_states(), expected, "{}", rule);
+            }
+        }
+    }
+
+    fn second_order_tester<T: Gol>() {
+        let size = 16;
+        let start: Vec<bool> = soup(size).iter().map(|state| *state == 1).collect();
+        assert!(T::from_slice(size, &start).step_back().is_none());
+
+        for rule in ["B3/S23", "B36/S23", "B2/S34H", "B1/SV"] {
+            let rule: LifeLike = rule.parse().unwrap();
+            let mut life = T::from_slice(size, &start);
+            life.set_rule(Rule::LifeLike(rule.clone())).unwrap();
+            let mut state = T::from_slice(size, &start);
+            state.set_rule(Rule::SecondOrder(rule.clone())).unwrap();
+
+            // with nothing before the start the first step is the plain rule
+            let mut state = state.to_next();
+            assert_eq!(state.to_vec(), life.to_next().to_vec(), "{}", rule);
+
+            let mut forward = vec![start.clone()];
+            for _ in 1..12 {
+                forward.push(state.to_vec());
+                state = state.to_next();
+            }
+            for expected in forward.iter().rev() {
+                state = state.step_back().unwrap();
+                assert_eq!(&state.to_vec(), expected, "{}", rule);
+            }
+        }
+    }
+
+    fn stochastic_tester<T: Gol>() {
+        let size = 32;
+        let start = soup(size);
+        let stochastic = |rule: &str, birth, survival| Stochastic {
+            rule: rule.parse().unwrap(),
+            birth: Probability::new(birth),
+            survival: Probability::new(survival),
+            seed: 42,
+        };
+
+        // certain chances are the plain rule
+        let mut life = T::from_states(size, &start);
+        life.set_rule("B36/S23".parse().unwrap()).unwrap();
+        let mut state = T::from_states(size, &start);
+        state
+            .set_rule(Rule::Stochastic(stochastic("B36/S23", 1.0, 1.0)))
+            .unwrap();
+        let (mut life, mut state): (Box<dyn Gol>, Box<dyn Gol>) = (Box::new(life), Box::new(state));
+        for _ in 0..4 {
+            (life, state) = (life.to_next(), state.to_next());
+            assert_eq!(state.to_states(), life.to_states());
+        }
+
+        // and without births nothing comes alive
+        let mut state: Box<dyn Gol> = Box::new(T::from_states(size, &start));
+        state
+            .set_rule(Rule::Stochastic(stochastic("B3/S23", 0.0, 0.7)))
+            .unwrap();
+        let mut previous = start.clone();
+        for _ in 0..4 {
+            state = state.to_next();
+            let states = state.to_states();
+            assert!(states
+                .iter()
+                .zip(&previous)
+                .all(|(next, previous)| *next <= *previous));
+            previous = states;
+        }
+
+        for rule in [
+            stochastic("B3/S23", 0.5, 0.8),
+            stochastic("B2/S345/C4", 0.3, 0.6),
+            stochastic("B2/S34H", 0.9, 0.5),
+        ] {
+            let mut state: Box<dyn Gol> = Box::new(T::from_states(size, &start));
+            state.set_rule(Rule::Stochastic(rule.clone())).unwrap();
+            let mut expected = start.clone();
+            for generation in 0..5 {
+                state = state.to_next();
+                expected = stochastic_step(size, &rule, &expected, generation);
+                assert_eq!(state.to_states(), expected, "{}", rule);
+            }
+        }
+    }
+
+    fn noise_tester<T: Gol>() {
+        // nothing is born on an empty board, so only the noise shows
+        let size = 64;
+        let noise = Noise::new(0.1, 9);
+        let mut state = T::from_states(size, &vec![0; size * size]);
+        state.set_rule("B3/S23".parse().unwrap()).unwrap();
+        state.set_noise(Some(noise));
+        assert_eq!(state.noise(), Some(noise));
+        let state = state.to_next();
+        let states = state.to_states();
+        let expected: Vec<u8> = (0..size * size).map(|i| noise.apply(0, 0, i)).collect();
+        assert_eq!(states, expected);
+        let live = states.iter().filter(|state| **state == 1).count();
+        assert!((live as f64 / (size * size) as f64 - 0.1).abs() < 0.02);
+
+        // every later generation runs the rule and then flips its own cells
+        let rule: LifeLike = "B3/S23".parse().unwrap();
+        let mut expected = states;
+        let mut state = state;
+        for generation in 1..4 {
+            state = state.to_next();
+            expected = life_step(size, &rule, &expected)
+                .into_iter()
+                .enumerate()
+                .map(|(i, next)| noise.apply(next, generation, i))
+                .collect();
+            assert_eq!(state.to_states(), expected);
+        }
+    }
+
+    fn schedule_tester<T: Gol>() {
+        let size = 16;
+        let start = soup(size);
+        let life: LifeLike = "B3/S23".parse().unwrap();
+        let high_life: LifeLike = "B36/S125".parse().unwrap();
+        let schedule = Schedule::cycle(vec![
+            Rule::LifeLike(life.clone()),
+            Rule::LifeLike(high_life.clone()),
+        ])
+        .change_at(6, Rule::LifeLike(high_life.clone()));
+
+        let mut state: Box<dyn Gol> = Box::new(T::from_states(size, &start));
+        assert_eq!(state.generation(), 0);
+        let mut expected = start.clone();
+        for generation in 0..10 {
+            let rule = if generation % 2 == 0 && generation < 6 {
+                &life
+            } else {
+                &high_life
+            };
+            assert_eq!(schedule.rule_at(generation), &Rule::LifeLike(rule.clone()));
+            state = schedule.step(state).unwrap();
+            expected = life_step(size, rule, &expected);
+            assert_eq!(state.to_states(), expected, "generation {}", generation);
+            assert_eq!(state.generation(), generation + 1);
+        }
+
+        // the count goes through pastes and back with step_back
+        let pasted = state.paste(&Pattern::empty(1, 1), 0, 0, PasteMode::Or);
+        assert_eq!(pasted.generation(), 10);
+        let mut state: Box<dyn Gol> = Box::new(T::from_states(size, &start));
+        state.set_rule(Rule::SecondOrder(life)).unwrap();
+        let state = state.to_next().to_next();
+        assert_eq!(state.step_back().unwrap().generation(), 1);
+    }
+
+    fn layers_tester<T: Gol>() {
+        let size = 16;
+        let start = soup(size);
+        let mut state = T::from_states(size, &start);
+        state.set_rule("B2/S345/C4".parse().unwrap()).unwrap();
+        assert_eq!(state.layer(Layer::Age), None);
+        state.track(Layer::Age, true);
+        state.track(Layer::Heat, true);
+        let mut age: Vec<u32> = start.iter().map(|state| (*state == 1) as u32).collect();
+        let mut heat = vec![0; size * size];
+        assert_eq!(state.layer(Layer::Age), Some(age.clone()));
+        assert_eq!(state.layer(Layer::Heat), Some(heat.clone()));
+
+        let mut state: Box<dyn Gol> = Box::new(state);
+        let mut before = start.clone();
+        for _ in 0..6 {
+            state = state.to_next();
+            let after = state.to_states();
+            for i in 0..size * size {
+                age[i] = if after[i] == 1 { age[i] + 1 } else { 0 };
+                heat[i] += (before[i] != after[i]) as u32;
+            }
+            assert_eq!(state.layer(Layer::Age), Some(age.clone()));
+            assert_eq!(state.layer(Layer::Heat), Some(heat.clone()));
+            before = after;
+        }
+        assert!(heat.iter().any(|heat| *heat > 1));
+
+        state.track(Layer::Age, false);
+        assert_eq!(state.to_next().layer(Layer::Age), None);
+        assert_eq!(
+            state.to_next().layer(Layer::Heat).unwrap().len(),
+            size * size
+        );
+
+        // stepping back keeps the tracked layers, starting them over
+        let mut state = T::from_states(size, &start);
+        state
+            .set_rule(Rule::SecondOrder("B3/S23".parse().unwrap()))
+            .unwrap();
+        state.track(Layer::Age, true);
+        let back = state.to_next().to_next().step_back().unwrap();
+        let age: Vec<u32> = back
+            .to_states()
+            .iter()
+            .map(|state| Layer::Age.start(*state))
+            .collect();
+        assert_eq!(back.layer(Layer::Age), Some(age));
+        assert_eq!(back.layer(Layer::Heat), None);
+    }
+
+    fn history_tester<T: Gol>() {
+        let size = 16;
+        let glider = patterns::glider().board(size, 1, 1, Orientation::Identity);
+        let mut start: Vec<u8> = glider.iter().map(|alive| *alive as u8).collect();
+        // a marked cell in the glider's path
+        let marked = 5 * size + 5;
+        start[marked] = history::MARKED_OFF;
+        let mut life: Box<dyn Gol> = Box::new(T::from_slice(size, &glider));
+        let mut state = T::from_states(size, &start);
+        state.set_rule("LifeHistory".parse().unwrap()).unwrap();
+        let mut state: Box<dyn Gol> = Box::new(state);
+
+        let mut envelope = glider;
+        let mut marked_on = false;
+        for _ in 0..16 {
+            (life, state) = (life.to_next(), state.to_next());
+            let states = state.to_states();
+            let live: Vec<bool> = states.iter().map(|state| History::alive(*state)).collect();
+            assert_eq!(live, life.to_vec());
+
+            // the marked cell keeps its mark instead of leaving history
+            assert!(matches!(
+                states[marked],
+                history::MARKED_ON | history::MARKED_OFF
+            ));
+            marked_on |= states[marked] == history::MARKED_ON;
+            envelope.iter_mut().zip(&live).for_each(|(e, l)| *e |= *l);
+            envelope[marked] = live[marked];
+            assert_eq!(History::envelope(&states), envelope);
+        }
+        assert!(marked_on);
+
+        // the extended states go through RLE and back
+        let rle: Rle = Rle::from_game(state.as_ref()).to_string().parse().unwrap();
+        let read: T = rle.game(size, 0, 0).unwrap();
+        assert_eq!(read.rule(), state.rule());
+        assert_eq!(read.to_states(), state.to_states());
+        let live: Vec<bool> = state
+            .to_states()
+            .iter()
+            .map(|state| History::alive(*state))
+            .collect();
+        assert_eq!(rle.to_pattern().cells, live);
+        assert_eq!(state.copy_region(Region::whole(size)).cells, live);
+        assert_eq!(state.to_vec(), live);
+        assert_eq!(Pattern::from_game(state.as_ref()).population(), 5);
+
+        // a boundary cell is never born, so a blinker only grows one way
+        let mut state = T::from_states(8, &states_from_rows(8, &["", "0060", "0111"]));
+        state.set_rule("LifeHistory".parse().unwrap()).unwrap();
+        assert_eq!(
+            state.to_next().to_states(),
+            states_from_rows(8, &["", "0060", "0212", "0010"])
+        );
+    }
+
+    // one generation of a stochastic rule drawing every cell's number itself
+    fn stochastic_step(size: usize, rule: &Stochastic, states: &[u8], generation: u32) -> Vec<u8> {
+        let totals = live_neighbours(size, &rule.rule, states);
+        (0..size * size)
+            .map(|i| {
+                let random = stochastic::random(rule.seed, generation, i as u32);
+                rule.next(states[i], totals[i], random)
+            })
+            .collect()
+    }
+
+    fn life_step(size: usize, rule: &LifeLike, states: &[u8]) -> Vec<u8> {
+        let totals = live_neighbours(size, rule, states);
+        (0..size * size)
+            .map(|i| rule.next(states[i], totals[i]))
+            .collect()
+    }
+
+    fn live_neighbours(size: usize, rule: &LifeLike, states: &[u8]) -> Vec<u32> {
+        (0..size * size)
+            .map(|i| {
+                let (x, y) = ((i % size) as i32, (i / size) as i32);
+                rule.neighbourhood
+                    .offsets()
+                    .iter()
+                    .filter(|(dx, dy)| {
+                        let x = (x + dx).rem_euclid(size as i32) as usize;
+                        let y = (y + dy).rem_euclid(size as i32) as usize;
+                        states[y * size + x] == 1
+                    })
+                    .count() as u32
+            })
+            .collect()
+    }
+
+    // one generation going block by block
+    fn margolus_step(size: usize, rule: &Margolus, states: &[u8], generation: u64) -> Vec<u8> {
+        let offset = (generation % 2) as usize;
+        let mut next = vec![0; size * size];
+        for y in (offset..size + offset).step_by(2) {
+            for x in (offset..size + offset).step_by(2) {
+                let cells = [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)]
+                    .map(|(x, y)| (y % size) * size + x % size);
+                let block = cells
+                    .iter()
+                    .enumerate()
+                    .fold(0, |block, (bit, i)| block | states[*i] << bit);
+                for (bit, i) in cells.iter().enumerate() {
+                    next[*i] = rule.next(block) >> bit & 1;
+                }
+            }
+        }
+        next
+    }
+
+    // one generation counting every neighbour separately
+    fn larger_than_life_step(size: usize, rule: &LargerThanLife, states: &[u8]) -> Vec<u8> {
+        let range = rule.range as i32;
+        let in_shape = |dx: i32, dy: i32| match rule.shape {
+            Shape::Moore => true,
+            Shape::VonNeumann => dx.abs() + dy.abs() <= range,
+            Shape::Circular => dx * dx + dy * dy <= range * range,
+        };
+        (0..size * size)
+            .map(|i| {
+                let (x, y) = ((i % size) as i32, (i / size) as i32);
+                let mut total = 0;
+                for dy in -range..=range {
+                    for dx in -range..=range {
+                        let x = (x + dx).rem_euclid(size as i32) as usize;
+                        let y = (y + dy).rem_euclid(size as i32) as usize;
+                        let counted = in_shape(dx, dy) && (rule.middle || dx != 0 || dy != 0);
+                        total += (counted && states[y * size + x] == 1) as u32;
+                    }
+                }
+                rule.next(states[i], total)
+            })
+            .collect()
+    }
+
+    // run for one period and expect to be back at the start
+    fn assert_structure<T: Gol>(size: usize, start: &[bool], period: usize) {
+        let state = T::from_slice(size, start);
+        state.print();
+        let mut state: Box<dyn Gol> = Box::new(state);
+        for _ in 0..period {
+            state = state.to_next();
+            state.print();
+        }
+
+        assert!(state.to_vec() == start);
+    }
+
+    // size * size board with a plain text pattern ('O' is alive) placed at x, y,
+    // wrapping around the edges
+    fn board_from_rows(size: usize, x: usize, y: usize, rows: &[&str]) -> Vec<bool> {
+        let mut board = vec![false; size * size];
+        for (row_y, row) in rows.iter().enumerate() {
+            for (row_x, c) in row.chars().enumerate() {
+                board[((y + row_y) % size) * size + (x + row_x) % size] = c == 'O';
+            }
+        }
+        board
+    }
+
+    // size * size board with a fixed scattering of live cells, about 2 in 5
+    fn soup(size: usize) -> Vec<u8> {
+        (0..size * size)
+            .map(|i| ((i * 7 + i / 3) % 5 < 2) as u8)
+            .collect()
+    }
+
+    // size * size board of cell states given as digits, missing cells are 0
+    fn states_from_rows(size: usize, rows: &[&str]) -> Vec<u8> {
+        let mut board = vec![0; size * size];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                board[y * size + x] = c.to_digit(10).unwrap_or(0) as u8;
+            }
+        }
+        board
+    }
+
+    fn assert_period<T: Gol>(size: usize, start: &[bool], period: usize) {
+        let state = T::from_slice(size, start);
+        let outcome = analysis::classify(&state, period * 4);
+        assert!(
+            outcome.period() == Some(period),
+            "expected period {}, got {:?}",
+            period,
+            outcome
+        );
+    }
+
+    fn assert_spaceship<T: Gol>(
+        size: usize,
+        start: &[bool],
+        period: usize,
+        displacement: (isize, isize),
+        velocity: &str,
+    ) {
+        let state = T::from_slice(size, start);
+        let outcome = analysis::classify(&state, period * 4);
+        assert_eq!(
+            outcome,
+            analysis::Outcome::Spaceship {
+                period,
+                preperiod: 0,
+                displacement
+            }
+        );
+        assert_eq!(outcome.velocity().unwrap().to_string(), velocity);
     }
 }
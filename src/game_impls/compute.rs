@@ -4,16 +4,26 @@ use vulkano::{
     buffer::Subbuffer,
     command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
     descriptor_set::{layout::DescriptorSetLayout, PersistentDescriptorSet, WriteDescriptorSet},
+    format::Format,
     image::{view::ImageView, Image},
     pipeline::{
         compute::ComputePipelineCreateInfo, layout::PipelineDescriptorSetLayoutCreateInfo,
         ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
         PipelineShaderStageCreateInfo,
     },
+    shader::ShaderModule,
     sync::{self, GpuFuture},
 };
 
-use crate::{Gol, VulkanContext};
+use crate::layers::Layer;
+use crate::patterns::{PasteMode, Pattern};
+use crate::rules::history;
+use crate::rules::stochastic::Noise;
+use crate::rules::{BoardSizeError, Rule};
+use crate::{Gol, Region, VulkanContext};
+
+// One shader per kind of rule.
+const RULE_SHADERS: usize = 8;
 
 pub struct GameState {
     size: (usize, usize),
@@ -21,7 +31,29 @@ pub struct GameState {
     game_state: Arc<Image>,
     compute_pipeline: Arc<ComputePipeline>,
     descriptor_set_layout: Arc<DescriptorSetLayout>,
-    bounds_buffer: Subbuffer<[u32]>,
+    params_buffer: Subbuffer<[u32]>,
+    // neighbourhood spans or a compiled rule table for the rules needing them
+    rule_buffer: Option<Subbuffer<[u32]>>,
+    tile_size: usize,
+    rule: Rule,
+    noise: Option<Noise>,
+    // a second pass flipping cells, built once noise is first set
+    noise_pipeline: Option<(Arc<ComputePipeline>, Arc<DescriptorSetLayout>)>,
+    // counts the live cells of each tile, built on the first count
+    population_pipeline: Option<(Arc<ComputePipeline>, Arc<DescriptorSetLayout>)>,
+    // R16_UINT ages (R32_UINT without 16 bit storage images) and R32_UINT
+    // heat of the tracked layers, indexed by Layer, each with the pass making
+    // the next one
+    layers: [Option<Arc<Image>>; 2],
+    layer_pipelines: [Option<(Arc<ComputePipeline>, Arc<DescriptorSetLayout>)>; 2],
+    // the pipelines built so far for each kind of rule, indexed by
+    // shader_index, so changing rules back and forth doesn't rebuild them
+    rule_pipelines: [Option<(Arc<ComputePipeline>, Arc<DescriptorSetLayout>)>; RULE_SHADERS],
+    // the generation before this one, kept for second order rules
+    previous: Option<Arc<Image>>,
+    // generations since the board was made, Margolus blocks and stochastic
+    // draws depend on it
+    generation: i64,
 }
 
 impl GameState {
@@ -31,69 +63,48 @@ impl GameState {
             (0..size * size).map(|_| rand::random::<u8>() & 1),
         );
 
-        let bounds_buffer =
-            context.uniform_buffer_from_iter([size as u32, size as u32].into_iter());
-
-        let (compute_pipeline, descriptor_set_layout) = Self::create_pipeline(context.clone());
-
-        GameState {
-            size: (size, size),
-            context: context.clone(),
-            game_state,
-            compute_pipeline,
-            descriptor_set_layout,
-            bounds_buffer,
-        }
+        Self::from_image(context, game_state, size, size)
     }
 
-    fn create_pipeline(
+    // Lay several boards of tile_size * tile_size out side by side in one image.
+    // Each tile wraps around its own edges, so they all run independently but
+    // advance with a single dispatch.
+    pub fn from_tiles(
         context: Arc<VulkanContext>,
-    ) -> (Arc<ComputePipeline>, Arc<DescriptorSetLayout>) {
-        let shader = cs::load(context.device.clone()).expect("failed to create shader module");
-        let cs = shader.entry_point("main").unwrap();
-        let stage = PipelineShaderStageCreateInfo::new(cs);
-        let layout = PipelineLayout::new(
-            context.device.clone(),
-            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
-                .into_pipeline_layout_create_info(context.device.clone())
-                .unwrap(),
-        )
-        .unwrap();
-
-        let descriptor_set_layouts = layout.set_layouts();
-        let descriptor_set_layout_index = 0;
-        let descriptor_set_layout = descriptor_set_layouts
-            .get(descriptor_set_layout_index)
-            .unwrap();
+        tile_size: usize,
+        tiles: &[Vec<bool>],
+    ) -> GameState {
+        let per_side = (1..).find(|n| n * n >= tiles.len()).unwrap();
+        let size = per_side * tile_size;
 
-        (
-            ComputePipeline::new(
-                context.device.clone(),
-                None,
-                ComputePipelineCreateInfo::stage_layout(stage, layout.clone()),
-            )
-            .expect("failed to create compute pipeline"),
-            descriptor_set_layout.clone(),
-        )
-    }
-}
+        let mut board = vec![0u8; size * size];
+        for (i, tile) in tiles.iter().enumerate() {
+            debug_assert!(tile.len() == tile_size * tile_size);
+            let (tile_x, tile_y) = ((i % per_side) * tile_size, (i / per_side) * tile_size);
+            for (j, alive) in tile.iter().enumerate() {
+                board[(tile_y + j / tile_size) * size + tile_x + j % tile_size] = *alive as u8;
+            }
+        }
 
-impl Gol for GameState {
-    fn from_slice(size: usize, slice: &[bool]) -> Self
-    where
-        Self: Sized,
-    {
-        let context = Arc::new(VulkanContext::try_create().unwrap());
+        let game_state = context.image_from_iter([size as u32, size as u32, 1], board.into_iter());
 
-        let game_state = context.image_from_iter(
-            [size as u32, size as u32, 1],
-            slice.iter().map(|b| if *b { 1u8 } else { 0u8 }),
-        );
+        Self::from_image(context, game_state, size, tile_size)
+    }
 
-        let bounds_buffer =
-            context.uniform_buffer_from_iter([size as u32, size as u32].into_iter());
+    fn from_image(
+        context: Arc<VulkanContext>,
+        game_state: Arc<Image>,
+        size: usize,
+        tile_size: usize,
+    ) -> GameState {
+        let rule = Rule::default();
+        let params_buffer = Self::params_buffer(&context, size, tile_size, &rule, 0);
 
-        let (compute_pipeline, descriptor_set_layout) = Self::create_pipeline(context.clone());
+        let (compute_pipeline, descriptor_set_layout) =
+            Self::create_pipeline(context.clone(), &rule);
+        let mut rule_pipelines: [_; RULE_SHADERS] = Default::default();
+        rule_pipelines[Self::shader_index(&rule)] =
+            Some((compute_pipeline.clone(), descriptor_set_layout.clone()));
 
         GameState {
             size: (size, size),
@@ -101,17 +112,178 @@ impl Gol for GameState {
             game_state,
             compute_pipeline,
             descriptor_set_layout,
-            bounds_buffer,
+            params_buffer,
+            rule_buffer: None,
+            tile_size,
+            rule,
+            noise: None,
+            noise_pipeline: None,
+            population_pipeline: None,
+            layers: Default::default(),
+            layer_pipelines: Default::default(),
+            rule_pipelines,
+            previous: None,
+            generation: 0,
         }
     }
 
-    fn to_vec(&self) -> Vec<bool> {
-        let buffer_content = self.context.buffer_from_image(&self.game_state);
-        let binding = buffer_content.read().unwrap();
-        Vec::from_iter(binding.iter().map(|x| *x > 0u8))
+    // Laid out like game_params in the shader for the rule.
+    fn params_buffer(
+        context: &VulkanContext,
+        size: usize,
+        tile_size: usize,
+        rule: &Rule,
+        generation: i64,
+    ) -> Subbuffer<[u32]> {
+        let mut params = vec![size as u32, size as u32, tile_size as u32, tile_size as u32];
+        match rule {
+            Rule::LifeLike(rule) | Rule::SecondOrder(rule) => params.extend([
+                rule.birth,
+                rule.survival,
+                rule.states as u32,
+                rule.neighbourhood.offsets().len() as u32,
+            ]),
+            Rule::History(history) => {
+                let rule = &history.rule;
+                params.extend([
+                    rule.birth,
+                    rule.survival,
+                    history::STATES as u32,
+                    rule.neighbourhood.offsets().len() as u32,
+                ])
+            }
+            Rule::Stochastic(stochastic) => {
+                let rule = &stochastic.rule;
+                params.extend([
+                    rule.birth,
+                    rule.survival,
+                    rule.states as u32,
+                    rule.neighbourhood.offsets().len() as u32,
+                    stochastic.seed,
+                    generation as u32,
+                    stochastic.birth.fraction(),
+                    stochastic.survival.fraction(),
+                ])
+            }
+            Rule::Isotropic(rule) => params.extend([rule.states as u32, 0, 0, 0]),
+            Rule::LargerThanLife(rule) => params.extend([
+                rule.states as u32,
+                rule.middle as u32,
+                *rule.birth.start(),
+                *rule.birth.end(),
+                *rule.survival.start(),
+                *rule.survival.end(),
+                rule.spans().len() as u32,
+                0,
+            ]),
+            Rule::Table(table) => {
+                let positions = table.neighbourhood.offsets().len() + 1;
+                params.extend([
+                    table.states as u32,
+                    table.words() as u32,
+                    (positions - 1) as u32,
+                    // the outputs follow the masks
+                    (positions * table.states as usize * table.words()) as u32,
+                ])
+            }
+            Rule::Margolus(_) => params.extend([generation.rem_euclid(2) as u32, 0, 0, 0]),
+        }
+        context.uniform_buffer_from_iter(params.into_iter())
     }
 
-    fn to_next(&self) -> Box<dyn Gol> {
+    // Same game with a different board.
+    fn with_image(&self, game_state: Arc<Image>) -> GameState {
+        GameState {
+            size: self.size,
+            context: self.context.clone(),
+            game_state,
+            compute_pipeline: self.compute_pipeline.clone(),
+            descriptor_set_layout: self.descriptor_set_layout.clone(),
+            params_buffer: self.params_buffer.clone(),
+            rule_buffer: self.rule_buffer.clone(),
+            tile_size: self.tile_size,
+            rule: self.rule.clone(),
+            noise: self.noise,
+            noise_pipeline: self.noise_pipeline.clone(),
+            population_pipeline: self.population_pipeline.clone(),
+            layers: self.layers.clone(),
+            layer_pipelines: self.layer_pipelines.clone(),
+            rule_pipelines: self.rule_pipelines.clone(),
+            previous: self.previous.clone(),
+            generation: self.generation,
+        }
+    }
+
+    // Live cells in each tile, tiles in row major order. They are counted on
+    // the device, so only the counts are read back.
+    pub fn tile_populations(&mut self) -> Vec<u32> {
+        let per_side = self.size.0 / self.tile_size;
+        let (pipeline, layout) = self
+            .population_pipeline
+            .get_or_insert_with(|| {
+                let shader = cs_population::load(self.context.device.clone())
+                    .expect("failed to create shader module");
+                Self::pipeline_from_shader(self.context.clone(), shader)
+            })
+            .clone();
+
+        let counts = self
+            .context
+            .readable_buffer_from_iter((0..per_side * per_side).map(|_| 0));
+        let params = [
+            self.tile_size as u32,
+            per_side as u32,
+            matches!(self.rule, Rule::History(_)) as u32,
+            0,
+        ];
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.context.descriptor_set_allocator,
+            layout,
+            [
+                WriteDescriptorSet::buffer(
+                    0,
+                    self.context.uniform_buffer_from_iter(params.into_iter()),
+                ),
+                WriteDescriptorSet::image_view(
+                    1,
+                    ImageView::new_default(self.game_state.clone()).unwrap(),
+                ),
+                WriteDescriptorSet::buffer(2, counts.clone()),
+            ],
+            [],
+        )
+        .unwrap();
+
+        let mut command_buffer_builder = AutoCommandBufferBuilder::primary(
+            &self.context.command_buffer_allocator,
+            self.context.compute_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        command_buffer_builder
+            .bind_pipeline_compute(pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                pipeline.layout().clone(),
+                0,
+                descriptor_set,
+            )
+            .unwrap()
+            .dispatch([
+                (per_side as u32 - 1) / 8 + 1,
+                (per_side as u32 - 1) / 8 + 1,
+                1,
+            ])
+            .unwrap();
+        self.context
+            .submit_compute(command_buffer_builder.build().unwrap());
+
+        let counts = counts.read().unwrap().to_vec();
+        counts
+    }
+
+    pub(crate) fn next(&self) -> GameState {
         let next_state =
             self.context
                 .uninitialized_image([self.size.0 as u32, self.size.1 as u32, 1]);
@@ -119,14 +291,23 @@ impl Gol for GameState {
         let view_previous = ImageView::new_default(self.game_state.clone()).unwrap();
         let view_next = ImageView::new_default(next_state.clone()).unwrap();
 
+        let mut writes = vec![
+            WriteDescriptorSet::buffer(0, self.params_buffer.clone()),
+            WriteDescriptorSet::image_view(1, view_previous.clone()),
+            WriteDescriptorSet::image_view(2, view_next.clone()),
+        ]; // 0 is the binding
+        if let Some(rule_buffer) = &self.rule_buffer {
+            writes.push(WriteDescriptorSet::buffer(3, rule_buffer.clone()));
+        }
+        if let Some(previous) = &self.previous {
+            let view_before = ImageView::new_default(previous.clone()).unwrap();
+            writes.push(WriteDescriptorSet::image_view(3, view_before));
+        }
+
         let descriptor_set = PersistentDescriptorSet::new(
             &self.context.descriptor_set_allocator,
             self.descriptor_set_layout.clone(),
-            [
-                WriteDescriptorSet::buffer(0, self.bounds_buffer.clone()),
-                WriteDescriptorSet::image_view(1, view_previous.clone()),
-                WriteDescriptorSet::image_view(2, view_next.clone()),
-            ], // 0 is the binding
+            writes,
             [],
         )
         .unwrap();
@@ -156,6 +337,93 @@ impl Gol for GameState {
             .unwrap()
             .dispatch(work_group_counts)
             .unwrap();
+        if let (Some(noise), Some((pipeline, layout))) = (&self.noise, &self.noise_pipeline) {
+            let params = [
+                self.size.0 as u32,
+                self.size.1 as u32,
+                noise.seed,
+                self.generation as u32,
+                noise.fraction.fraction(),
+                0,
+                0,
+                0,
+            ];
+            let descriptor_set = PersistentDescriptorSet::new(
+                &self.context.descriptor_set_allocator,
+                layout.clone(),
+                [
+                    WriteDescriptorSet::buffer(
+                        0,
+                        self.context.uniform_buffer_from_iter(params.into_iter()),
+                    ),
+                    WriteDescriptorSet::image_view(1, view_next.clone()),
+                ],
+                [],
+            )
+            .unwrap();
+            command_buffer_builder
+                .bind_pipeline_compute(pipeline.clone())
+                .unwrap()
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Compute,
+                    pipeline.layout().clone(),
+                    0,
+                    descriptor_set,
+                )
+                .unwrap()
+                .dispatch(work_group_counts)
+                .unwrap();
+        }
+
+        let extent = [self.size.0 as u32, self.size.1 as u32, 1];
+        let mut next_layers: [Option<Arc<Image>>; 2] = Default::default();
+        for layer in Layer::ALL {
+            let (Some(values), Some((pipeline, layout))) = (
+                &self.layers[layer as usize],
+                &self.layer_pipelines[layer as usize],
+            ) else {
+                continue;
+            };
+            let next_values = self
+                .context
+                .uninitialized_image_with_format(extent, values.format());
+            let params = [self.size.0 as u32, self.size.1 as u32, 0, 0];
+            let mut writes = vec![
+                WriteDescriptorSet::buffer(
+                    0,
+                    self.context.uniform_buffer_from_iter(params.into_iter()),
+                ),
+                WriteDescriptorSet::image_view(2, view_next.clone()),
+                WriteDescriptorSet::image_view(3, ImageView::new_default(values.clone()).unwrap()),
+                WriteDescriptorSet::image_view(
+                    4,
+                    ImageView::new_default(next_values.clone()).unwrap(),
+                ),
+            ];
+            if layer == Layer::Heat {
+                writes.push(WriteDescriptorSet::image_view(1, view_previous.clone()));
+            }
+            let descriptor_set = PersistentDescriptorSet::new(
+                &self.context.descriptor_set_allocator,
+                layout.clone(),
+                writes,
+                [],
+            )
+            .unwrap();
+            command_buffer_builder
+                .bind_pipeline_compute(pipeline.clone())
+                .unwrap()
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Compute,
+                    pipeline.layout().clone(),
+                    0,
+                    descriptor_set,
+                )
+                .unwrap()
+                .dispatch(work_group_counts)
+                .unwrap();
+            next_layers[layer as usize] = Some(next_values);
+        }
 
         let command_buffer = command_buffer_builder.build().unwrap();
 
@@ -167,23 +435,315 @@ impl Gol for GameState {
 
         future.wait(None).unwrap();
 
-        Box::new(GameState {
-            size: self.size,
-            context: self.context.clone(),
-            game_state: next_state,
-            compute_pipeline: self.compute_pipeline.clone(),
-            descriptor_set_layout: self.descriptor_set_layout.clone(),
-            bounds_buffer: self.bounds_buffer.clone(),
+        let mut next = self.with_image(next_state);
+        next.layers = next_layers;
+        next.previous = self.previous.as_ref().map(|_| self.game_state.clone());
+        next.generation += 1;
+        if matches!(self.rule, Rule::Margolus(_) | Rule::Stochastic(_)) {
+            next.params_buffer = Self::params_buffer(
+                &self.context,
+                self.size.0,
+                self.tile_size,
+                &self.rule,
+                next.generation,
+            );
+        }
+        next
+    }
+
+    fn shader_index(rule: &Rule) -> usize {
+        match rule {
+            Rule::LifeLike(_) => 0,
+            Rule::Isotropic(_) => 1,
+            Rule::LargerThanLife(_) => 2,
+            Rule::Table(_) => 3,
+            Rule::Margolus(_) => 4,
+            Rule::SecondOrder(_) => 5,
+            Rule::Stochastic(_) => 6,
+            Rule::History(_) => 7,
+        }
+    }
+
+    fn create_pipeline(
+        context: Arc<VulkanContext>,
+        rule: &Rule,
+    ) -> (Arc<ComputePipeline>, Arc<DescriptorSetLayout>) {
+        let shader = match rule {
+            Rule::LifeLike(_) => cs::load(context.device.clone()),
+            Rule::Isotropic(_) => cs_isotropic::load(context.device.clone()),
+            Rule::LargerThanLife(_) => cs_larger::load(context.device.clone()),
+            Rule::Table(_) => cs_table::load(context.device.clone()),
+            Rule::Margolus(_) => cs_margolus::load(context.device.clone()),
+            Rule::SecondOrder(_) => cs_second_order::load(context.device.clone()),
+            Rule::Stochastic(_) => cs_stochastic::load(context.device.clone()),
+            Rule::History(_) => cs_history::load(context.device.clone()),
+        }
+        .expect("failed to create shader module");
+        Self::pipeline_from_shader(context, shader)
+    }
+
+    fn pipeline_from_shader(
+        context: Arc<VulkanContext>,
+        shader: Arc<ShaderModule>,
+    ) -> (Arc<ComputePipeline>, Arc<DescriptorSetLayout>) {
+        let cs = shader.entry_point("main").unwrap();
+        let stage = PipelineShaderStageCreateInfo::new(cs);
+        let layout = PipelineLayout::new(
+            context.device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(context.device.clone())
+                .unwrap(),
+        )
+        .unwrap();
+
+        let descriptor_set_layouts = layout.set_layouts();
+        let descriptor_set_layout_index = 0;
+        let descriptor_set_layout = descriptor_set_layouts
+            .get(descriptor_set_layout_index)
+            .unwrap();
+
+        (
+            ComputePipeline::new(
+                context.device.clone(),
+                None,
+                ComputePipelineCreateInfo::stage_layout(stage, layout.clone()),
+            )
+            .expect("failed to create compute pipeline"),
+            descriptor_set_layout.clone(),
+        )
+    }
+}
+
+// Split the cells of a board built by GameState::from_tiles back into tiles.
+pub fn split_tiles(size: usize, tile_size: usize, cells: &[bool]) -> Vec<Vec<bool>> {
+    let per_side = size / tile_size;
+    (0..per_side * per_side)
+        .map(|i| {
+            let (tile_x, tile_y) = ((i % per_side) * tile_size, (i / per_side) * tile_size);
+            (0..tile_size * tile_size)
+                .map(|j| cells[(tile_y + j / tile_size) * size + tile_x + j % tile_size])
+                .collect()
         })
+        .collect()
+}
+
+impl Gol for GameState {
+    fn from_states(size: usize, states: &[u8]) -> Self
+    where
+        Self: Sized,
+    {
+        let context = Arc::new(VulkanContext::try_create().unwrap());
+
+        let game_state =
+            context.image_from_iter([size as u32, size as u32, 1], states.iter().copied());
+
+        Self::from_image(context, game_state, size, size)
     }
 
-    fn print(&self) {
-        for (i, is_alive) in self.to_vec().iter().enumerate() {
-            print!("{}", if *is_alive { "1" } else { "0" });
-            if (i + 1) % (self.size.0) == 0 {
-                println!();
+    fn to_states(&self) -> Vec<u8> {
+        let buffer_content = self.context.buffer_from_image(&self.game_state);
+        let binding = buffer_content.read().unwrap();
+        binding.to_vec()
+    }
+
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    fn set_rule(&mut self, rule: Rule) -> Result<(), BoardSizeError> {
+        // every tile is a board of its own
+        rule.check_size(self.tile_size)?;
+        self.params_buffer = Self::params_buffer(
+            &self.context,
+            self.size.0,
+            self.tile_size,
+            &rule,
+            self.generation,
+        );
+        self.rule_buffer = match &rule {
+            Rule::Isotropic(rule) => Some(
+                self.context
+                    .storage_buffer_from_iter(rule.table().iter().map(|alive| *alive as u32)),
+            ),
+            Rule::LargerThanLife(rule) => {
+                let spans = rule.spans().into_iter().flat_map(|span| {
+                    [span.top, span.bottom, span.left, span.right].map(|offset| offset as u32)
+                });
+                Some(
+                    self.context
+                        .storage_buffer_from_iter(spans.collect::<Vec<_>>().into_iter()),
+                )
+            }
+            Rule::Table(table) => Some(
+                self.context
+                    .storage_buffer_from_iter(table.to_gpu_words().into_iter()),
+            ),
+            Rule::Margolus(rule) => Some(
+                self.context
+                    .storage_buffer_from_iter(rule.blocks.iter().map(|block| *block as u32)),
+            ),
+            _ => None,
+        };
+        // the generation before the first one is empty
+        self.previous = match rule {
+            Rule::SecondOrder(_) => self.previous.take().or_else(|| {
+                let (width, height) = self.size;
+                Some(self.context.image_from_iter(
+                    [width as u32, height as u32, 1],
+                    (0..width * height).map(|_| 0),
+                ))
+            }),
+            _ => None,
+        };
+        let pipeline = self.rule_pipelines[Self::shader_index(&rule)]
+            .get_or_insert_with(|| Self::create_pipeline(self.context.clone(), &rule));
+        (self.compute_pipeline, self.descriptor_set_layout) = pipeline.clone();
+        self.rule = rule;
+        Ok(())
+    }
+
+    fn noise(&self) -> Option<Noise> {
+        self.noise
+    }
+
+    fn set_noise(&mut self, noise: Option<Noise>) {
+        if noise.is_some() && self.noise_pipeline.is_none() {
+            let shader = cs_noise::load(self.context.device.clone())
+                .expect("failed to create shader module");
+            self.noise_pipeline = Some(Self::pipeline_from_shader(self.context.clone(), shader));
+        }
+        self.noise = noise;
+    }
+
+    fn track(&mut self, layer: Layer, on: bool) {
+        let index = layer as usize;
+        if !on {
+            self.layers[index] = None;
+            return;
+        }
+        if self.layers[index].is_some() {
+            return;
+        }
+        let narrow_ages = self
+            .context
+            .device
+            .enabled_features()
+            .shader_storage_image_extended_formats;
+        if self.layer_pipelines[index].is_none() {
+            let shader = match layer {
+                Layer::Age if narrow_ages => cs_age::load(self.context.device.clone()),
+                Layer::Age => cs_age_wide::load(self.context.device.clone()),
+                Layer::Heat => cs_heat::load(self.context.device.clone()),
+            }
+            .expect("failed to create shader module");
+            self.layer_pipelines[index] =
+                Some(Self::pipeline_from_shader(self.context.clone(), shader));
+        }
+
+        let extent = [self.size.0 as u32, self.size.1 as u32, 1];
+        let values = self.to_states().into_iter().map(|state| layer.start(state));
+        self.layers[index] = Some(match layer {
+            Layer::Age if narrow_ages => self.context.image_from_iter_with_format(
+                extent,
+                Format::R16_UINT,
+                values.map(|value| value as u16),
+            ),
+            Layer::Age | Layer::Heat => {
+                self.context
+                    .image_from_iter_with_format(extent, Format::R32_UINT, values)
+            }
+        });
+    }
+
+    fn layer(&self, layer: Layer) -> Option<Vec<u32>> {
+        let values = self.layers[layer as usize].as_ref()?;
+        Some(match layer {
+            Layer::Age if values.format() == Format::R16_UINT => {
+                let buffer_content = self.context.texels_from_image::<u16>(values);
+                let binding = buffer_content.read().unwrap();
+                binding.iter().map(|value| *value as u32).collect()
+            }
+            Layer::Age | Layer::Heat => {
+                let buffer_content = self.context.texels_from_image::<u32>(values);
+                let binding = buffer_content.read().unwrap();
+                binding.to_vec()
+            }
+        })
+    }
+
+    fn size(&self) -> usize {
+        self.size.0
+    }
+
+    fn generation(&self) -> i64 {
+        self.generation
+    }
+
+    fn to_next(&self) -> Box<dyn Gol> {
+        Box::new(self.next())
+    }
+
+    // Running the rule with the two generations swapped goes backwards.
+    fn step_back(&self) -> Option<Box<dyn Gol>> {
+        let mut swapped = self.with_image(self.previous.clone()?);
+        swapped.previous = Some(self.game_state.clone());
+        // noise can't be undone, so it is left out going backwards, and
+        // tracked layers start over from the earlier state
+        swapped.noise = None;
+        swapped.layers = Default::default();
+        let mut back = swapped.next();
+        std::mem::swap(&mut back.game_state, back.previous.as_mut().unwrap());
+        back.noise = self.noise;
+        back.generation = self.generation - 1;
+        for layer in Layer::ALL {
+            if self.layers[layer as usize].is_some() {
+                back.track(layer, true);
             }
         }
+        Some(Box::new(back))
+    }
+
+    fn print(&self) {
+        print!("{}", self.to_text());
+    }
+
+    fn copy_region(&self, region: Region) -> Pattern {
+        let mut pattern = Pattern::empty(region.width, region.height);
+        if pattern.cells.is_empty() {
+            return pattern;
+        }
+        let content = self.context.read_image_region(&self.game_state, region);
+        for (cell, value) in pattern.cells.iter_mut().zip(content) {
+            *cell = self.rule.alive(value);
+        }
+        pattern
+    }
+
+    // Only the pasted region crosses to the host and back, the rest of the
+    // board is copied on the device.
+    fn paste(&self, pattern: &Pattern, x: usize, y: usize, mode: PasteMode) -> Box<dyn Gol> {
+        let next_state = self.context.image_from_image(&self.game_state);
+        if !pattern.cells.is_empty() {
+            let region = Region {
+                x,
+                y,
+                width: pattern.width,
+                height: pattern.height,
+            };
+            let states: Vec<u8> = match mode {
+                PasteMode::Copy => pattern.cells.iter().map(|alive| *alive as u8).collect(),
+                _ => self
+                    .context
+                    .read_image_region(&self.game_state, region)
+                    .into_iter()
+                    .zip(&pattern.cells)
+                    .map(|(state, pasted)| mode.combine_state(state, *pasted))
+                    .collect(),
+            };
+            self.context
+                .write_image_region(&next_state, region, states.into_iter());
+        }
+        Box::new(self.with_image(next_state))
     }
 }
 
@@ -197,6 +757,15 @@ mod cs {
 
             layout(set = 0, binding = 0) uniform game_params {
                 uvec2 game_size;
+                // boards can be split into tiles that each wrap on their own
+                uvec2 tile_size;
+                // bit n is set when n live neighbours give birth or survival
+                uint birth;
+                uint survival;
+                // more than 2 for Generations rules, the states after 1 are dying
+                uint states;
+                // 8 for Moore, 4 for von Neumann and 6 for hexagonal
+                uint neighbours;
             } params;
 
             layout(set = 0, binding = 1, r8ui) uniform readonly uimage2D src;
@@ -210,9 +779,9 @@ mod cs {
                 return ret;
             }
 
-            uint is_alive(ivec2 coords)
+            uint is_alive(ivec2 tile_origin, ivec2 coords)
             {
-                return imageLoad(src, ivec2(coords % params.game_size)).x;
+                return uint(imageLoad(src, tile_origin + ivec2(coords % params.tile_size)).x == 1);
             }
 
             void main() {
@@ -221,32 +790,804 @@ mod cs {
                     return;
                 }
 
-                ivec2 id = ivec2(params.game_size + gl_GlobalInvocationID.xy);
                 ivec2 id_abs = ivec2(gl_GlobalInvocationID.xy);
+                ivec2 tile_origin = ivec2((gl_GlobalInvocationID.xy / params.tile_size) * params.tile_size);
+                ivec2 id = ivec2(params.tile_size) + id_abs - tile_origin;
+
+                // hexagonal leaves out the NE and SW corners, von Neumann all of them
+                uint corners = uint(params.neighbours == 8);
+                uint hex_corners = uint(params.neighbours != 4);
 
                 uint total = 0;
-                total += is_alive(id + ivec2(-1, -1));
-                total += is_alive(id + ivec2(0, -1));
-                total += is_alive(id + ivec2(1, -1));
+                total += hex_corners * is_alive(tile_origin, id + ivec2(-1, -1));
+                total += is_alive(tile_origin, id + ivec2(0, -1));
+                total += corners * is_alive(tile_origin, id + ivec2(1, -1));
 
-                total += is_alive(id + ivec2(-1, 0));
+                total += is_alive(tile_origin, id + ivec2(-1, 0));
                 // skip self
-                total += is_alive(id + ivec2(1, 0));
+                total += is_alive(tile_origin, id + ivec2(1, 0));
 
-                total += is_alive(id + ivec2(-1, 1));
-                total += is_alive(id + ivec2(0, 1));
-                total += is_alive(id + ivec2(1, 1));
+                total += corners * is_alive(tile_origin, id + ivec2(-1, 1));
+                total += is_alive(tile_origin, id + ivec2(0, 1));
+                total += hex_corners * is_alive(tile_origin, id + ivec2(1, 1));
 
-                bool this_is_alive = (imageLoad(src, id_abs).x > 0);
-                bool this_stays_alive = false;
-                if(this_is_alive)
+                uint state = imageLoad(src, id_abs).x;
+                uint next;
+                if(state == 0)
                 {
-                    if(total == 2 || total == 3) this_stays_alive = true;
+                    next = (params.birth >> total) & 1;
+                } else if(state == 1 && ((params.survival >> total) & 1) == 1) {
+                    next = 1;
                 } else {
-                    if(total == 3) this_stays_alive = true;
+                    next = (state + 1) % params.states;
+                }
+
+                imageStore(dest, id_abs, uvec4(next));
+            }
+        ",
+    }
+}
+
+// Isotropic rules, looking the 3x3 block up in the rule's 512 entry table.
+mod cs_isotropic {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform game_params {
+                uvec2 game_size;
+                // boards can be split into tiles that each wrap on their own
+                uvec2 tile_size;
+                uint states;
+            } params;
+
+            layout(set = 0, binding = 1, r8ui) uniform readonly uimage2D src;
+
+            layout(set = 0, binding = 2, r8ui) uniform writeonly uimage2D dest;
+
+            // 1 where the 3x3 index gives a live cell, see rules::isotropic
+            layout(set = 0, binding = 3) readonly buffer lookup {
+                uint alive[];
+            } table;
+
+            uint is_alive(ivec2 tile_origin, ivec2 coords)
+            {
+                return uint(imageLoad(src, tile_origin + ivec2(coords % params.tile_size)).x == 1);
+            }
+
+            void main() {
+                if(gl_GlobalInvocationID.x >= params.game_size.x || gl_GlobalInvocationID.y >= params.game_size.y)
+                {
+                    return;
+                }
+
+                ivec2 id_abs = ivec2(gl_GlobalInvocationID.xy);
+                ivec2 tile_origin = ivec2((gl_GlobalInvocationID.xy / params.tile_size) * params.tile_size);
+                ivec2 id = ivec2(params.tile_size) + id_abs - tile_origin;
+
+                uint index = 0;
+                for(int y = -1; y <= 1; y++)
+                {
+                    for(int x = -1; x <= 1; x++)
+                    {
+                        index |= is_alive(tile_origin, id + ivec2(x, y)) << ((y + 1) * 3 + x + 1);
+                    }
                 }
 
-                imageStore(dest, id_abs, uvec4(this_stays_alive ? 1 : 0));
+                uint state = imageLoad(src, id_abs).x;
+                uint next;
+                if(state <= 1 && table.alive[index] == 1)
+                {
+                    next = 1;
+                }
+                else if(state == 0)
+                {
+                    next = 0;
+                }
+                else
+                {
+                    next = (state + 1) % params.states;
+                }
+                imageStore(dest, id_abs, uvec4(next));
+            }
+        ",
+    }
+}
+
+// Larger than Life, counting the live cells in each span of offsets from the
+// spans buffer.
+mod cs_larger {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform game_params {
+                uvec2 game_size;
+                // boards can be split into tiles that each wrap on their own
+                uvec2 tile_size;
+                uint states;
+                // 1 when the cell itself is counted
+                uint middle;
+                uvec2 birth;
+                uvec2 survival;
+                uint span_count;
+            } params;
+
+            layout(set = 0, binding = 1, r8ui) uniform readonly uimage2D src;
+
+            layout(set = 0, binding = 2, r8ui) uniform writeonly uimage2D dest;
+
+            // top, bottom, left and right offsets of each span, inclusive
+            layout(set = 0, binding = 3) readonly buffer neighbourhood {
+                ivec4 spans[];
+            } neighbourhood;
+
+            uint is_alive(ivec2 tile_origin, ivec2 coords)
+            {
+                ivec2 wrapped = ivec2(uvec2(coords) % params.tile_size);
+                return uint(imageLoad(src, tile_origin + wrapped).x == 1);
+            }
+
+            void main() {
+                if(gl_GlobalInvocationID.x >= params.game_size.x || gl_GlobalInvocationID.y >= params.game_size.y)
+                {
+                    return;
+                }
+
+                ivec2 id_abs = ivec2(gl_GlobalInvocationID.xy);
+                ivec2 tile_origin = ivec2((gl_GlobalInvocationID.xy / params.tile_size) * params.tile_size);
+                // far enough from 0 that no offset makes it negative
+                ivec2 id = ivec2(params.tile_size * 512) + id_abs - tile_origin;
+
+                uint total = 0;
+                for(uint i = 0; i < params.span_count; i++)
+                {
+                    ivec4 span = neighbourhood.spans[i];
+                    for(int y = span.x; y <= span.y; y++)
+                    {
+                        for(int x = span.z; x <= span.w; x++)
+                        {
+                            total += is_alive(tile_origin, id + ivec2(x, y));
+                        }
+                    }
+                }
+
+                uint state = imageLoad(src, id_abs).x;
+                if(params.middle == 0)
+                {
+                    total -= uint(state == 1);
+                }
+
+                uint next;
+                if(state == 0)
+                {
+                    next = uint(total >= params.birth.x && total <= params.birth.y);
+                }
+                else if(state == 1 && total >= params.survival.x && total <= params.survival.y)
+                {
+                    next = 1;
+                }
+                else
+                {
+                    next = (state + 1) % params.states;
+                }
+                imageStore(dest, id_abs, uvec4(next));
+            }
+        ",
+    }
+}
+
+// Golly style rule tables, see rules::table::RuleTable for the layout of the
+// table buffer.
+mod cs_table {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform game_params {
+                uvec2 game_size;
+                // boards can be split into tiles that each wrap on their own
+                uvec2 tile_size;
+                uint states;
+                // 32 bit words per state and position in the masks
+                uint words;
+                // 8 for Moore, 4 for von Neumann and 6 for hexagonal
+                uint neighbours;
+                // where the transition outputs start in the table
+                uint outputs;
+            } params;
+
+            layout(set = 0, binding = 1, r8ui) uniform readonly uimage2D src;
+
+            layout(set = 0, binding = 2, r8ui) uniform writeonly uimage2D dest;
+
+            layout(set = 0, binding = 3) readonly buffer rule_table {
+                uint data[];
+            } table;
+
+            // clockwise from north like rule tables list them
+            const ivec2 moore[8] = ivec2[8](
+                ivec2(0, -1), ivec2(1, -1), ivec2(1, 0), ivec2(1, 1),
+                ivec2(0, 1), ivec2(-1, 1), ivec2(-1, 0), ivec2(-1, -1)
+            );
+            const ivec2 von_neumann[4] = ivec2[4](
+                ivec2(0, -1), ivec2(1, 0), ivec2(0, 1), ivec2(-1, 0)
+            );
+            const ivec2 hexagonal[6] = ivec2[6](
+                ivec2(0, -1), ivec2(1, 0), ivec2(1, 1), ivec2(0, 1), ivec2(-1, 0), ivec2(-1, -1)
+            );
+
+            ivec2 neighbour_offset(uint i)
+            {
+                if(params.neighbours == 8) return moore[i];
+                if(params.neighbours == 4) return von_neumann[i];
+                return hexagonal[i];
+            }
+
+            uint state_at(ivec2 tile_origin, ivec2 coords)
+            {
+                return imageLoad(src, tile_origin + ivec2(coords % params.tile_size)).x;
+            }
+
+            void main() {
+                if(gl_GlobalInvocationID.x >= params.game_size.x || gl_GlobalInvocationID.y >= params.game_size.y)
+                {
+                    return;
+                }
+
+                ivec2 id_abs = ivec2(gl_GlobalInvocationID.xy);
+                ivec2 tile_origin = ivec2((gl_GlobalInvocationID.xy / params.tile_size) * params.tile_size);
+                ivec2 id = ivec2(params.tile_size) + id_abs - tile_origin;
+
+                // the cell itself, then its neighbours
+                uint cells[9];
+                cells[0] = imageLoad(src, id_abs).x;
+                bool known = cells[0] < params.states;
+                for(uint i = 0; i < params.neighbours; i++)
+                {
+                    cells[i + 1] = state_at(tile_origin, id + neighbour_offset(i));
+                    known = known && cells[i + 1] < params.states;
+                }
+
+                // first transition accepting every position wins
+                uint next = cells[0];
+                for(uint word = 0; known && word < params.words; word++)
+                {
+                    uint matches = 0xffffffffu;
+                    for(uint position = 0; position <= params.neighbours; position++)
+                    {
+                        matches &= table.data[(position * params.states + cells[position]) * params.words + word];
+                    }
+                    if(matches != 0)
+                    {
+                        next = table.data[params.outputs + word * 32 + uint(findLSB(matches))];
+                        break;
+                    }
+                }
+
+                imageStore(dest, id_abs, uvec4(next));
+            }
+        ",
+    }
+}
+
+// Margolus block rules, turning the 2x2 block holding the cell with the
+// blocks buffer.
+mod cs_margolus {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform game_params {
+                uvec2 game_size;
+                // boards can be split into tiles that each wrap on their own
+                uvec2 tile_size;
+                // 1 when the blocks start at odd coordinates
+                uint phase;
+            } params;
+
+            layout(set = 0, binding = 1, r8ui) uniform readonly uimage2D src;
+
+            layout(set = 0, binding = 2, r8ui) uniform writeonly uimage2D dest;
+
+            // the block each block turns into, see rules::margolus
+            layout(set = 0, binding = 3) readonly buffer block_rule {
+                uint blocks[16];
+            } rule;
+
+            uint is_alive(ivec2 tile_origin, ivec2 coords)
+            {
+                return uint(imageLoad(src, tile_origin + ivec2(coords % params.tile_size)).x == 1);
+            }
+
+            void main() {
+                if(gl_GlobalInvocationID.x >= params.game_size.x || gl_GlobalInvocationID.y >= params.game_size.y)
+                {
+                    return;
+                }
+
+                ivec2 id_abs = ivec2(gl_GlobalInvocationID.xy);
+                ivec2 tile_origin = ivec2((gl_GlobalInvocationID.xy / params.tile_size) * params.tile_size);
+                ivec2 id = ivec2(params.tile_size) + id_abs - tile_origin;
+
+                // the cell's place in its block
+                ivec2 position = (id + int(params.phase)) % ivec2(2);
+                ivec2 corner = id - position;
+
+                uint block = 0;
+                block |= is_alive(tile_origin, corner);
+                block |= is_alive(tile_origin, corner + ivec2(1, 0)) << 1;
+                block |= is_alive(tile_origin, corner + ivec2(0, 1)) << 2;
+                block |= is_alive(tile_origin, corner + ivec2(1, 1)) << 3;
+
+                uint next = (rule.blocks[block] >> (position.y * 2 + position.x)) & 1;
+                imageStore(dest, id_abs, uvec4(next));
+            }
+        ",
+    }
+}
+
+// Life-like rules XORed with the generation before, which is bound in place of
+// a rule buffer.
+mod cs_second_order {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform game_params {
+                uvec2 game_size;
+                // boards can be split into tiles that each wrap on their own
+                uvec2 tile_size;
+                // bit n is set when n live neighbours give birth or survival
+                uint birth;
+                uint survival;
+                uint states;
+                // 8 for Moore, 4 for von Neumann and 6 for hexagonal
+                uint neighbours;
+            } params;
+
+            layout(set = 0, binding = 1, r8ui) uniform readonly uimage2D src;
+
+            layout(set = 0, binding = 2, r8ui) uniform writeonly uimage2D dest;
+
+            layout(set = 0, binding = 3, r8ui) uniform readonly uimage2D before;
+
+            uint is_alive(ivec2 tile_origin, ivec2 coords)
+            {
+                return uint(imageLoad(src, tile_origin + ivec2(coords % params.tile_size)).x == 1);
+            }
+
+            void main() {
+                if(gl_GlobalInvocationID.x >= params.game_size.x || gl_GlobalInvocationID.y >= params.game_size.y)
+                {
+                    return;
+                }
+
+                ivec2 id_abs = ivec2(gl_GlobalInvocationID.xy);
+                ivec2 tile_origin = ivec2((gl_GlobalInvocationID.xy / params.tile_size) * params.tile_size);
+                ivec2 id = ivec2(params.tile_size) + id_abs - tile_origin;
+
+                // hexagonal leaves out the NE and SW corners, von Neumann all of them
+                uint corners = uint(params.neighbours == 8);
+                uint hex_corners = uint(params.neighbours != 4);
+
+                uint total = 0;
+                total += hex_corners * is_alive(tile_origin, id + ivec2(-1, -1));
+                total += is_alive(tile_origin, id + ivec2(0, -1));
+                total += corners * is_alive(tile_origin, id + ivec2(1, -1));
+
+                total += is_alive(tile_origin, id + ivec2(-1, 0));
+                total += is_alive(tile_origin, id + ivec2(1, 0));
+
+                total += corners * is_alive(tile_origin, id + ivec2(-1, 1));
+                total += is_alive(tile_origin, id + ivec2(0, 1));
+                total += hex_corners * is_alive(tile_origin, id + ivec2(1, 1));
+
+                uint counts = imageLoad(src, id_abs).x == 1 ? params.survival : params.birth;
+                uint alive = (counts >> total) & 1;
+                uint alive_before = uint(imageLoad(before, id_abs).x == 1);
+
+                imageStore(dest, id_abs, uvec4(alive ^ alive_before));
+            }
+        ",
+    }
+}
+
+// Life-like rules whose births and survivals only happen when the cell's
+// random number is under their chance. hash is rules::stochastic::hash.
+mod cs_stochastic {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform game_params {
+                uvec2 game_size;
+                // boards can be split into tiles that each wrap on their own
+                uvec2 tile_size;
+                // bit n is set when n live neighbours give birth or survival
+                uint birth;
+                uint survival;
+                uint states;
+                // 8 for Moore, 4 for von Neumann and 6 for hexagonal
+                uint neighbours;
+                uint seed;
+                uint generation;
+                // chances out of 2^24
+                uint birth_chance;
+                uint survival_chance;
+            } params;
+
+            layout(set = 0, binding = 1, r8ui) uniform readonly uimage2D src;
+
+            layout(set = 0, binding = 2, r8ui) uniform writeonly uimage2D dest;
+
+            uint hash(uint x)
+            {
+                x ^= x >> 16;
+                x *= 0x7feb352du;
+                x ^= x >> 15;
+                x *= 0x846ca68bu;
+                x ^= x >> 16;
+                return x;
+            }
+
+            uint is_alive(ivec2 tile_origin, ivec2 coords)
+            {
+                return uint(imageLoad(src, tile_origin + ivec2(coords % params.tile_size)).x == 1);
+            }
+
+            void main() {
+                if(gl_GlobalInvocationID.x >= params.game_size.x || gl_GlobalInvocationID.y >= params.game_size.y)
+                {
+                    return;
+                }
+
+                ivec2 id_abs = ivec2(gl_GlobalInvocationID.xy);
+                ivec2 tile_origin = ivec2((gl_GlobalInvocationID.xy / params.tile_size) * params.tile_size);
+                ivec2 id = ivec2(params.tile_size) + id_abs - tile_origin;
+
+                // hexagonal leaves out the NE and SW corners, von Neumann all of them
+                uint corners = uint(params.neighbours == 8);
+                uint hex_corners = uint(params.neighbours != 4);
+
+                uint total = 0;
+                total += hex_corners * is_alive(tile_origin, id + ivec2(-1, -1));
+                total += is_alive(tile_origin, id + ivec2(0, -1));
+                total += corners * is_alive(tile_origin, id + ivec2(1, -1));
+
+                total += is_alive(tile_origin, id + ivec2(-1, 0));
+                total += is_alive(tile_origin, id + ivec2(1, 0));
+
+                total += corners * is_alive(tile_origin, id + ivec2(-1, 1));
+                total += is_alive(tile_origin, id + ivec2(0, 1));
+                total += hex_corners * is_alive(tile_origin, id + ivec2(1, 1));
+
+                uint cell = gl_GlobalInvocationID.y * params.game_size.x + gl_GlobalInvocationID.x;
+                uint random = hash(hash(hash(params.seed) ^ params.generation) ^ cell);
+
+                uint state = imageLoad(src, id_abs).x;
+                uint next;
+                if(state == 0)
+                {
+                    next = ((params.birth >> total) & 1) * uint((random >> 8) < params.birth_chance);
+                } else if(state == 1 && ((params.survival >> total) & 1) == 1) {
+                    next = (random >> 8) < params.survival_chance ? 1 : 2 % params.states;
+                } else {
+                    next = (state + 1) % params.states;
+                }
+
+                imageStore(dest, id_abs, uvec4(next));
+            }
+        ",
+    }
+}
+
+// Noise, flipping the cells whose random number is under the fraction in
+// place after the rule's pass.
+mod cs_noise {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform noise_params {
+                uvec2 game_size;
+                uint seed;
+                // the generation the board was computed from
+                uint generation;
+                // out of 2^24
+                uint fraction;
+            } params;
+
+            layout(set = 0, binding = 1, r8ui) uniform uimage2D board;
+
+            uint hash(uint x)
+            {
+                x ^= x >> 16;
+                x *= 0x7feb352du;
+                x ^= x >> 15;
+                x *= 0x846ca68bu;
+                x ^= x >> 16;
+                return x;
+            }
+
+            void main() {
+                if(gl_GlobalInvocationID.x >= params.game_size.x || gl_GlobalInvocationID.y >= params.game_size.y)
+                {
+                    return;
+                }
+
+                ivec2 id = ivec2(gl_GlobalInvocationID.xy);
+                uint cell = gl_GlobalInvocationID.y * params.game_size.x + gl_GlobalInvocationID.x;
+                uint random = hash(hash(hash(params.seed) ^ params.generation) ^ cell);
+                if((random >> 8) < params.fraction)
+                {
+                    imageStore(board, id, uvec4(uint(imageLoad(board, id).x != 1)));
+                }
+            }
+        ",
+    }
+}
+
+// Age layer, counting up while the cell stays alive in the new board.
+mod cs_age {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform layer_params {
+                uvec2 game_size;
+            } params;
+
+            layout(set = 0, binding = 2, r8ui) uniform readonly uimage2D after;
+
+            layout(set = 0, binding = 3, r16ui) uniform readonly uimage2D age;
+
+            layout(set = 0, binding = 4, r16ui) uniform writeonly uimage2D next_age;
+
+            void main() {
+                if(gl_GlobalInvocationID.x >= params.game_size.x || gl_GlobalInvocationID.y >= params.game_size.y)
+                {
+                    return;
+                }
+
+                ivec2 id = ivec2(gl_GlobalInvocationID.xy);
+                uint next = 0;
+                if(imageLoad(after, id).x == 1)
+                {
+                    next = min(imageLoad(age, id).x + 1, 65535u);
+                }
+                imageStore(next_age, id, uvec4(next));
+            }
+        ",
+    }
+}
+
+// The age layer for devices without 16 bit storage images, in R32_UINT
+// but still stopping at the same maximum.
+mod cs_age_wide {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform layer_params {
+                uvec2 game_size;
+            } params;
+
+            layout(set = 0, binding = 2, r8ui) uniform readonly uimage2D after;
+
+            layout(set = 0, binding = 3, r32ui) uniform readonly uimage2D age;
+
+            layout(set = 0, binding = 4, r32ui) uniform writeonly uimage2D next_age;
+
+            void main() {
+                if(gl_GlobalInvocationID.x >= params.game_size.x || gl_GlobalInvocationID.y >= params.game_size.y)
+                {
+                    return;
+                }
+
+                ivec2 id = ivec2(gl_GlobalInvocationID.xy);
+                uint next = 0;
+                if(imageLoad(after, id).x == 1)
+                {
+                    next = min(imageLoad(age, id).x + 1, 65535u);
+                }
+                imageStore(next_age, id, uvec4(next));
+            }
+        ",
+    }
+}
+
+// Heat layer, counting the cells whose state changed between the boards.
+mod cs_heat {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform layer_params {
+                uvec2 game_size;
+            } params;
+
+            layout(set = 0, binding = 1, r8ui) uniform readonly uimage2D before;
+
+            layout(set = 0, binding = 2, r8ui) uniform readonly uimage2D after;
+
+            layout(set = 0, binding = 3, r32ui) uniform readonly uimage2D heat;
+
+            layout(set = 0, binding = 4, r32ui) uniform writeonly uimage2D next_heat;
+
+            void main() {
+                if(gl_GlobalInvocationID.x >= params.game_size.x || gl_GlobalInvocationID.y >= params.game_size.y)
+                {
+                    return;
+                }
+
+                ivec2 id = ivec2(gl_GlobalInvocationID.xy);
+                uint value = imageLoad(heat, id).x;
+                uint changed = uint(imageLoad(before, id).x != imageLoad(after, id).x);
+                if(value != 0xffffffffu)
+                {
+                    value += changed;
+                }
+                imageStore(next_heat, id, uvec4(value));
+            }
+        ",
+    }
+}
+
+// History rules, see rules::history for the states. Odd states are alive.
+mod cs_history {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform game_params {
+                uvec2 game_size;
+                // boards can be split into tiles that each wrap on their own
+                uvec2 tile_size;
+                // bit n is set when n live neighbours give birth or survival
+                uint birth;
+                uint survival;
+                uint states;
+                // 8 for Moore, 4 for von Neumann and 6 for hexagonal
+                uint neighbours;
+            } params;
+
+            layout(set = 0, binding = 1, r8ui) uniform readonly uimage2D src;
+
+            layout(set = 0, binding = 2, r8ui) uniform writeonly uimage2D dest;
+
+            const uint HISTORY = 2;
+            const uint MARKED_ON = 3;
+            const uint MARKED_OFF = 4;
+            const uint BOUNDARY = 6;
+
+            uint is_alive(ivec2 tile_origin, ivec2 coords)
+            {
+                return imageLoad(src, tile_origin + ivec2(coords % params.tile_size)).x & 1;
+            }
+
+            void main() {
+                if(gl_GlobalInvocationID.x >= params.game_size.x || gl_GlobalInvocationID.y >= params.game_size.y)
+                {
+                    return;
+                }
+
+                ivec2 id_abs = ivec2(gl_GlobalInvocationID.xy);
+                ivec2 tile_origin = ivec2((gl_GlobalInvocationID.xy / params.tile_size) * params.tile_size);
+                ivec2 id = ivec2(params.tile_size) + id_abs - tile_origin;
+
+                // hexagonal leaves out the NE and SW corners, von Neumann all of them
+                uint corners = uint(params.neighbours == 8);
+                uint hex_corners = uint(params.neighbours != 4);
+
+                uint total = 0;
+                total += hex_corners * is_alive(tile_origin, id + ivec2(-1, -1));
+                total += is_alive(tile_origin, id + ivec2(0, -1));
+                total += corners * is_alive(tile_origin, id + ivec2(1, -1));
+
+                total += is_alive(tile_origin, id + ivec2(-1, 0));
+                total += is_alive(tile_origin, id + ivec2(1, 0));
+
+                total += corners * is_alive(tile_origin, id + ivec2(-1, 1));
+                total += is_alive(tile_origin, id + ivec2(0, 1));
+                total += hex_corners * is_alive(tile_origin, id + ivec2(1, 1));
+
+                uint state = imageLoad(src, id_abs).x;
+                bool was_alive = (state & 1) == 1;
+                uint counts = was_alive ? params.survival : params.birth;
+                bool alive = ((counts >> total) & 1) == 1;
+
+                uint next = state;
+                if(state != BOUNDARY && was_alive && !alive)
+                {
+                    // marked and start cells keep a mark, the rest leave history
+                    next = state == 1 ? HISTORY : MARKED_OFF;
+                }
+                else if(state != BOUNDARY && !was_alive && alive)
+                {
+                    next = state == MARKED_OFF ? MARKED_ON : 1;
+                }
+
+                imageStore(dest, id_abs, uvec4(next));
+            }
+        ",
+    }
+}
+
+// Counts the live cells of each tile into its slot of the counts buffer.
+mod cs_population {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform population_params {
+                uint tile_size;
+                uint tiles_per_row;
+                // 1 when every odd state is alive
+                uint history;
+            } params;
+
+            layout(set = 0, binding = 1, r8ui) uniform readonly uimage2D board;
+
+            layout(set = 0, binding = 2) buffer populations {
+                uint counts[];
+            } populations;
+
+            // one invocation per tile, adding up its cells
+            void main() {
+                if(gl_GlobalInvocationID.x >= params.tiles_per_row || gl_GlobalInvocationID.y >= params.tiles_per_row)
+                {
+                    return;
+                }
+
+                ivec2 corner = ivec2(gl_GlobalInvocationID.xy * params.tile_size);
+                int tile_size = int(params.tile_size);
+                uint count = 0;
+                for(int y = 0; y < tile_size; y++)
+                {
+                    for(int x = 0; x < tile_size; x++)
+                    {
+                        uint state = imageLoad(board, corner + ivec2(x, y)).x;
+                        count += params.history == 1 ? state % 2 : uint(state == 1);
+                    }
+                }
+                populations.counts[gl_GlobalInvocationID.y * params.tiles_per_row + gl_GlobalInvocationID.x] = count;
             }
         ",
     }
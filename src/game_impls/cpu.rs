@@ -1,64 +1,218 @@
-use crate::Gol;
+use crate::layers::Layer;
+use crate::patterns::{PasteMode, Pattern};
+use crate::rules::history::History;
+use crate::rules::isotropic::Isotropic;
+use crate::rules::larger::{LargerThanLife, Span};
+use crate::rules::margolus::Margolus;
+use crate::rules::stochastic::{self, Noise};
+use crate::rules::table::RuleTable;
+use crate::rules::{BoardSizeError, Rule};
+use crate::{Gol, Region};
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread;
 
 pub struct GameState {
     game_size: usize,
-    state: Vec<bool>,
+    state: Vec<u8>,
+    rule: Rule,
+    noise: Option<Noise>,
+    // counters of the tracked layers, indexed by Layer
+    layers: [Option<Vec<u32>>; 2],
+    // the generation before this one, kept for second order rules
+    previous: Option<Vec<u8>>,
+    // generations since the board was made, Margolus blocks and stochastic
+    // draws depend on it
+    generation: i64,
 }
 
 impl Gol for GameState {
-    fn from_slice(size: usize, slice: &[bool]) -> GameState {
-        debug_assert!(size * size == slice.len());
+    fn from_states(size: usize, states: &[u8]) -> GameState {
+        debug_assert!(size * size == states.len());
         GameState {
             game_size: size,
-            state: slice.to_owned(),
+            state: states.to_owned(),
+            rule: Rule::default(),
+            noise: None,
+            layers: Default::default(),
+            previous: None,
+            generation: 0,
         }
     }
 
-    fn to_vec(&self) -> Vec<bool> {
+    fn to_states(&self) -> Vec<u8> {
         self.state.to_vec()
     }
 
-    fn to_next(&self) -> Box<dyn Gol> {
-        let mut next = GameState::new(self.game_size);
-        let size_as_i32: i32 = TryInto::<i32>::try_into(self.game_size).unwrap();
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
 
-        for (next, (i, _)) in next.state.iter_mut().zip(self.state.iter().enumerate()) {
-            *next = self.next_state_for(i, size_as_i32);
+    fn set_rule(&mut self, rule: Rule) -> Result<(), BoardSizeError> {
+        rule.check_size(self.game_size)?;
+        // the generation before the first one is empty
+        self.previous = match rule {
+            Rule::SecondOrder(_) => self
+                .previous
+                .take()
+                .or_else(|| Some(vec![0; self.state.len()])),
+            _ => None,
+        };
+        self.rule = rule;
+        Ok(())
+    }
+
+    fn noise(&self) -> Option<Noise> {
+        self.noise
+    }
+
+    fn set_noise(&mut self, noise: Option<Noise>) {
+        self.noise = noise;
+    }
+
+    fn track(&mut self, layer: Layer, on: bool) {
+        let values = &mut self.layers[layer as usize];
+        match (on, values.is_some()) {
+            (true, false) => {
+                *values = Some(self.state.iter().map(|state| layer.start(*state)).collect())
+            }
+            (false, _) => *values = None,
+            _ => {}
         }
+    }
+
+    fn layer(&self, layer: Layer) -> Option<Vec<u32>> {
+        self.layers[layer as usize].clone()
+    }
+
+    fn size(&self) -> usize {
+        self.game_size
+    }
 
-        Box::new(next)
+    fn generation(&self) -> i64 {
+        self.generation
+    }
+
+    fn to_next(&self) -> Box<dyn Gol> {
+        Box::new(self.next())
+    }
+
+    // Running the rule with the two generations swapped goes backwards.
+    fn step_back(&self) -> Option<Box<dyn Gol>> {
+        let swapped = GameState {
+            game_size: self.game_size,
+            state: self.previous.clone()?,
+            rule: self.rule.clone(),
+            // noise can't be undone, so it is left out going backwards, and
+            // tracked layers start over from the earlier state
+            noise: None,
+            layers: Default::default(),
+            previous: Some(self.state.clone()),
+            generation: self.generation,
+        };
+        let mut back = swapped.next();
+        std::mem::swap(&mut back.state, back.previous.as_mut().unwrap());
+        back.noise = self.noise;
+        back.generation = self.generation - 1;
+        for layer in Layer::ALL {
+            if self.layers[layer as usize].is_some() {
+                back.track(layer, true);
+            }
+        }
+        Some(Box::new(back))
     }
 
     fn print(&self) {
-        for (i, is_alive) in self.state.iter().enumerate() {
-            print!("{}", if *is_alive { "1" } else { "0" });
-            if (i + 1) % (self.game_size) == 0 {
-                println!();
+        print!("{}", self.to_text());
+    }
+
+    fn copy_region(&self, region: Region) -> Pattern {
+        let mut pattern = Pattern::empty(region.width, region.height);
+        for y in 0..region.height {
+            for x in 0..region.width {
+                pattern.cells[y * region.width + x] = self
+                    .rule
+                    .alive(self.state[region.index(self.game_size, x, y)]);
             }
         }
+        pattern
+    }
+
+    fn paste(&self, pattern: &Pattern, x: usize, y: usize, mode: PasteMode) -> Box<dyn Gol> {
+        let mut state = self.state.clone();
+        pattern.paste_states(self.game_size, &mut state, x, y, mode);
+        Box::new(GameState {
+            game_size: self.game_size,
+            state,
+            rule: self.rule.clone(),
+            noise: self.noise,
+            layers: self.layers.clone(),
+            previous: self.previous.clone(),
+            generation: self.generation,
+        })
     }
 }
 
 impl GameState {
-    fn new(size: usize) -> Self {
+    fn new(size: usize, rule: Rule) -> Self {
         GameState {
             game_size: size,
-            state: vec![false; size * size],
+            state: vec![0; size * size],
+            rule,
+            noise: None,
+            layers: Default::default(),
+            previous: None,
+            generation: 0,
+        }
+    }
+
+    fn next(&self) -> GameState {
+        let mut next = GameState::new(self.game_size, self.rule.clone());
+        next.noise = self.noise;
+        next.previous = self.previous.as_ref().map(|_| self.state.clone());
+        next.generation = self.generation + 1;
+        let size_as_i32: i32 = TryInto::<i32>::try_into(self.game_size).unwrap();
+        let summed_area = self.summed_area();
+
+        for (next, (i, _)) in next.state.iter_mut().zip(self.state.iter().enumerate()) {
+            *next = self.next_state_for(i, size_as_i32, summed_area.as_ref());
+        }
+        next.apply_noise(self.generation);
+        next.layers = self.next_layers(&next.state);
+
+        next
+    }
+
+    fn next_layers(&self, next: &[u8]) -> [Option<Vec<u32>>; 2] {
+        Layer::ALL.map(|layer| {
+            let values = self.layers[layer as usize].as_ref()?;
+            let cells = values.iter().zip(&self.state).zip(next);
+            Some(
+                cells
+                    .map(|((value, before), after)| layer.next(*value, *before, *after))
+                    .collect(),
+            )
+        })
+    }
+
+    // generation is the one the states were computed from
+    fn apply_noise(&mut self, generation: i64) {
+        if let Some(noise) = &self.noise {
+            for (i, state) in self.state.iter_mut().enumerate() {
+                *state = noise.apply(*state, generation, i);
+            }
         }
     }
     pub fn from_random(size: usize) -> Box<dyn Gol> {
-        let mut new_game = GameState::new(size);
+        let mut new_game = GameState::new(size, Rule::default());
         for field in &mut new_game.state {
-            *field = rand::random();
+            *field = rand::random::<u8>() & 1;
         }
         Box::new(new_game)
     }
 
     pub fn from_previous_parallel(previous: &GameState, threads: usize) -> GameState {
-        let next_state = vec![false; previous.game_size * previous.game_size];
+        let next_state = vec![0u8; previous.game_size * previous.game_size];
 
         let previous_arc = Arc::new(previous);
         let next_arc = Arc::new(Mutex::new(next_state));
@@ -67,6 +221,8 @@ impl GameState {
 
         let scope_next_arc = next_arc.clone();
         let size_as_i32: i32 = TryInto::<i32>::try_into(previous.game_size).unwrap();
+        let summed_area = previous.summed_area();
+        let summed_area = summed_area.as_ref();
 
         thread::scope(move |s| {
             let mut next_thread_offset = 0;
@@ -87,20 +243,28 @@ impl GameState {
                     let start = thread_offset;
                     let end = thread_offset + thread_slize_size;
                     for i in start..end {
-                        let alive = previous.next_state_for(i, size_as_i32);
+                        let next = previous.next_state_for(i, size_as_i32, summed_area);
                         {
                             let state = &mut thread_next_arc.lock().unwrap();
-                            state[i] = alive;
+                            state[i] = next;
                         }
                     }
                 });
             }
         });
 
-        GameState {
+        let mut next = GameState {
             game_size: previous.game_size,
             state: Arc::try_unwrap(next_arc).unwrap().into_inner().unwrap(),
-        }
+            rule: previous.rule.clone(),
+            noise: previous.noise,
+            layers: Default::default(),
+            previous: previous.previous.as_ref().map(|_| previous.state.clone()),
+            generation: previous.generation + 1,
+        };
+        next.apply_noise(previous.generation);
+        next.layers = previous.next_layers(&next.state);
+        next
     }
 
     fn coords_from_index(&self, i: usize) -> (i32, i32) {
@@ -110,27 +274,152 @@ impl GameState {
         (x, y)
     }
 
-    fn next_state_for(&self, i: usize, size_as_i32: i32) -> bool {
+    // Only larger than life rules need one.
+    fn summed_area(&self) -> Option<SummedArea> {
+        match &self.rule {
+            Rule::LargerThanLife(rule) => Some(SummedArea::new(self.game_size, rule, &self.state)),
+            _ => None,
+        }
+    }
+
+    fn next_state_for(&self, i: usize, size_as_i32: i32, summed_area: Option<&SummedArea>) -> u8 {
+        let rule = match &self.rule {
+            Rule::LifeLike(rule) | Rule::SecondOrder(rule) => rule,
+            Rule::Stochastic(rule) => &rule.rule,
+            Rule::History(rule) => &rule.rule,
+            Rule::LargerThanLife(rule) => {
+                let summed_area = summed_area.expect("larger than life needs a summed area");
+                let (x, y) = self.coords_from_index(i);
+                let mut total = summed_area.live_neighbours(x as usize, y as usize);
+                if !rule.middle {
+                    total -= (self.state[i] == 1) as u32;
+                }
+                return rule.next(self.state[i], total);
+            }
+            Rule::Isotropic(rule) => return self.next_state_isotropic(rule, i, size_as_i32),
+            Rule::Table(table) => return self.next_state_from_table(table, i, size_as_i32),
+            Rule::Margolus(rule) => return self.next_state_margolus(rule, i),
+        };
+
+        // every odd state is alive in history rules
+        let history = matches!(self.rule, Rule::History(_));
         let mut total = 0;
         let (this_x, this_y) = GameState::coords_from_index(self, i);
+        for (neighbor_x, neighbor_y) in rule.neighbourhood.offsets() {
+            let neighbor_x_abs = (this_x + neighbor_x).rem_euclid(size_as_i32) as usize;
+            let neighbor_y_abs = (this_y + neighbor_y).rem_euclid(size_as_i32) as usize;
+
+            let neighbor_idx_abs = neighbor_y_abs * self.game_size + neighbor_x_abs;
+            let state = self.state[neighbor_idx_abs];
+            if state == 1 || history && History::alive(state) {
+                total += 1;
+            }
+        }
+        match &self.rule {
+            Rule::SecondOrder(_) => {
+                let previous = self.previous.as_ref().map_or(0, |states| states[i]);
+                rule.next_second_order(previous, self.state[i], total)
+            }
+            Rule::Stochastic(rule) => {
+                let random = stochastic::random(rule.seed, self.generation as u32, i as u32);
+                rule.next(self.state[i], total, random)
+            }
+            Rule::History(rule) => rule.next(self.state[i], total),
+            _ => rule.next(self.state[i], total),
+        }
+    }
+
+    // Looks the live cells of the 3x3 block up in the rule's table.
+    fn next_state_isotropic(&self, rule: &Isotropic, i: usize, size_as_i32: i32) -> u8 {
+        let (this_x, this_y) = GameState::coords_from_index(self, i);
+        let mut index = 0;
         for neighbor_y in -1..=1 {
             for neighbor_x in -1..=1 {
-                if neighbor_x != 0 || neighbor_y != 0 {
-                    let neighbor_x_abs = (this_x + neighbor_x).rem_euclid(size_as_i32) as usize;
-                    let neighbor_y_abs = (this_y + neighbor_y).rem_euclid(size_as_i32) as usize;
-
-                    let neighbor_idx_abs = neighbor_y_abs * self.game_size + neighbor_x_abs;
-                    if self.state[neighbor_idx_abs] {
-                        total += 1;
-                    }
+                let neighbor_x_abs = (this_x + neighbor_x).rem_euclid(size_as_i32) as usize;
+                let neighbor_y_abs = (this_y + neighbor_y).rem_euclid(size_as_i32) as usize;
+                if self.state[neighbor_y_abs * self.game_size + neighbor_x_abs] == 1 {
+                    index |= 1 << ((neighbor_y + 1) * 3 + neighbor_x + 1);
                 }
             }
         }
-        // rules differ if the current cell is live or not
-        if self.state[i] {
-            total == 2 || total == 3
-        } else {
-            total == 3
+        rule.next(self.state[i], index)
+    }
+
+    // Turns the cell's block and picks the cell's place out of the new one.
+    fn next_state_margolus(&self, rule: &Margolus, i: usize) -> u8 {
+        let size = self.game_size;
+        let (x, x_position) = Margolus::block_start(i % size, size, self.generation);
+        let (y, y_position) = Margolus::block_start(i / size, size, self.generation);
+        let mut block = 0;
+        for (bit, (dx, dy)) in [(0, 0), (1, 0), (0, 1), (1, 1)].into_iter().enumerate() {
+            if self.state[(y + dy) % size * size + (x + dx) % size] == 1 {
+                block |= 1 << bit;
+            }
+        }
+        rule.next(block) >> (y_position * 2 + x_position) & 1
+    }
+
+    fn next_state_from_table(&self, table: &RuleTable, i: usize, size_as_i32: i32) -> u8 {
+        let (this_x, this_y) = GameState::coords_from_index(self, i);
+        let offsets = table.neighbourhood.offsets();
+        let mut cells = [0u8; 9];
+        cells[0] = self.state[i];
+        for (cell, (dx, dy)) in cells[1..].iter_mut().zip(offsets) {
+            let neighbor_x_abs = (this_x + dx).rem_euclid(size_as_i32) as usize;
+            let neighbor_y_abs = (this_y + dy).rem_euclid(size_as_i32) as usize;
+            *cell = self.state[neighbor_y_abs * self.game_size + neighbor_x_abs];
         }
+        table.next(&cells[..=offsets.len()])
+    }
+}
+
+// Live cells of the board padded with range wrapped cells on every side,
+// summed over each rectangle starting at its top left corner. Any rectangle
+// of neighbours can then be counted with four lookups.
+struct SummedArea {
+    range: usize,
+    // side of the padded board plus the row and column of zeroes
+    side: usize,
+    sums: Vec<u32>,
+    spans: Vec<Span>,
+}
+
+impl SummedArea {
+    fn new(size: usize, rule: &LargerThanLife, state: &[u8]) -> SummedArea {
+        let range = rule.range as usize;
+        let padded = size + 2 * range;
+        let side = padded + 1;
+        let mut sums = vec![0; side * side];
+
+        for y in 0..padded {
+            let state_y = (y as i64 - range as i64).rem_euclid(size as i64) as usize;
+            let mut row = 0;
+            for x in 0..padded {
+                let state_x = (x as i64 - range as i64).rem_euclid(size as i64) as usize;
+                row += (state[state_y * size + state_x] == 1) as u32;
+                sums[(y + 1) * side + x + 1] = sums[y * side + x + 1] + row;
+            }
+        }
+
+        SummedArea {
+            range,
+            side,
+            sums,
+            spans: rule.spans(),
+        }
+    }
+
+    // including the cell itself
+    fn live_neighbours(&self, x: usize, y: usize) -> u32 {
+        let at = |x: i32, y: i32| self.sums[y as usize * self.side + x as usize];
+        let (x, y) = ((x + self.range) as i32, (y + self.range) as i32);
+        self.spans
+            .iter()
+            .map(|span| {
+                let (top, bottom) = (y + span.top, y + span.bottom + 1);
+                let (left, right) = (x + span.left, x + span.right + 1);
+                at(right, bottom) + at(left, top) - at(right, top) - at(left, bottom)
+            })
+            .sum()
     }
 }
@@ -1,139 +1,441 @@
-use crate::Gol;
+use crate::layers::Layer;
+use crate::patterns::{PasteMode, Pattern};
+use crate::rules::history::History;
+use crate::rules::isotropic::Isotropic;
+use crate::rules::larger::{LargerThanLife, Span};
+use crate::rules::margolus::Margolus;
+use crate::rules::stochastic::{self, Noise};
+use crate::rules::table::RuleTable;
+use crate::rules::{BoardSizeError, Neighbourhood, Rule};
+use crate::{Gol, Region};
 use ndarray::prelude::*;
-use ndarray::Array;
+use ndarray::{Array, Zip};
 
 pub struct GameState {
-    state: Array<bool, Ix2>,
+    state: Array<u8, Ix2>,
     neighbor_offsets: Array<usize, Dim<[usize; 2]>>,
     boundaries: Array<usize, Dim<[usize; 2]>>,
+    rule: Rule,
+    noise: Option<Noise>,
+    // counters of the tracked layers, indexed by Layer
+    layers: [Option<Array<u32, Ix2>>; 2],
+    // the generation before this one, kept for second order rules
+    previous: Option<Array<u8, Ix2>>,
+    // generations since the board was made, Margolus blocks and stochastic
+    // draws depend on it
+    generation: i64,
 }
 
 impl Gol for GameState {
-    fn from_slice(size: usize, slice: &[bool]) -> GameState {
-        debug_assert!(size * size == slice.len());
-        let mut state: Array<bool, _> = Array::default((size, size).f());
+    fn from_states(size: usize, states: &[u8]) -> GameState {
+        debug_assert!(size * size == states.len());
+        let mut state: Array<u8, _> = Array::default((size, size).f());
 
-        for (src, dest) in slice.iter().zip(state.iter_mut()) {
+        for (src, dest) in states.iter().zip(state.iter_mut()) {
             *dest = *src;
         }
 
         GameState {
             state,
-            neighbor_offsets: Self::gen_neighbor_offsets(size, size),
-            boundaries: Self::gen_boundary(size, size),
+            neighbor_offsets: Self::gen_neighbor_offsets(size, Neighbourhood::Moore),
+            boundaries: Self::gen_boundary(size, Neighbourhood::Moore),
+            rule: Rule::default(),
+            noise: None,
+            layers: Default::default(),
+            previous: None,
+            generation: 0,
         }
     }
 
-    fn to_vec(&self) -> Vec<bool> {
+    fn to_states(&self) -> Vec<u8> {
         let dim = self.state.dim();
-        let mut vec = Vec::<bool>::with_capacity(dim.0 * dim.1);
+        let mut vec = Vec::<u8>::with_capacity(dim.0 * dim.1);
         for field in self.state.iter() {
             vec.push(*field);
         }
         vec
     }
 
-    fn to_next(&self) -> Box<dyn Gol> {
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    fn set_rule(&mut self, rule: Rule) -> Result<(), BoardSizeError> {
         let size = self.state.dim().0;
+        rule.check_size(size)?;
+        let neighbourhood = match &rule {
+            Rule::LifeLike(rule) | Rule::SecondOrder(rule) => rule.neighbourhood,
+            Rule::Stochastic(rule) => rule.rule.neighbourhood,
+            Rule::History(rule) => rule.rule.neighbourhood,
+            _ => Neighbourhood::Moore,
+        };
+        self.neighbor_offsets = Self::gen_neighbor_offsets(size, neighbourhood);
+        self.boundaries = Self::gen_boundary(size, neighbourhood);
+        // the generation before the first one is empty
+        self.previous = match rule {
+            Rule::SecondOrder(_) => self
+                .previous
+                .take()
+                .or_else(|| Some(Array::default((size, size).f()))),
+            _ => None,
+        };
+        self.rule = rule;
+        Ok(())
+    }
 
-        let mut next = GameState {
-            state: Array::<bool, _>::default((size, size).f()),
+    fn noise(&self) -> Option<Noise> {
+        self.noise
+    }
+
+    fn set_noise(&mut self, noise: Option<Noise>) {
+        self.noise = noise;
+    }
+
+    fn track(&mut self, layer: Layer, on: bool) {
+        let values = &mut self.layers[layer as usize];
+        match (on, values.is_some()) {
+            (true, false) => *values = Some(self.state.map(|state| layer.start(*state))),
+            (false, _) => *values = None,
+            _ => {}
+        }
+    }
+
+    fn layer(&self, layer: Layer) -> Option<Vec<u32>> {
+        let values = self.layers[layer as usize].as_ref()?;
+        Some(values.iter().copied().collect())
+    }
+
+    fn size(&self) -> usize {
+        self.state.dim().0
+    }
+
+    fn generation(&self) -> i64 {
+        self.generation
+    }
+
+    fn to_next(&self) -> Box<dyn Gol> {
+        Box::new(self.next())
+    }
+
+    // Running the rule with the two generations swapped goes backwards.
+    fn step_back(&self) -> Option<Box<dyn Gol>> {
+        let swapped = GameState {
+            state: self.previous.clone()?,
             neighbor_offsets: self.neighbor_offsets.to_owned(),
             boundaries: self.boundaries.to_owned(),
+            rule: self.rule.clone(),
+            // noise can't be undone, so it is left out going backwards, and
+            // tracked layers start over from the earlier state
+            noise: None,
+            layers: Default::default(),
+            previous: Some(self.state.clone()),
+            generation: self.generation,
         };
-
-        for (prev, next) in self.state.indexed_iter().zip(next.state.iter_mut()) {
-            *next = self.next_state_for(prev.0);
+        let mut back = swapped.next();
+        std::mem::swap(&mut back.state, back.previous.as_mut().unwrap());
+        back.noise = self.noise;
+        back.generation = self.generation - 1;
+        for layer in Layer::ALL {
+            if self.layers[layer as usize].is_some() {
+                back.track(layer, true);
+            }
         }
-
-        Box::new(next)
+        Some(Box::new(back))
     }
 
     fn print(&self) {
-        for (i, is_alive) in self.state.indexed_iter() {
-            print!("{}", if *is_alive { "1" } else { "0" });
-            if i.1 == self.state.dim().1 - 1 {
-                println!();
+        print!("{}", self.to_text());
+    }
+
+    fn copy_region(&self, region: Region) -> Pattern {
+        let size = self.state.dim().0;
+        let mut pattern = Pattern::empty(region.width, region.height);
+        for y in 0..region.height {
+            for x in 0..region.width {
+                pattern.cells[y * region.width + x] = self
+                    .rule
+                    .alive(self.state[[(region.y + y) % size, (region.x + x) % size]]);
             }
         }
+        pattern
+    }
+
+    fn paste(&self, pattern: &Pattern, x: usize, y: usize, mode: PasteMode) -> Box<dyn Gol> {
+        let size = self.state.dim().0;
+        let mut next = GameState {
+            state: self.state.clone(),
+            neighbor_offsets: self.neighbor_offsets.to_owned(),
+            boundaries: self.boundaries.to_owned(),
+            rule: self.rule.clone(),
+            noise: self.noise,
+            layers: self.layers.clone(),
+            previous: self.previous.clone(),
+            generation: self.generation,
+        };
+        for py in 0..pattern.height {
+            for px in 0..pattern.width {
+                let cell = &mut next.state[[(y + py) % size, (x + px) % size]];
+                *cell = mode.combine_state(*cell, pattern.is_alive(px, py));
+            }
+        }
+        Box::new(next)
     }
 }
 
 impl GameState {
     fn new(size: usize) -> Self {
         GameState {
-            state: Array::<bool, _>::default((size, size).f()),
-            neighbor_offsets: Self::gen_neighbor_offsets(size, size),
-            boundaries: Self::gen_boundary(size, size),
+            state: Array::<u8, _>::default((size, size).f()),
+            neighbor_offsets: Self::gen_neighbor_offsets(size, Neighbourhood::Moore),
+            boundaries: Self::gen_boundary(size, Neighbourhood::Moore),
+            rule: Rule::default(),
+            noise: None,
+            layers: Default::default(),
+            previous: None,
+            generation: 0,
+        }
+    }
+    fn next(&self) -> GameState {
+        let size = self.state.dim().0;
+
+        let mut next = GameState {
+            state: Array::<u8, _>::default((size, size).f()),
+            neighbor_offsets: self.neighbor_offsets.to_owned(),
+            boundaries: self.boundaries.to_owned(),
+            rule: self.rule.clone(),
+            noise: self.noise,
+            layers: Default::default(),
+            previous: self.previous.as_ref().map(|_| self.state.clone()),
+            generation: self.generation + 1,
+        };
+
+        if let Rule::LargerThanLife(rule) = &self.rule {
+            next.state = self.next_larger_than_life(rule);
+        } else {
+            for (prev, next) in self.state.indexed_iter().zip(next.state.iter_mut()) {
+                *next = self.next_state_for(prev.0);
+            }
+        }
+        if let Some(noise) = &self.noise {
+            for ((y, x), state) in next.state.indexed_iter_mut() {
+                *state = noise.apply(*state, self.generation, y * size + x);
+            }
         }
+        next.layers = Layer::ALL.map(|layer| {
+            let values = self.layers[layer as usize].as_ref()?;
+            Some(
+                Zip::from(values)
+                    .and(&self.state)
+                    .and(&next.state)
+                    .map_collect(|value, before, after| layer.next(*value, *before, *after)),
+            )
+        });
+
+        next
     }
+
     pub fn from_random(size: usize) -> Box<dyn Gol> {
         let mut new_game = GameState::new(size);
         for field in &mut new_game.state {
-            *field = rand::random();
+            *field = rand::random::<u8>() & 1;
         }
         Box::new(new_game)
     }
 
-    pub fn next_state_for(&self, coords: (usize, usize)) -> bool {
+    pub fn next_state_for(&self, coords: (usize, usize)) -> u8 {
+        let rule = match &self.rule {
+            Rule::LifeLike(rule) | Rule::SecondOrder(rule) => rule,
+            Rule::Stochastic(rule) => &rule.rule,
+            Rule::History(rule) => &rule.rule,
+            Rule::LargerThanLife(rule) => {
+                return self.next_state_larger_than_life(rule, coords);
+            }
+            Rule::Isotropic(rule) => return self.next_state_isotropic(rule, coords),
+            Rule::Table(table) => return self.next_state_from_table(table, coords),
+            Rule::Margolus(rule) => return self.next_state_margolus(rule, coords),
+        };
+
         let mut total = 0;
 
-        // This is ugly but turned out to be quite a lot faster than using a closure.
-        let mut neighbors = array![
-            [coords.0, coords.1],
-            [coords.0, coords.1],
-            [coords.0, coords.1],
-            [coords.0, coords.1],
-            [coords.0, coords.1],
-            [coords.0, coords.1],
-            [coords.0, coords.1],
-            [coords.0, coords.1],
-        ];
-
-        neighbors += &self.neighbor_offsets;
-        neighbors %= &self.boundaries;
-
-        for neighbor_abs in neighbors.rows() {
-            total += if self.state[(neighbor_abs[0], neighbor_abs[1])] {
+        if let Rule::LifeLike(rule) = &self.rule {
+            if rule.neighbourhood == Neighbourhood::Moore {
+                // This is ugly but turned out to be quite a lot faster than using a closure.
+                let mut neighbors = self.neighbor_offsets.to_owned();
+                neighbors += &array![coords.0, coords.1];
+                neighbors %= &self.boundaries;
+
+                for neighbor_abs in neighbors.rows() {
+                    total += if self.state[(neighbor_abs[0], neighbor_abs[1])] == 1 {
+                        1
+                    } else {
+                        0
+                    };
+                }
+
+                return rule.next(self.state[(coords.0, coords.1)], total);
+            }
+        }
+
+        // every odd state is alive in history rules
+        let history = matches!(self.rule, Rule::History(_));
+
+        // offsets are kept positive, so adding them and wrapping finds the neighbours
+        for (offset, boundary) in self
+            .neighbor_offsets
+            .rows()
+            .into_iter()
+            .zip(self.boundaries.rows())
+        {
+            let neighbor = self.state[(
+                (coords.0 + offset[0]) % boundary[0],
+                (coords.1 + offset[1]) % boundary[1],
+            )];
+            total += if neighbor == 1 || history && History::alive(neighbor) {
                 1
             } else {
                 0
             };
         }
 
-        // rules differ if the current cell is live or not
-        if self.state[(coords.0, coords.1)] {
-            total == 2 || total == 3
-        } else {
-            total == 3
-        }
-    }
-
-    fn gen_neighbor_offsets(x_size: usize, y_size: usize) -> Array<usize, Dim<[usize; 2]>> {
-        array![
-            [x_size - 1, y_size - 1],
-            [x_size - 1, y_size],
-            [x_size - 1, y_size + 1],
-            [x_size, y_size - 1],
-            // skip self [0, 0]
-            [x_size, y_size + 1],
-            [x_size + 1, y_size - 1],
-            [x_size + 1, y_size],
-            [x_size + 1, y_size + 1],
-        ]
-    }
-
-    fn gen_boundary(x_size: usize, y_size: usize) -> Array<usize, Dim<[usize; 2]>> {
-        array![
-            [x_size, y_size],
-            [x_size, y_size],
-            [x_size, y_size],
-            [x_size, y_size],
-            // skip self [0, 0]
-            [x_size, y_size],
-            [x_size, y_size],
-            [x_size, y_size],
-            [x_size, y_size],
-        ]
+        let state = self.state[(coords.0, coords.1)];
+        match &self.rule {
+            Rule::SecondOrder(_) => {
+                let previous = self.previous.as_ref().map_or(0, |states| states[coords]);
+                rule.next_second_order(previous, state, total)
+            }
+            Rule::Stochastic(rule) => {
+                let cell = coords.0 * self.state.dim().0 + coords.1;
+                let random = stochastic::random(rule.seed, self.generation as u32, cell as u32);
+                rule.next(state, total, random)
+            }
+            Rule::History(rule) => rule.next(state, total),
+            _ => rule.next(state, total),
+        }
+    }
+
+    // Looks the live cells of the 3x3 block up in the rule's table.
+    fn next_state_isotropic(&self, rule: &Isotropic, coords: (usize, usize)) -> u8 {
+        let size = self.state.dim().0 as i32;
+        let mut index = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let y = (coords.0 as i32 + dy).rem_euclid(size) as usize;
+                let x = (coords.1 as i32 + dx).rem_euclid(size) as usize;
+                if self.state[(y, x)] == 1 {
+                    index |= 1 << ((dy + 1) * 3 + dx + 1);
+                }
+            }
+        }
+        rule.next(self.state[coords], index)
+    }
+
+    // Turns the cell's block and picks the cell's place out of the new one.
+    fn next_state_margolus(&self, rule: &Margolus, coords: (usize, usize)) -> u8 {
+        let size = self.state.dim().0;
+        let (y, y_position) = Margolus::block_start(coords.0, size, self.generation);
+        let (x, x_position) = Margolus::block_start(coords.1, size, self.generation);
+        let mut index = 0;
+        for (bit, (dx, dy)) in [(0, 0), (1, 0), (0, 1), (1, 1)].into_iter().enumerate() {
+            if self.state[((y + dy) % size, (x + dx) % size)] == 1 {
+                index |= 1 << bit;
+            }
+        }
+        rule.next(index) >> (y_position * 2 + x_position) & 1
+    }
+
+    // coords are (y, x) like the state array
+    fn next_state_from_table(&self, table: &RuleTable, coords: (usize, usize)) -> u8 {
+        let size = self.state.dim().0 as i32;
+        let offsets = table.neighbourhood.offsets();
+        let mut cells = [0u8; 9];
+        cells[0] = self.state[coords];
+        for (cell, (dx, dy)) in cells[1..].iter_mut().zip(offsets) {
+            let y = (coords.0 as i32 + dy).rem_euclid(size) as usize;
+            let x = (coords.1 as i32 + dx).rem_euclid(size) as usize;
+            *cell = self.state[(y, x)];
+        }
+        table.next(&cells[..=offsets.len()])
+    }
+
+    // Counts a single cell's neighbours span by span, the whole board is
+    // stepped with the summed area table below.
+    fn next_state_larger_than_life(&self, rule: &LargerThanLife, coords: (usize, usize)) -> u8 {
+        let size = self.state.dim().0 as i32;
+        let state = self.state[coords];
+        let mut total = 0;
+        for span in rule.spans() {
+            for dy in span.top..=span.bottom {
+                for dx in span.left..=span.right {
+                    let y = (coords.0 as i32 + dy).rem_euclid(size) as usize;
+                    let x = (coords.1 as i32 + dx).rem_euclid(size) as usize;
+                    total += (self.state[(y, x)] == 1) as u32;
+                }
+            }
+        }
+        if !rule.middle {
+            total -= (state == 1) as u32;
+        }
+        rule.next(state, total)
+    }
+
+    // Counts neighbours with a summed area table of the board padded by range
+    // wrapped cells on every side, which costs the same for any range.
+    fn next_larger_than_life(&self, rule: &LargerThanLife) -> Array<u8, Ix2> {
+        let size = self.state.dim().0;
+        let range = rule.range as usize;
+        let padded = size + 2 * range;
+
+        // row and column 0 stay zero so every sum is a difference of four
+        let mut sums = Array::<u32, _>::from_shape_fn((padded + 1, padded + 1), |(y, x)| {
+            if y == 0 || x == 0 {
+                return 0;
+            }
+            let y = (y as i64 - 1 - range as i64).rem_euclid(size as i64) as usize;
+            let x = (x as i64 - 1 - range as i64).rem_euclid(size as i64) as usize;
+            (self.state[(y, x)] == 1) as u32
+        });
+        sums.accumulate_axis_inplace(Axis(0), |&previous, sum| *sum += previous);
+        sums.accumulate_axis_inplace(Axis(1), |&previous, sum| *sum += previous);
+
+        let spans = rule.spans();
+        let count = |y: usize, x: usize, span: &Span| {
+            let (top, bottom) = (
+                (y as i32 + span.top) as usize,
+                (y as i32 + span.bottom) as usize + 1,
+            );
+            let (left, right) = (
+                (x as i32 + span.left) as usize,
+                (x as i32 + span.right) as usize + 1,
+            );
+            sums[(bottom, right)] + sums[(top, left)] - sums[(top, right)] - sums[(bottom, left)]
+        };
+
+        let mut next = Array::<u8, _>::default((size, size).f());
+        for ((y, x), next) in next.indexed_iter_mut() {
+            let state = self.state[(y, x)];
+            let mut total: u32 = spans
+                .iter()
+                .map(|span| count(y + range, x + range, span))
+                .sum();
+            if !rule.middle {
+                total -= (state == 1) as u32;
+            }
+            *next = rule.next(state, total);
+        }
+        next
+    }
+
+    // (y, x) offsets of the neighbourhood with size added to stay positive.
+    fn gen_neighbor_offsets(size: usize, neighbourhood: Neighbourhood) -> Array<usize, Ix2> {
+        let offsets = neighbourhood.offsets();
+        Array::from_shape_fn((offsets.len(), 2), |(i, axis)| {
+            let (dx, dy) = offsets[i];
+            let offset = if axis == 0 { dy } else { dx };
+            (size as i32 + offset) as usize
+        })
+    }
+
+    fn gen_boundary(size: usize, neighbourhood: Neighbourhood) -> Array<usize, Ix2> {
+        Array::from_elem((neighbourhood.offsets().len(), 2), size)
     }
 }
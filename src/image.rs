@@ -0,0 +1,616 @@
+use crate::layers::Layer;
+use crate::{Gol, Region};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+pub mod gif;
+mod png;
+mod pnm;
+
+pub type Rgb = [u8; 3];
+
+// Controls how a board is rasterized. Each cell becomes a cell_size square,
+// optionally separated by one pixel grid lines.
+#[derive(Clone, Debug)]
+pub struct ImageOptions {
+    pub cell_size: usize,
+    pub grid_lines: Option<Rgb>,
+    pub alive: Rgb,
+    pub dead: Rgb,
+    // colour for each cell state, used instead of alive and dead when set.
+    // States past the end of the palette use the alive colour.
+    pub palette: Option<Vec<Rgb>>,
+    // skew the rows for hexagonal rules, each row half a cell left of the
+    // one above it. Games with hexagonal rules are always drawn this way.
+    pub hexagonal: bool,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        ImageOptions {
+            cell_size: 1,
+            grid_lines: None,
+            alive: [0, 0, 0],
+            dead: [255, 255, 255],
+            palette: None,
+            hexagonal: false,
+        }
+    }
+}
+
+// Rasterized board, 3 bytes per pixel, row major.
+pub struct RgbImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbImage {
+    pub fn from_cells(size: usize, cells: &[bool], options: &ImageOptions) -> RgbImage {
+        let states: Vec<u8> = cells.iter().map(|alive| *alive as u8).collect();
+        Self::from_states(size, &states, options)
+    }
+
+    pub fn from_states(size: usize, states: &[u8], options: &ImageOptions) -> RgbImage {
+        debug_assert!(size * size == states.len());
+        let cell_size = options.cell_size.max(1);
+        let grid = if options.grid_lines.is_some() { 1 } else { 0 };
+        let pitch = cell_size + grid;
+        let board_width = size * pitch + grid;
+        let height = board_width;
+        let width = board_width + hex_shift(options, size, 0, pitch);
+
+        let mut pixels = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            let row_shift = hex_shift(options, size, y.saturating_sub(grid) / pitch, pitch);
+            for x in 0..width {
+                let x = match x.checked_sub(row_shift).filter(|x| *x < board_width) {
+                    Some(x) => x,
+                    // outside the skewed board
+                    None => {
+                        pixels.extend_from_slice(&options.dead);
+                        continue;
+                    }
+                };
+                let on_grid = grid == 1 && (x % pitch == 0 || y % pitch == 0);
+                let colour = match options.grid_lines {
+                    Some(colour) if on_grid => colour,
+                    _ => {
+                        let cell_x = (x - grid) / pitch;
+                        let cell_y = (y - grid) / pitch;
+                        let state = states[cell_y * size + cell_x];
+                        match &options.palette {
+                            Some(palette) => palette
+                                .get(state as usize)
+                                .copied()
+                                .unwrap_or(options.alive),
+                            None if state == 1 => options.alive,
+                            None => options.dead,
+                        }
+                    }
+                };
+                pixels.extend_from_slice(&colour);
+            }
+        }
+
+        RgbImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn from_game(game: &dyn Gol, options: &ImageOptions) -> RgbImage {
+        let options = ImageOptions {
+            hexagonal: options.hexagonal || game.rule().hexagonal(),
+            ..options.clone()
+        };
+        Self::from_states(game.size(), &game.to_states(), &options)
+    }
+
+    // Heat map of a tracked layer, drawn with the palette or heat_palette.
+    pub fn from_layer(game: &dyn Gol, layer: Layer, options: &ImageOptions) -> Option<RgbImage> {
+        let options = ImageOptions {
+            hexagonal: options.hexagonal || game.rule().hexagonal(),
+            palette: Some(options.palette.clone().unwrap_or_else(heat_palette)),
+            ..options.clone()
+        };
+        let levels = layer_levels(&game.layer(layer)?);
+        Some(Self::from_states(game.size(), &levels, &options))
+    }
+
+    pub fn to_grey(&self) -> Vec<u8> {
+        self.pixels
+            .chunks_exact(3)
+            .map(|p| luma([p[0], p[1], p[2]]))
+            .collect()
+    }
+}
+
+// How far a row of cells is moved right, half a cell for each row below it.
+fn hex_shift(options: &ImageOptions, rows: usize, row: usize, pitch: usize) -> usize {
+    if options.hexagonal {
+        rows.saturating_sub(row + 1) * pitch / 2
+    } else {
+        0
+    }
+}
+
+// 256 colours going from black through red and yellow to white, brighter for
+// higher levels.
+pub fn heat_palette() -> Vec<Rgb> {
+    (0..256u32)
+        .map(|level| {
+            let channel = |start: u32| ((level * 3).saturating_sub(start).min(255)) as u8;
+            [channel(0), channel(255), channel(510)]
+        })
+        .collect()
+}
+
+// Layer values scaled to levels 0 to 255, 255 for the largest value.
+pub fn layer_levels(values: &[u32]) -> Vec<u8> {
+    let max = values.iter().copied().max().unwrap_or(0).max(1) as u64;
+    values
+        .iter()
+        .map(|value| (*value as u64 * 255 / max) as u8)
+        .collect()
+}
+
+// ITU-R 601 luma, which is what most viewers use for greyscale.
+fn luma(colour: Rgb) -> u8 {
+    ((colour[0] as u32 * 299 + colour[1] as u32 * 587 + colour[2] as u32 * 114) / 1000) as u8
+}
+
+// Largest image the decoders accept, so a corrupt header can't ask for more
+// memory than any board could use.
+const MAX_PIXELS: usize = 1 << 28;
+
+// Number of pixels in a decoded image, rejecting empty and oversized ones.
+fn pixel_count(width: usize, height: usize) -> io::Result<usize> {
+    width
+        .checked_mul(height)
+        .filter(|count| *count > 0 && *count <= MAX_PIXELS)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "image is empty or too large"))
+}
+
+// 8 bit greyscale image, row major. Imported images are reduced to this
+// before being turned into cells.
+pub struct GreyImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl GreyImage {
+    // Decode a PBM, PGM or PNG file, detected from its contents.
+    pub fn decode(data: &[u8]) -> io::Result<GreyImage> {
+        if data.starts_with(b"\x89PNG") {
+            png::decode_grey(data)
+        } else if data.starts_with(b"P") {
+            pnm::decode_grey(data)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unrecognized image format",
+            ))
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<GreyImage> {
+        Self::decode(&fs::read(path)?)
+    }
+
+    // Turn the image into live/dead cells, dark pixels become live cells.
+    pub fn to_cells(&self, method: Binarize) -> Vec<bool> {
+        match method {
+            Binarize::Threshold(threshold) => self.pixels.iter().map(|p| *p < threshold).collect(),
+            Binarize::Dither => self.dither(),
+        }
+    }
+
+    // Floyd-Steinberg error diffusion.
+    fn dither(&self) -> Vec<bool> {
+        let mut values: Vec<i32> = self.pixels.iter().map(|p| *p as i32).collect();
+        let mut cells = vec![false; values.len()];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = y * self.width + x;
+                let alive = values[i] < 128;
+                let error = values[i] - if alive { 0 } else { 255 };
+                cells[i] = alive;
+
+                let mut spread = |dx: isize, dy: usize, weight: i32| {
+                    let nx = x as isize + dx;
+                    if nx >= 0 && (nx as usize) < self.width && y + dy < self.height {
+                        values[(y + dy) * self.width + nx as usize] += error * weight / 16;
+                    }
+                };
+                spread(1, 0, 7);
+                spread(-1, 1, 3);
+                spread(0, 1, 5);
+                spread(1, 1, 1);
+            }
+        }
+        cells
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Binarize {
+    // pixels darker than the threshold are alive
+    Threshold(u8),
+    Dither,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SeedOptions {
+    pub method: Binarize,
+    // position of the image's top left corner on the board, wrapping around the edges
+    pub offset: (usize, usize),
+    // treat light pixels as alive instead of dark ones
+    pub invert: bool,
+}
+
+impl Default for SeedOptions {
+    fn default() -> Self {
+        SeedOptions {
+            method: Binarize::Threshold(128),
+            offset: (0, 0),
+            invert: false,
+        }
+    }
+}
+
+// Cells for a size * size board with the image placed on it. Parts of the
+// image that do not fit on the board are dropped.
+pub fn seed_cells(image: &GreyImage, size: usize, options: &SeedOptions) -> Vec<bool> {
+    let cells = image.to_cells(options.method);
+    let region = Region {
+        x: options.offset.0,
+        y: options.offset.1,
+        width: image.width.min(size),
+        height: image.height.min(size),
+    };
+
+    let mut board = vec![false; size * size];
+    for y in 0..region.height {
+        for x in 0..region.width {
+            board[region.index(size, x, y)] = cells[y * image.width + x] != options.invert;
+        }
+    }
+    board
+}
+
+pub fn seed<T: Gol>(image: &GreyImage, size: usize, options: &SeedOptions) -> T {
+    T::from_slice(size, &seed_cells(image, size, options))
+}
+
+// Binary PBM (P4). Live cells are written as 1 (black), scaled by cell_size.
+// Grid lines are not representable in a bitmap and are ignored.
+pub fn write_pbm(game: &dyn Gol, options: &ImageOptions, w: &mut impl Write) -> io::Result<()> {
+    let size = game.size();
+    let cells = game.to_vec();
+    let cell_size = options.cell_size.max(1);
+    let options = ImageOptions {
+        hexagonal: options.hexagonal || game.rule().hexagonal(),
+        ..options.clone()
+    };
+    let height = size * cell_size;
+    let width = height + hex_shift(&options, size, 0, cell_size);
+
+    write!(w, "P4\n{} {}\n", width, height)?;
+    let mut row = vec![0u8; width.div_ceil(8)];
+    for y in 0..height {
+        row.fill(0);
+        let row_shift = hex_shift(&options, size, y / cell_size, cell_size);
+        for x in row_shift..row_shift + height {
+            if cells[(y / cell_size) * size + (x - row_shift) / cell_size] {
+                row[x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        w.write_all(&row)?;
+    }
+    Ok(())
+}
+
+// Binary PGM (P5) of the rasterized board, colours converted to grey.
+pub fn write_pgm(game: &dyn Gol, options: &ImageOptions, w: &mut impl Write) -> io::Result<()> {
+    let image = RgbImage::from_game(game, options);
+    write!(w, "P5\n{} {}\n255\n", image.width, image.height)?;
+    w.write_all(&image.to_grey())
+}
+
+pub fn write_png(game: &dyn Gol, options: &ImageOptions, w: &mut impl Write) -> io::Result<()> {
+    let image = RgbImage::from_game(game, options);
+    png::encode_rgb(&image, w)
+}
+
+fn layer_image(game: &dyn Gol, layer: Layer, options: &ImageOptions) -> io::Result<RgbImage> {
+    RgbImage::from_layer(game, layer, options).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("the {:?} layer is not tracked", layer),
+        )
+    })
+}
+
+// Binary PGM (P5) of a tracked layer, the heat map colours converted to grey.
+pub fn write_layer_pgm(
+    game: &dyn Gol,
+    layer: Layer,
+    options: &ImageOptions,
+    w: &mut impl Write,
+) -> io::Result<()> {
+    let image = layer_image(game, layer, options)?;
+    write!(w, "P5\n{} {}\n255\n", image.width, image.height)?;
+    w.write_all(&image.to_grey())
+}
+
+pub fn write_layer_png(
+    game: &dyn Gol,
+    layer: Layer,
+    options: &ImageOptions,
+    w: &mut impl Write,
+) -> io::Result<()> {
+    png::encode_rgb(&layer_image(game, layer, options)?, w)
+}
+
+fn unsupported(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("unsupported image format: {}", path.display()),
+    )
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+}
+
+// Write the board to a file, picking the format from the extension.
+pub fn save(game: &dyn Gol, path: impl AsRef<Path>, options: &ImageOptions) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut w = BufWriter::new(File::create(path)?);
+    match extension(path).as_deref() {
+        Some("pbm") => write_pbm(game, options, &mut w)?,
+        Some("pgm") => write_pgm(game, options, &mut w)?,
+        Some("png") => write_png(game, options, &mut w)?,
+        _ => return Err(unsupported(path)),
+    }
+    w.flush()
+}
+
+// Write a tracked layer to a PGM or PNG file, picked from the extension.
+pub fn save_layer(
+    game: &dyn Gol,
+    layer: Layer,
+    path: impl AsRef<Path>,
+    options: &ImageOptions,
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut w = BufWriter::new(File::create(path)?);
+    match extension(path).as_deref() {
+        Some("pgm") => write_layer_pgm(game, layer, options, &mut w)?,
+        Some("png") => write_layer_png(game, layer, options, &mut w)?,
+        _ => return Err(unsupported(path)),
+    }
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_impls::cpu;
+
+    fn blinker() -> cpu::GameState {
+        cpu::GameState::from_slice(
+            3,
+            &[
+                false, true, false, //
+                false, true, false, //
+                false, true, false,
+            ],
+        )
+    }
+
+    #[test]
+    fn pbm_scaled() {
+        let mut out = Vec::new();
+        let options = ImageOptions {
+            cell_size: 2,
+            ..Default::default()
+        };
+        write_pbm(&blinker(), &options, &mut out).unwrap();
+
+        let header = b"P4\n6 6\n";
+        assert_eq!(&out[..header.len()], header);
+        assert_eq!(&out[header.len()..], &[0b0011_0000; 6]);
+    }
+
+    #[test]
+    fn hexagonal_rows_are_skewed() {
+        let mut game = blinker();
+        game.set_rule("B2/S34H".parse().unwrap()).unwrap();
+        let options = ImageOptions {
+            cell_size: 2,
+            ..Default::default()
+        };
+
+        let image = RgbImage::from_game(&game, &options);
+        assert_eq!((image.width, image.height), (8, 6));
+        let alive = |x: usize, y: usize| image.pixels[(y * image.width + x) * 3] == 0;
+        assert!(alive(4, 0) && alive(5, 1) && !alive(3, 0));
+        assert!(alive(2, 4) && alive(3, 5) && !alive(4, 4));
+
+        let mut out = Vec::new();
+        write_pbm(&game, &options, &mut out).unwrap();
+        let header = b"P4\n8 6\n";
+        assert_eq!(&out[..header.len()], header);
+        assert_eq!(
+            &out[header.len()..],
+            &[
+                0b0000_1100,
+                0b0000_1100,
+                0b0001_1000,
+                0b0001_1000,
+                0b0011_0000,
+                0b0011_0000
+            ]
+        );
+    }
+
+    #[test]
+    fn grid_lines_surround_cells() {
+        let options = ImageOptions {
+            cell_size: 2,
+            grid_lines: Some([255, 0, 0]),
+            ..Default::default()
+        };
+        let image = RgbImage::from_game(&blinker(), &options);
+        assert_eq!(image.width, 10);
+        // top left corner is grid, first pixel of the centre cell is alive
+        assert_eq!(&image.pixels[0..3], &[255, 0, 0]);
+        let centre = (4 * image.width + 4) * 3;
+        assert_eq!(&image.pixels[centre..centre + 3], &options.alive);
+    }
+
+    #[test]
+    fn png_layout() {
+        let mut out = Vec::new();
+        write_png(&blinker(), &ImageOptions::default(), &mut out).unwrap();
+        assert_eq!(&out[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&out[12..16], b"IHDR");
+        assert_eq!(&out[out.len() - 8..out.len() - 4], b"IEND");
+        // well known CRC of an empty IEND chunk
+        assert_eq!(&out[out.len() - 4..], &[0xae, 0x42, 0x60, 0x82]);
+    }
+
+    #[test]
+    fn export_import_round_trip() {
+        let options = ImageOptions {
+            cell_size: 3,
+            ..Default::default()
+        };
+        let writers = [write_pbm, write_pgm, write_png];
+        for write in writers {
+            let mut out = Vec::new();
+            write(&blinker(), &options, &mut out).unwrap();
+            let image = GreyImage::decode(&out).unwrap();
+            assert_eq!(image.width, 9);
+
+            // sample the centre of each 3x3 cell back onto a 3x3 board
+            let cells = image.to_cells(Binarize::Threshold(128));
+            let sampled: Vec<bool> = (0..9)
+                .map(|i| cells[(i / 3 * 3 + 1) * 9 + i % 3 * 3 + 1])
+                .collect();
+            assert_eq!(sampled, blinker().to_vec());
+        }
+    }
+
+    #[test]
+    fn plain_pbm_seeded_at_offset() {
+        let image = GreyImage::decode(b"P1\n# glider\n3 3\n010\n001\n111\n").unwrap();
+        let options = SeedOptions {
+            offset: (3, 3),
+            ..Default::default()
+        };
+        let game: cpu::GameState = seed(&image, 4, &options);
+        let alive: Vec<usize> = game
+            .to_vec()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, alive)| alive.then_some(i))
+            .collect();
+        // the glider wraps around the corner of the 4x4 board
+        assert_eq!(alive, vec![1, 4, 5, 7, 12]);
+    }
+
+    #[test]
+    fn layer_heat_maps() {
+        let mut states = vec![0; 25];
+        for i in [7, 12, 17] {
+            states[i] = 1;
+        }
+        let mut game = cpu::GameState::from_states(5, &states);
+        let mut out = Vec::new();
+        let error = write_layer_png(&game, Layer::Heat, &ImageOptions::default(), &mut out);
+        assert_eq!(error.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        game.track(Layer::Heat, true);
+        game.track(Layer::Age, true);
+        let game = game.to_next().to_next().to_next();
+        // the ends of the blinker flip every generation, its centre stays alive
+        let heat = game.layer(Layer::Heat).unwrap();
+        let changed = [7, 11, 13, 17];
+        assert!((0..25).all(|i| heat[i] == if changed.contains(&i) { 3 } else { 0 }));
+        let levels = layer_levels(&game.layer(Layer::Age).unwrap());
+        assert_eq!((levels[12], levels[11], levels[0]), (255, 63, 0));
+
+        let palette = heat_palette();
+        assert_eq!((palette[0], palette[255]), ([0, 0, 0], [255, 255, 255]));
+        let image = RgbImage::from_layer(game.as_ref(), Layer::Heat, &ImageOptions::default());
+        let pixels = image.unwrap().pixels;
+        assert_eq!(
+            (&pixels[..3], &pixels[21..24]),
+            (&[0, 0, 0][..], &[255; 3][..])
+        );
+
+        let mut out = Vec::new();
+        write_layer_pgm(
+            game.as_ref(),
+            Layer::Heat,
+            &ImageOptions::default(),
+            &mut out,
+        )
+        .unwrap();
+        let image = GreyImage::decode(&out).unwrap();
+        let hot: Vec<usize> = (0..25).filter(|i| image.pixels[*i] == 255).collect();
+        assert_eq!(hot, changed);
+    }
+
+    #[test]
+    fn compressed_png() {
+        // 4x4 8 bit greyscale with a black diagonal, deflated by zlib and split
+        // over two IDAT chunks
+        let data = [
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x04, 0x08, 0x00, 0x00, 0x00,
+            0x00, 0x8c, 0x9a, 0xc1, 0xa2, 0x00, 0x00, 0x00, 0x09, 0x49, 0x44, 0x41, 0x54, 0x78,
+            0xda, 0x63, 0x60, 0xf8, 0xff, 0xff, 0x3f, 0x03, 0x16, 0x32, 0xb1, 0x6e, 0x00, 0x00,
+            0x00, 0x0a, 0x49, 0x44, 0x41, 0x54, 0x10, 0x42, 0x10, 0x98, 0x07, 0x00, 0x77, 0x9c,
+            0x0b, 0xf5, 0x66, 0x50, 0x4a, 0x3a, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+            0xae, 0x42, 0x60, 0x82,
+        ];
+        let image = GreyImage::decode(&data).unwrap();
+        let cells = image.to_cells(Binarize::Threshold(128));
+        assert_eq!(cells, (0..16).map(|i| i % 5 == 0).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn corrupt_images_are_rejected() {
+        let corrupt: [&[u8]; 6] = [
+            b"P4\n0 4\n",
+            b"P1\n2 0\n",
+            b"P2\n2 1\n3\n1 4\n",
+            b"P5\n1 1\n3\n\x09",
+            b"P5\n100000 100000\n255\n",
+            b"P4\n18446744073709551615 2\n",
+        ];
+        for data in corrupt {
+            let error = GreyImage::decode(data).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+
+        let mut png = Vec::new();
+        write_png(&blinker(), &ImageOptions::default(), &mut png).unwrap();
+        // IHDR's width, then its height
+        for (offset, size) in [(16, 0), (20, 0), (20, 1)] {
+            let mut data = png.clone();
+            data[offset..offset + 4].copy_from_slice(&(size as u32).to_be_bytes());
+            let error = GreyImage::decode(&data).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,453 @@
+use super::Rgb;
+use crate::{Gol, Region};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// GIF codes are at most 12 bits wide.
+const MAX_CODE_BITS: u32 = 12;
+
+#[derive(Clone, Debug)]
+pub struct GifOptions {
+    // delay between frames in hundredths of a second
+    pub delay: u16,
+    // pixels per cell
+    pub scale: usize,
+    // only record this part of the board, whole board if None
+    pub crop: Option<Region>,
+    // colour per cell state, index 0 is dead and 1 is alive
+    pub palette: Vec<Rgb>,
+    // loop forever when true, play once otherwise
+    pub repeat: bool,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        GifOptions {
+            delay: 10,
+            scale: 4,
+            crop: None,
+            palette: vec![[255, 255, 255], [0, 0, 0]],
+            repeat: true,
+        }
+    }
+}
+
+// Collects generations of a game as palette indexed frames and encodes them
+// as an animated GIF.
+pub struct Recorder {
+    options: GifOptions,
+    width: usize,
+    height: usize,
+    frames: Vec<Vec<u8>>,
+}
+
+impl Recorder {
+    pub fn new(options: GifOptions) -> Recorder {
+        assert!(
+            (2..=256).contains(&options.palette.len()),
+            "GIF palettes need between 2 and 256 colours"
+        );
+        Recorder {
+            options,
+            width: 0,
+            height: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    // Add the current state of the game as a frame.
+    pub fn capture(&mut self, game: &dyn Gol) {
+        let size = game.size();
+        let states = game.to_states();
+        // states past the end of the palette get its last colour
+        let last = (self.options.palette.len() - 1) as u8;
+        let region = self.options.crop.unwrap_or(Region::whole(size));
+        let scale = self.options.scale.max(1);
+        // hexagonal boards are skewed like image::RgbImage draws them
+        let hex_shift = |row: usize| {
+            if game.rule().hexagonal() {
+                region.height.saturating_sub(row + 1) * scale / 2
+            } else {
+                0
+            }
+        };
+        let board_width = region.width * scale;
+        let width = board_width + hex_shift(0);
+        let height = region.height * scale;
+
+        if self.frames.is_empty() {
+            self.width = width;
+            self.height = height;
+        }
+        assert!(
+            self.width == width && self.height == height,
+            "all frames of a recording must be the same size"
+        );
+
+        let mut frame = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let row_shift = hex_shift(y / scale);
+            for x in 0..width {
+                let state = match x.checked_sub(row_shift).filter(|x| *x < board_width) {
+                    Some(x) => states[region.index(size, x / scale, y / scale)],
+                    None => 0,
+                };
+                frame.push(state.min(last));
+            }
+        }
+        self.frames.push(frame);
+    }
+
+    // Capture the starting state and the next generations, returning the last one.
+    pub fn record(&mut self, game: Box<dyn Gol>, generations: usize) -> Box<dyn Gol> {
+        let mut game = game;
+        self.capture(game.as_ref());
+        for _ in 0..generations {
+            game = game.to_next();
+            self.capture(game.as_ref());
+        }
+        game
+    }
+
+    // Fails without writing anything when the frames are wider or higher
+    // than the 65535 pixels GIF sizes can hold.
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        let (width, height) = match (u16::try_from(self.width), u16::try_from(self.height)) {
+            (Ok(width), Ok(height)) => (width.to_le_bytes(), height.to_le_bytes()),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "GIF frames are at most 65535 pixels wide and high",
+                ))
+            }
+        };
+        // the colour table size has to be a power of two, at least 2 entries
+        let table_bits = (self.options.palette.len() as u32)
+            .next_power_of_two()
+            .trailing_zeros()
+            .max(1);
+
+        w.write_all(b"GIF89a")?;
+        w.write_all(&width)?;
+        w.write_all(&height)?;
+        // global colour table present, 8 bit colour resolution
+        w.write_all(&[0x80 | 0x70 | (table_bits as u8 - 1), 0, 0])?;
+        for i in 0..(1 << table_bits) {
+            let colour = self.options.palette.get(i).unwrap_or(&[0, 0, 0]);
+            w.write_all(colour)?;
+        }
+
+        if self.options.repeat {
+            // NETSCAPE2.0 application extension, loop count 0 means forever
+            w.write_all(&[0x21, 0xff, 0x0b])?;
+            w.write_all(b"NETSCAPE2.0")?;
+            w.write_all(&[0x03, 0x01, 0x00, 0x00, 0x00])?;
+        }
+
+        // LZW needs at least 2 bit codes even for 2 colour images
+        let min_code_size = table_bits.max(2);
+        for frame in &self.frames {
+            // graphic control extension carrying the frame delay
+            w.write_all(&[0x21, 0xf9, 0x04, 0x00])?;
+            w.write_all(&self.options.delay.to_le_bytes())?;
+            w.write_all(&[0x00, 0x00])?;
+
+            // image descriptor covering the whole screen, no local colour table
+            w.write_all(&[0x2c, 0, 0, 0, 0])?;
+            w.write_all(&width)?;
+            w.write_all(&height)?;
+            w.write_all(&[0x00])?;
+
+            w.write_all(&[min_code_size as u8])?;
+            for block in lzw_encode(frame, min_code_size).chunks(255) {
+                w.write_all(&[block.len() as u8])?;
+                w.write_all(block)?;
+            }
+            w.write_all(&[0x00])?;
+        }
+
+        w.write_all(&[0x3b])
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write(&mut w)?;
+        w.flush()
+    }
+}
+
+// Packs variable width codes least significant bit first, as GIF expects.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, width: u32) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += width;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+fn lzw_encode(indices: &[u8], min_code_size: u32) -> Vec<u8> {
+    let clear_code: u16 = 1 << min_code_size;
+    let end_code = clear_code + 1;
+
+    let mut out = BitWriter {
+        bytes: Vec::new(),
+        buffer: 0,
+        bits: 0,
+    };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = end_code + 1;
+    let mut code_bits = min_code_size + 1;
+
+    out.write(clear_code, code_bits);
+
+    let mut pixels = indices.iter();
+    let mut prefix = match pixels.next() {
+        Some(first) => *first as u16,
+        None => {
+            out.write(end_code, code_bits);
+            return out.finish();
+        }
+    };
+
+    for pixel in pixels {
+        if let Some(code) = table.get(&(prefix, *pixel)) {
+            prefix = *code;
+            continue;
+        }
+
+        out.write(prefix, code_bits);
+        code_bits = widened(code_bits, next_code);
+        if next_code < 1 << MAX_CODE_BITS {
+            table.insert((prefix, *pixel), next_code);
+            next_code += 1;
+        } else {
+            out.write(clear_code, code_bits);
+            table.clear();
+            next_code = end_code + 1;
+            code_bits = min_code_size + 1;
+        }
+        prefix = *pixel as u16;
+    }
+
+    out.write(prefix, code_bits);
+    code_bits = widened(code_bits, next_code);
+    out.write(end_code, code_bits);
+    out.finish()
+}
+
+// Decoders grow their code width as soon as the next free code no longer fits,
+// which includes the entry they add for the code just written.
+fn widened(code_bits: u32, next_code: u16) -> u32 {
+    if next_code >= 1 << code_bits && code_bits < MAX_CODE_BITS {
+        code_bits + 1
+    } else {
+        code_bits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_impls::cpu;
+    use crate::rules::stochastic;
+
+    // Minimal GIF flavoured LZW decoder to check the encoder against.
+    fn lzw_decode(data: &[u8], min_code_size: u32) -> Vec<u8> {
+        let clear_code = 1usize << min_code_size;
+        let end_code = clear_code + 1;
+        let mut pos = 0;
+        let mut read = |bits: u32| {
+            let mut code = 0;
+            for i in 0..bits as usize {
+                let bit = (data[(pos + i) / 8] >> ((pos + i) % 8)) & 1;
+                code |= (bit as usize) << i;
+            }
+            pos += bits as usize;
+            code
+        };
+
+        let mut out = Vec::new();
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let mut code_bits = min_code_size + 1;
+        let mut previous: Option<Vec<u8>> = None;
+        loop {
+            let code = read(code_bits);
+            if code == clear_code {
+                table = (0..clear_code).map(|i| vec![i as u8]).collect();
+                table.extend([vec![], vec![]]);
+                code_bits = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == end_code {
+                return out;
+            }
+            let entry = match (&previous, table.get(code)) {
+                (_, Some(entry)) => entry.clone(),
+                (Some(previous), None) => [previous.clone(), vec![previous[0]]].concat(),
+                (None, None) => panic!("invalid first code"),
+            };
+            out.extend_from_slice(&entry);
+            if let Some(previous) = previous {
+                if table.len() < 1 << MAX_CODE_BITS {
+                    table.push([previous, vec![entry[0]]].concat());
+                }
+                if table.len() == 1 << code_bits && code_bits < MAX_CODE_BITS {
+                    code_bits += 1;
+                }
+            }
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn lzw_round_trip() {
+        let mut pixels: Vec<u8> = (0..20000)
+            .map(|i| (stochastic::random(7, 0, i) & 1) as u8)
+            .collect();
+        pixels.extend([0; 3000]);
+        for len in [0, 1, 2, 7, 20, 100, pixels.len()] {
+            let encoded = lzw_encode(&pixels[..len], 2);
+            assert_eq!(lzw_decode(&encoded, 2), &pixels[..len]);
+        }
+    }
+
+    // Logical screen size and the decoded frames of a GIF written by Recorder.
+    fn gif_decode(data: &[u8]) -> ((u16, u16), Vec<Vec<u8>>) {
+        assert_eq!(&data[..6], b"GIF89a");
+        let size = (
+            u16::from_le_bytes([data[6], data[7]]),
+            u16::from_le_bytes([data[8], data[9]]),
+        );
+        let mut pos = 13 + 3 * (2 << (data[10] & 0x07));
+        // concatenated data sub-blocks starting at pos
+        let sub_blocks = |pos: &mut usize| {
+            let mut out = Vec::new();
+            while data[*pos] != 0 {
+                let len = data[*pos] as usize;
+                out.extend_from_slice(&data[*pos + 1..*pos + 1 + len]);
+                *pos += len + 1;
+            }
+            *pos += 1;
+            out
+        };
+
+        let mut frames = Vec::new();
+        loop {
+            match data[pos] {
+                0x21 => {
+                    pos += 2;
+                    sub_blocks(&mut pos);
+                }
+                0x2c => {
+                    let min_code_size = data[pos + 10] as u32;
+                    pos += 11;
+                    frames.push(lzw_decode(&sub_blocks(&mut pos), min_code_size));
+                }
+                0x3b => return (size, frames),
+                block => panic!("unexpected GIF block {:#x}", block),
+            }
+        }
+    }
+
+    #[test]
+    fn records_cropped_scaled_generations() {
+        // blinker lying across row 3
+        let mut cells = vec![false; 8 * 8];
+        cells[3 * 8 + 2..3 * 8 + 5].fill(true);
+        let game = cpu::GameState::from_slice(8, &cells);
+
+        let options = GifOptions {
+            scale: 2,
+            crop: Some(Region {
+                x: 2,
+                y: 2,
+                width: 3,
+                height: 4,
+            }),
+            ..Default::default()
+        };
+        let mut recorder = Recorder::new(options);
+        recorder.record(Box::new(game), 2);
+        let mut data = Vec::new();
+        recorder.write(&mut data).unwrap();
+
+        let (size, frames) = gif_decode(&data);
+        assert_eq!(size, (6, 8));
+        assert_eq!(frames.len(), 3);
+        let horizontal: Vec<u8> = (0..6 * 8).map(|i| (i / 12 == 1) as u8).collect();
+        let vertical: Vec<u8> = (0..6 * 8)
+            .map(|i| (i / 6 < 6 && i % 6 / 2 == 1) as u8)
+            .collect();
+        assert_eq!(frames, [horizontal.clone(), vertical, horizontal]);
+    }
+
+    #[test]
+    fn oversized_frames_are_not_written() {
+        let game = cpu::GameState::from_slice(1, &[true]);
+        let options = GifOptions {
+            scale: 1,
+            crop: Some(Region {
+                x: 0,
+                y: 0,
+                width: 65536,
+                height: 1,
+            }),
+            ..Default::default()
+        };
+        let mut recorder = Recorder::new(options);
+        recorder.capture(&game);
+        let mut data = Vec::new();
+        let error = recorder.write(&mut data).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn frames_keep_cell_states() {
+        let states = [0, 1, 2, 0];
+        let mut game = cpu::GameState::from_states(2, &states);
+        game.set_rule("/2/3".parse().unwrap()).unwrap();
+
+        let options = GifOptions {
+            scale: 1,
+            palette: vec![[255, 255, 255], [0, 0, 0], [255, 0, 0]],
+            ..Default::default()
+        };
+        let mut recorder = Recorder::new(options);
+        recorder.capture(&game);
+        assert_eq!(recorder.frames[0], states);
+
+        // two colours draw every state past dead with the live colour
+        let options = GifOptions {
+            scale: 1,
+            ..Default::default()
+        };
+        let mut recorder = Recorder::new(options);
+        recorder.capture(&game);
+        assert_eq!(recorder.frames[0], [0, 1, 1, 0]);
+    }
+}
@@ -0,0 +1,558 @@
+use super::{luma, pixel_count, GreyImage, Rgb, RgbImage};
+use std::io::{self, Write};
+
+const SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+// Largest payload of a stored (uncompressed) deflate block.
+const MAX_STORED_BLOCK: usize = 65535;
+
+// Encode an 8 bit truecolour PNG. The image data is written as stored deflate
+// blocks, which keeps the encoder dependency free at the cost of file size.
+pub fn encode_rgb(image: &RgbImage, w: &mut impl Write) -> io::Result<()> {
+    w.write_all(SIGNATURE)?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(image.width as u32).to_be_bytes());
+    header.extend_from_slice(&(image.height as u32).to_be_bytes());
+    // bit depth 8, colour type 2 (rgb), default compression, filter, no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    write_chunk(w, b"IHDR", &header)?;
+
+    // every scanline is prefixed with filter type 0 (none)
+    let stride = image.width * 3;
+    let mut raw = Vec::with_capacity((stride + 1) * image.height);
+    for row in image.pixels.chunks_exact(stride.max(1)) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    write_chunk(w, b"IDAT", &zlib_stored(&raw))?;
+
+    write_chunk(w, b"IEND", &[])
+}
+
+fn write_chunk(w: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+
+    let mut crc = Crc32::new();
+    crc.update(kind);
+    crc.update(data);
+    w.write_all(&crc.finish().to_be_bytes())
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut out = Vec::with_capacity(data.len() + blocks * 5 + 6);
+    // deflate, 32K window, no preset dictionary, fastest
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        out.push(if last { 1 } else { 0 });
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+struct Crc32 {
+    table: [u32; 256],
+    value: u32,
+}
+
+impl Crc32 {
+    fn new() -> Self {
+        let mut table = [0u32; 256];
+        for (n, entry) in table.iter_mut().enumerate() {
+            let mut c = n as u32;
+            for _ in 0..8 {
+                c = if c & 1 == 1 {
+                    0xedb8_8320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+            }
+            *entry = c;
+        }
+        Crc32 {
+            table,
+            value: 0xffff_ffff,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.value =
+                self.table[((self.value ^ *byte as u32) & 0xff) as usize] ^ (self.value >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        self.value ^ 0xffff_ffff
+    }
+}
+
+// Decode a non interlaced PNG of any colour type and bit depth to greyscale.
+// Transparent pixels are composited onto white.
+pub fn decode_grey(data: &[u8]) -> io::Result<GreyImage> {
+    if data.len() < 8 || &data[..8] != SIGNATURE {
+        return Err(invalid("not a PNG file"));
+    }
+
+    let mut header = None;
+    let mut palette: Vec<Rgb> = Vec::new();
+    let mut transparency: Vec<u8> = Vec::new();
+    let mut compressed = Vec::new();
+
+    let mut pos = 8;
+    while pos + 12 <= data.len() {
+        let len = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        let body = data
+            .get(pos + 8..pos + 8 + len)
+            .ok_or_else(|| invalid("truncated PNG chunk"))?;
+        match kind {
+            b"IHDR" => header = Some(Header::parse(body)?),
+            b"PLTE" => palette = body.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect(),
+            b"tRNS" => transparency = body.to_vec(),
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            _ => (),
+        }
+        pos += len + 12;
+    }
+
+    let header = header.ok_or_else(|| invalid("PNG is missing IHDR"))?;
+    // a stream inflating past what IHDR describes is corrupt
+    let raw = zlib_inflate(&compressed, header.raw_len()?)?;
+    let pixels = header.unfilter(&raw)?;
+
+    let mut grey = Vec::with_capacity(header.width * header.height);
+    for row in pixels.chunks_exact(header.stride()) {
+        for x in 0..header.width {
+            let (colour, alpha) = header.pixel(row, x, &palette, &transparency)?;
+            let luma = luma(colour) as u32;
+            let alpha = alpha as u32;
+            grey.push(((luma * alpha + 255 * (255 - alpha)) / 255) as u8);
+        }
+    }
+
+    Ok(GreyImage {
+        width: header.width,
+        height: header.height,
+        pixels: grey,
+    })
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+struct Header {
+    width: usize,
+    height: usize,
+    bit_depth: usize,
+    colour_type: u8,
+}
+
+impl Header {
+    fn parse(body: &[u8]) -> io::Result<Header> {
+        if body.len() < 13 {
+            return Err(invalid("short IHDR"));
+        }
+        let header = Header {
+            width: u32::from_be_bytes(body[0..4].try_into().unwrap()) as usize,
+            height: u32::from_be_bytes(body[4..8].try_into().unwrap()) as usize,
+            bit_depth: body[8] as usize,
+            colour_type: body[9],
+        };
+        if body[12] != 0 {
+            return Err(invalid("interlaced PNGs are not supported"));
+        }
+        if !matches!(header.bit_depth, 1 | 2 | 4 | 8 | 16) {
+            return Err(invalid("unsupported PNG bit depth"));
+        }
+        pixel_count(header.width, header.height)?;
+        Ok(header)
+    }
+
+    fn channels(&self) -> io::Result<usize> {
+        match self.colour_type {
+            0 | 3 => Ok(1),
+            4 => Ok(2),
+            2 => Ok(3),
+            6 => Ok(4),
+            _ => Err(invalid("unsupported PNG colour type")),
+        }
+    }
+
+    fn stride(&self) -> usize {
+        (self.width * self.channels().unwrap_or(1) * self.bit_depth).div_ceil(8)
+    }
+
+    // Size of the image data, with each scanline's filter byte.
+    fn raw_len(&self) -> io::Result<usize> {
+        (self.stride() + 1)
+            .checked_mul(self.height)
+            .ok_or_else(|| invalid("PNG image is too large"))
+    }
+
+    // Undo the per scanline filters, returning rows without the filter byte.
+    fn unfilter(&self, raw: &[u8]) -> io::Result<Vec<u8>> {
+        let bytes_per_pixel = (self.channels()? * self.bit_depth).div_ceil(8);
+        let stride = self.stride();
+        if raw.len() < self.raw_len()? {
+            return Err(invalid("PNG image data is too short"));
+        }
+
+        let mut out = vec![0u8; stride * self.height];
+        for y in 0..self.height {
+            let filter = raw[y * (stride + 1)];
+            let line = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+            let (previous, current) = out.split_at_mut(y * stride);
+            let above = if y > 0 {
+                &previous[(y - 1) * stride..]
+            } else {
+                &[][..]
+            };
+            let current = &mut current[..stride];
+
+            for x in 0..stride {
+                let a = if x >= bytes_per_pixel {
+                    current[x - bytes_per_pixel]
+                } else {
+                    0
+                };
+                let b = above.get(x).copied().unwrap_or(0);
+                let c = if x >= bytes_per_pixel {
+                    above.get(x - bytes_per_pixel).copied().unwrap_or(0)
+                } else {
+                    0
+                };
+                let predicted = match filter {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    4 => paeth(a, b, c),
+                    _ => return Err(invalid("unknown PNG filter type")),
+                };
+                current[x] = line[x].wrapping_add(predicted);
+            }
+        }
+        Ok(out)
+    }
+
+    fn sample(&self, row: &[u8], index: usize) -> u16 {
+        match self.bit_depth {
+            16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]),
+            8 => row[index] as u16,
+            depth => {
+                let bit = index * depth;
+                let shift = 8 - depth - bit % 8;
+                ((row[bit / 8] >> shift) & ((1 << depth) - 1) as u8) as u16
+            }
+        }
+    }
+
+    // Scale a sample to 8 bits.
+    fn sample8(&self, row: &[u8], index: usize) -> u8 {
+        let max = (1u32 << self.bit_depth) - 1;
+        (self.sample(row, index) as u32 * 255 / max) as u8
+    }
+
+    fn pixel(
+        &self,
+        row: &[u8],
+        x: usize,
+        palette: &[Rgb],
+        transparency: &[u8],
+    ) -> io::Result<(Rgb, u8)> {
+        let channels = self.channels()?;
+        let i = x * channels;
+        Ok(match self.colour_type {
+            0 => {
+                let v = self.sample8(row, i);
+                let transparent = transparency.len() >= 2
+                    && self.sample(row, i)
+                        == u16::from_be_bytes([transparency[0], transparency[1]]);
+                ([v; 3], if transparent { 0 } else { 255 })
+            }
+            2 => (
+                [
+                    self.sample8(row, i),
+                    self.sample8(row, i + 1),
+                    self.sample8(row, i + 2),
+                ],
+                255,
+            ),
+            3 => {
+                let index = self.sample(row, i) as usize;
+                let colour = *palette
+                    .get(index)
+                    .ok_or_else(|| invalid("PNG palette index out of range"))?;
+                (colour, transparency.get(index).copied().unwrap_or(255))
+            }
+            4 => ([self.sample8(row, i); 3], self.sample8(row, i + 1)),
+            _ => (
+                [
+                    self.sample8(row, i),
+                    self.sample8(row, i + 1),
+                    self.sample8(row, i + 2),
+                ],
+                self.sample8(row, i + 3),
+            ),
+        })
+    }
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// order in which code length code lengths are stored in dynamic blocks
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: usize) -> io::Result<u32> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = self
+                .data
+                .get(self.pos / 8)
+                .ok_or_else(|| invalid("unexpected end of deflate stream"))?;
+            value |= (((byte >> (self.pos % 8)) & 1) as u32) << i;
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+}
+
+// Canonical Huffman code stored as the number of codes per length and the
+// symbols ordered by code.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        for i in 1..15 {
+            offsets[i + 1] = offsets[i] + counts[i];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> io::Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("invalid Huffman code"))
+    }
+}
+
+// Inflate at most limit bytes, anything longer is an error.
+fn zlib_inflate(data: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+    if data.len() < 2 || data[0] & 0x0f != 8 || data[1] & 0x20 != 0 {
+        return Err(invalid("unsupported zlib stream"));
+    }
+    let mut reader = BitReader { data, pos: 16 };
+    let mut out = Vec::new();
+
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.pos = reader.pos.div_ceil(8) * 8;
+                let len = reader.bits(16)? as usize;
+                let start = reader.pos / 8 + 2;
+                let stored = data
+                    .get(start..start + len)
+                    .ok_or_else(|| invalid("truncated stored block"))?;
+                if out.len() + len > limit {
+                    return Err(too_long());
+                }
+                out.extend_from_slice(stored);
+                reader.pos = (start + len) * 8;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5; 30]);
+                inflate_block(&mut reader, &literals, &distances, &mut out, limit)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &literals, &distances, &mut out, limit)?;
+            }
+            _ => return Err(invalid("invalid deflate block type")),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+fn too_long() -> io::Error {
+    invalid("PNG image data is longer than its header says")
+}
+
+fn read_dynamic_tables(reader: &mut BitReader) -> io::Result<(Huffman, Huffman)> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for i in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[*i] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_length_code.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => (
+                *lengths
+                    .last()
+                    .ok_or_else(|| invalid("repeat with no previous length"))?,
+                3 + reader.bits(2)? as usize,
+            ),
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(invalid("too many code lengths"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    literals: &Huffman,
+    distances: &Huffman,
+    out: &mut Vec<u8>,
+    limit: usize,
+) -> io::Result<()> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 if out.len() == limit => return Err(too_long()),
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let i = symbol - 257;
+                if i >= LENGTH_BASE.len() {
+                    return Err(invalid("invalid length symbol"));
+                }
+                let length =
+                    LENGTH_BASE[i] as usize + reader.bits(LENGTH_EXTRA[i] as usize)? as usize;
+
+                let d = distances.decode(reader)? as usize;
+                if d >= DISTANCE_BASE.len() {
+                    return Err(invalid("invalid distance symbol"));
+                }
+                let distance =
+                    DISTANCE_BASE[d] as usize + reader.bits(DISTANCE_EXTRA[d] as usize)? as usize;
+                if distance > out.len() {
+                    return Err(invalid("distance too far back"));
+                }
+                if out.len() + length > limit {
+                    return Err(too_long());
+                }
+
+                let start = out.len() - distance;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
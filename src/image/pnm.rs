@@ -0,0 +1,147 @@
+use super::{pixel_count, GreyImage};
+use std::io;
+
+// Decode plain or binary PBM (P1/P4) and PGM (P2/P5) to greyscale.
+pub fn decode_grey(data: &[u8]) -> io::Result<GreyImage> {
+    let mut reader = Reader { data, pos: 0 };
+    let magic = reader.token()?;
+    let kind = match magic {
+        b"P1" | b"P2" | b"P4" | b"P5" => magic[1],
+        _ => return Err(invalid("unsupported PNM type")),
+    };
+    let width = reader.number()?;
+    let height = reader.number()?;
+    let max = if kind == b'1' || kind == b'4' {
+        1
+    } else {
+        reader.number()?
+    };
+    if max == 0 || max > 65535 {
+        return Err(invalid("invalid PGM maximum value"));
+    }
+    let count = pixel_count(width, height)?;
+
+    let mut pixels = Vec::with_capacity(count);
+    match kind {
+        b'1' => {
+            for _ in 0..count {
+                // bitmaps use 1 for black
+                pixels.push(if reader.bit()? { 0 } else { 255 });
+            }
+        }
+        b'2' => {
+            for _ in 0..count {
+                pixels.push((reader.sample(max)? * 255 / max) as u8);
+            }
+        }
+        b'4' => {
+            // exactly one whitespace character separates the header from the data
+            let start = reader.pos + 1;
+            let stride = width.div_ceil(8);
+            let bits = stride
+                .checked_mul(height)
+                .and_then(|len| data.get(start..start.checked_add(len)?))
+                .ok_or_else(|| invalid("truncated PBM"))?;
+            for row in bits.chunks_exact(stride) {
+                for x in 0..width {
+                    let black = row[x / 8] & (0x80 >> (x % 8)) != 0;
+                    pixels.push(if black { 0 } else { 255 });
+                }
+            }
+        }
+        _ => {
+            let start = reader.pos + 1;
+            let sample_size = if max > 255 { 2 } else { 1 };
+            let samples = count
+                .checked_mul(sample_size)
+                .and_then(|len| data.get(start..start.checked_add(len)?))
+                .ok_or_else(|| invalid("truncated PGM"))?;
+            for sample in samples.chunks_exact(sample_size) {
+                let value = if sample_size == 2 {
+                    u16::from_be_bytes([sample[0], sample[1]]) as usize
+                } else {
+                    sample[0] as usize
+                };
+                if value > max {
+                    return Err(invalid("PGM sample above the maximum value"));
+                }
+                pixels.push((value * 255 / max) as u8);
+            }
+        }
+    }
+
+    Ok(GreyImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    // skip whitespace and # comments
+    fn skip(&mut self) {
+        while let Some(c) = self.data.get(self.pos) {
+            if *c == b'#' {
+                while self.data.get(self.pos).is_some_and(|c| *c != b'\n') {
+                    self.pos += 1;
+                }
+            } else if c.is_ascii_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn token(&mut self) -> io::Result<&'a [u8]> {
+        self.skip();
+        let start = self.pos;
+        while self
+            .data
+            .get(self.pos)
+            .is_some_and(|c| !c.is_ascii_whitespace() && *c != b'#')
+        {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(invalid("unexpected end of PNM data"));
+        }
+        Ok(&self.data[start..self.pos])
+    }
+
+    fn number(&mut self) -> io::Result<usize> {
+        std::str::from_utf8(self.token()?)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| invalid("expected a number in PNM data"))
+    }
+
+    fn sample(&mut self, max: usize) -> io::Result<usize> {
+        let value = self.number()?;
+        if value > max {
+            return Err(invalid("PGM sample above the maximum value"));
+        }
+        Ok(value)
+    }
+
+    // plain PBM digits may or may not be separated by whitespace
+    fn bit(&mut self) -> io::Result<bool> {
+        self.skip();
+        let bit = match self.data.get(self.pos) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(invalid("expected 0 or 1 in PBM data")),
+        };
+        self.pos += 1;
+        Ok(bit)
+    }
+}
@@ -0,0 +1,37 @@
+// Counters a board can keep for every cell next to its states, updated with
+// each generation once tracked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Layer {
+    // generations the cell has been alive in a row, stopping at 65535 to fit
+    // the 16 bit image of the compute backend
+    Age,
+    // changes of the cell's state since tracking started
+    Heat,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 2] = [Layer::Age, Layer::Heat];
+
+    pub fn max(self) -> u32 {
+        match self {
+            Layer::Age => u16::MAX as u32,
+            Layer::Heat => u32::MAX,
+        }
+    }
+
+    // The value for a cell when tracking starts.
+    pub fn start(self, state: u8) -> u32 {
+        match self {
+            Layer::Age => (state == 1) as u32,
+            Layer::Heat => 0,
+        }
+    }
+
+    pub fn next(self, value: u32, before: u8, after: u8) -> u32 {
+        match self {
+            Layer::Age if after == 1 => (value + 1).min(self.max()),
+            Layer::Age => 0,
+            Layer::Heat => value.saturating_add((before != after) as u32),
+        }
+    }
+}
@@ -1,14 +1,15 @@
 use std::fmt::Display;
 use std::sync::Arc;
-use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer};
 use vulkano::command_buffer::allocator::{
     StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo,
 };
 use vulkano::command_buffer::{
-    AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferToImageInfo, CopyImageToBufferInfo,
+    AutoCommandBufferBuilder, BufferImageCopy, CommandBufferUsage, CopyBufferToImageInfo,
+    CopyImageInfo, CopyImageToBufferInfo, PrimaryAutoCommandBuffer,
 };
 use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
-use vulkano::device::{Device, DeviceCreateInfo, QueueCreateInfo};
+use vulkano::device::{Device, DeviceCreateInfo, Features, QueueCreateInfo};
 use vulkano::device::{Queue, QueueFlags};
 use vulkano::format::Format;
 use vulkano::image::{Image, ImageCreateInfo, ImageType, ImageUsage};
@@ -18,17 +19,144 @@ use vulkano::memory::allocator::{
     StandardMemoryAllocator,
 };
 use vulkano::sync::{self, GpuFuture};
-use vulkano::{Validated, VulkanError, VulkanLibrary};
+use vulkano::{DeviceSize, Validated, VulkanError, VulkanLibrary};
 
+pub mod analysis;
+pub mod census;
+pub mod continuous;
+pub mod elementary;
 pub mod game_impls;
+pub mod image;
+pub mod layers;
+pub mod life3d;
+pub mod objects;
+pub mod patterns;
+pub mod rules;
+pub mod turmite;
+
+use layers::Layer;
+use patterns::{PasteMode, Pattern};
+use rules::stochastic::Noise;
+use rules::{BoardSizeError, Rule};
 
 pub trait Gol {
-    fn from_slice(ize: usize, vec: &[bool]) -> Self
+    fn from_slice(size: usize, vec: &[bool]) -> Self
+    where
+        Self: Sized,
+    {
+        let states: Vec<u8> = vec.iter().map(|alive| *alive as u8).collect();
+        Self::from_states(size, &states)
+    }
+    // Cells are 0 when dead and 1 when alive, rules with more states use the
+    // values above that.
+    fn from_states(size: usize, states: &[u8]) -> Self
     where
         Self: Sized;
-    fn to_vec(&self) -> Vec<bool>;
+    // Live cells, the ones in state 1 or the odd states of history rules.
+    fn to_vec(&self) -> Vec<bool> {
+        let rule = self.rule();
+        self.to_states()
+            .iter()
+            .map(|state| rule.alive(*state))
+            .collect()
+    }
+    fn to_states(&self) -> Vec<u8>;
+    fn rule(&self) -> &Rule;
+    // Fails, leaving the rule as it was, when the rule can't run on a board
+    // of this size.
+    fn set_rule(&mut self, rule: Rule) -> Result<(), BoardSizeError>;
+    fn noise(&self) -> Option<Noise>;
+    // Flips a seeded fraction of the cells after every generation.
+    fn set_noise(&mut self, noise: Option<Noise>);
+    // Starts or stops keeping the layer's counters.
+    fn track(&mut self, layer: Layer, on: bool);
+    // The layer's counter for every cell, when it is tracked.
+    fn layer(&self, layer: Layer) -> Option<Vec<u32>>;
+    fn size(&self) -> usize;
+    // Generations since the board was made, 0 for a new one.
+    fn generation(&self) -> i64;
     fn to_next(&self) -> Box<dyn Gol>;
+    // The generation before this one, for the rules that can run backwards.
+    fn step_back(&self) -> Option<Box<dyn Gol>>;
     fn print(&self);
+    // One digit per cell state. Boards of hexagonal rules have their rows
+    // shifted half a cell further left going down, so the six neighbours of a
+    // cell are the ones touching it.
+    fn to_text(&self) -> String {
+        let size = self.size();
+        let hexagonal = self.rule().hexagonal();
+        let mut text = String::new();
+        for (y, row) in self.to_states().chunks(size.max(1)).enumerate() {
+            if hexagonal {
+                text.push_str(&" ".repeat(size - 1 - y));
+            }
+            for (x, state) in row.iter().enumerate() {
+                if hexagonal && x > 0 {
+                    text.push(' ');
+                }
+                text.push_str(&state.to_string());
+            }
+            text.push('\n');
+        }
+        text
+    }
+    // Only the cells inside the region are read back, so copying out of a
+    // large board is cheap on every backend.
+    fn copy_region(&self, region: Region) -> Pattern;
+    fn paste(&self, pattern: &Pattern, x: usize, y: usize, mode: PasteMode) -> Box<dyn Gol>;
+}
+
+// Rectangle of cells. Coordinates wrap around the board edges like the games do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    pub fn whole(size: usize) -> Region {
+        Region {
+            x: 0,
+            y: 0,
+            width: size,
+            height: size,
+        }
+    }
+
+    // Index into a row major size * size board for a cell inside the region.
+    pub fn index(&self, size: usize, x: usize, y: usize) -> usize {
+        ((self.y + y) % size) * size + (self.x + x) % size
+    }
+
+    // The region cut where it wraps around the edges of a size * size board,
+    // as pieces that each lie inside the board, paired with where each piece
+    // starts inside the region.
+    pub fn unwrapped(&self, size: usize) -> Vec<(Region, (usize, usize))> {
+        let spans = |start: usize, len: usize| {
+            let start = start % size;
+            let first = len.min(size - start);
+            let mut spans = vec![(start, first, 0)];
+            if len > first {
+                spans.push((0, len - first, first));
+            }
+            spans
+        };
+        let mut pieces = Vec::new();
+        for (y, height, offset_y) in spans(self.y, self.height.min(size)) {
+            for (x, width, offset_x) in spans(self.x, self.width.min(size)) {
+                let piece = Region {
+                    x,
+                    y,
+                    width,
+                    height,
+                };
+                pieces.push((piece, (offset_x, offset_y)));
+            }
+        }
+        pieces
+    }
 }
 
 // Common Vulkan ojects for allocating compute or graphics resources.
@@ -144,6 +272,13 @@ impl VulkanContext {
             physical_device.clone(),
             DeviceCreateInfo {
                 queue_create_infos,
+                // 16 bit storage images, which hold the age layer
+                enabled_features: Features {
+                    shader_storage_image_extended_formats: physical_device
+                        .supported_features()
+                        .shader_storage_image_extended_formats,
+                    ..Features::empty()
+                },
                 ..Default::default()
             },
         )?;
@@ -197,12 +332,57 @@ impl VulkanContext {
         .expect("failed to create buffer")
     }
 
+    fn storage_buffer_from_iter(
+        &self,
+        content: impl ExactSizeIterator<Item = u32>,
+    ) -> Subbuffer<[u32]> {
+        Buffer::from_iter(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            content,
+        )
+        .expect("failed to create buffer")
+    }
+
+    // Storage buffer the host reads back once a dispatch has written it.
+    fn readable_buffer_from_iter(
+        &self,
+        content: impl ExactSizeIterator<Item = u32>,
+    ) -> Subbuffer<[u32]> {
+        Buffer::from_iter(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..Default::default()
+            },
+            content,
+        )
+        .expect("failed to create buffer")
+    }
+
     fn uninitialized_image(&self, extent: [u32; 3]) -> Arc<Image> {
+        self.uninitialized_image_with_format(extent, Format::R8_UINT)
+    }
+
+    fn uninitialized_image_with_format(&self, extent: [u32; 3], format: Format) -> Arc<Image> {
         Image::new(
             self.memory_allocator.clone(),
             ImageCreateInfo {
-                image_type: ImageType::Dim2d,
-                format: Format::R8_UINT,
+                image_type: image_type(extent),
+                format,
                 extent,
                 usage: ImageUsage::TRANSFER_DST | ImageUsage::TRANSFER_SRC | ImageUsage::STORAGE,
                 ..Default::default()
@@ -219,6 +399,16 @@ impl VulkanContext {
         &self,
         extents: [u32; 3],
         content: impl ExactSizeIterator<Item = u8>,
+    ) -> Arc<Image> {
+        self.image_from_iter_with_format(extents, Format::R8_UINT, content)
+    }
+
+    // T has to be the size of one texel of format.
+    fn image_from_iter_with_format<T: BufferContents>(
+        &self,
+        extents: [u32; 3],
+        format: Format,
+        content: impl ExactSizeIterator<Item = T>,
     ) -> Arc<Image> {
         // allocate and populate staging buffer
         let buffer = Buffer::from_iter(
@@ -236,14 +426,17 @@ impl VulkanContext {
         )
         .expect("failed to create transfer buffer");
 
-        assert!(buffer.size() == (extents[0] * extents[1] * extents[2]) as u64);
+        assert!(
+            buffer.size()
+                == (extents[0] * extents[1] * extents[2]) as u64 * std::mem::size_of::<T>() as u64
+        );
 
         // create image
         let image = Image::new(
             self.memory_allocator.clone(),
             ImageCreateInfo {
-                image_type: ImageType::Dim2d,
-                format: Format::R8_UINT,
+                image_type: image_type(extents),
+                format,
                 extent: extents,
                 usage: ImageUsage::TRANSFER_DST | ImageUsage::TRANSFER_SRC | ImageUsage::STORAGE,
                 ..Default::default()
@@ -285,8 +478,12 @@ impl VulkanContext {
     }
 
     fn buffer_from_image(&self, image: &Arc<Image>) -> Subbuffer<[u8]> {
+        self.texels_from_image(image)
+    }
+
+    // T has to be the size of one texel of the image's format.
+    fn texels_from_image<T: BufferContents + Default>(&self, image: &Arc<Image>) -> Subbuffer<[T]> {
         let src_extent = image.extent();
-        // assuming R8
         let image_size = src_extent[0] * src_extent[1] * src_extent[2];
 
         let buffer = Buffer::from_iter(
@@ -300,7 +497,7 @@ impl VulkanContext {
                     | MemoryTypeFilter::HOST_RANDOM_ACCESS,
                 ..Default::default()
             },
-            (0..image_size).map(|_| 0u8),
+            (0..image_size).map(|_| T::default()),
         )
         .expect("failed to create transfer buffer");
 
@@ -332,6 +529,143 @@ impl VulkanContext {
 
         buffer
     }
+
+    // Copy of an image made on the device, nothing goes through the host.
+    fn image_from_image(&self, image: &Arc<Image>) -> Arc<Image> {
+        let copy = self.uninitialized_image_with_format(image.extent(), image.format());
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.compute_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        builder
+            .copy_image(CopyImageInfo::images(image.clone(), copy.clone()))
+            .unwrap();
+
+        self.submit_compute(builder.build().unwrap());
+
+        copy
+    }
+
+    // One copy per piece of a region that wraps around the edges of an R8
+    // image, laid out row by row in a buffer the size of the region.
+    fn region_copies(image: &Arc<Image>, region: Region) -> Vec<BufferImageCopy> {
+        region
+            .unwrapped(image.extent()[0] as usize)
+            .into_iter()
+            .map(|(piece, (offset_x, offset_y))| BufferImageCopy {
+                buffer_offset: (offset_y * region.width + offset_x) as DeviceSize,
+                buffer_row_length: region.width as u32,
+                buffer_image_height: region.height as u32,
+                image_subresource: image.subresource_layers(),
+                image_offset: [piece.x as u32, piece.y as u32, 0],
+                image_extent: [piece.width as u32, piece.height as u32, 1],
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    // Read just the cells of a region, row by row. Region copies go through
+    // the compute queue since dedicated transfer queues may only be able to
+    // copy whole blocks of texels.
+    fn read_image_region(&self, image: &Arc<Image>, region: Region) -> Vec<u8> {
+        let buffer = Buffer::from_iter(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..Default::default()
+            },
+            (0..region.width * region.height).map(|_| 0u8),
+        )
+        .expect("failed to create transfer buffer");
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.compute_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        builder
+            .copy_image_to_buffer(CopyImageToBufferInfo {
+                regions: Self::region_copies(image, region).into_iter().collect(),
+                ..CopyImageToBufferInfo::image_buffer(image.clone(), buffer.clone())
+            })
+            .unwrap();
+
+        self.submit_compute(builder.build().unwrap());
+
+        let content = buffer.read().unwrap();
+        content.to_vec()
+    }
+
+    // Overwrite the cells of a region with content given row by row.
+    fn write_image_region(
+        &self,
+        image: &Arc<Image>,
+        region: Region,
+        content: impl ExactSizeIterator<Item = u8>,
+    ) {
+        let buffer = Buffer::from_iter(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            content,
+        )
+        .expect("failed to create transfer buffer");
+
+        assert!(buffer.len() == (region.width * region.height) as u64);
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.compute_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        builder
+            .copy_buffer_to_image(CopyBufferToImageInfo {
+                regions: Self::region_copies(image, region).into_iter().collect(),
+                ..CopyBufferToImageInfo::buffer_image(buffer, image.clone())
+            })
+            .unwrap();
+
+        self.submit_compute(builder.build().unwrap());
+    }
+
+    fn submit_compute(&self, command_buffer: Arc<PrimaryAutoCommandBuffer>) {
+        let future = sync::now(self.device.clone())
+            .then_execute(self.compute_queue.clone(), command_buffer)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap();
+
+        future.wait(None).unwrap();
+    }
+}
+
+// Boards with depth are 3D images, everything else is 2D.
+fn image_type(extent: [u32; 3]) -> ImageType {
+    if extent[2] > 1 {
+        ImageType::Dim3d
+    } else {
+        ImageType::Dim2d
+    }
 }
 
 impl Display for VulkanContext {
@@ -0,0 +1,271 @@
+use crate::rules::ParseRuleError;
+use crate::Gol;
+use std::fmt::Display;
+use std::str::FromStr;
+
+pub mod compute;
+pub mod cpu;
+
+// Cells around a cell of a 3D grid that count as its neighbours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Neighbourhood3d {
+    // the 26 cells of the surrounding cube
+    Moore,
+    // the 6 cells sharing a face
+    VonNeumann,
+}
+
+impl Neighbourhood3d {
+    // (x, y, z) offsets
+    pub fn offsets(self) -> Vec<(i32, i32, i32)> {
+        let mut offsets = Vec::new();
+        for dz in -1..=1 {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let distance = i32::abs(dx) + i32::abs(dy) + i32::abs(dz);
+                    let included = match self {
+                        Neighbourhood3d::Moore => distance > 0,
+                        Neighbourhood3d::VonNeumann => distance == 1,
+                    };
+                    if included {
+                        offsets.push((dx, dy, dz));
+                    }
+                }
+            }
+        }
+        offsets
+    }
+}
+
+// Outer totalistic rule on a 3D grid. Cells are 0 or 1, boards are cubes
+// that wrap around on every axis.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rule3d {
+    // bit n is set when n live neighbours bring a dead cell to life
+    pub birth: u32,
+    // bit n is set when a live cell with n live neighbours stays alive
+    pub survival: u32,
+    pub neighbourhood: Neighbourhood3d,
+}
+
+impl Rule3d {
+    pub fn next(&self, state: u8, live_neighbours: u32) -> u8 {
+        let counts = if state == 1 {
+            self.survival
+        } else {
+            self.birth
+        };
+        (counts >> live_neighbours & 1) as u8
+    }
+}
+
+// B5/S45, Bays' 3D Life 4555
+impl Default for Rule3d {
+    fn default() -> Self {
+        Rule3d {
+            birth: 1 << 5,
+            survival: 1 << 4 | 1 << 5,
+            neighbourhood: Neighbourhood3d::Moore,
+        }
+    }
+}
+
+// Counts are single digits, or numbers separated by commas once they go past
+// 9, as in B5/S4,5,10. A V suffix picks the von Neumann neighbourhood.
+fn neighbour_counts(counts: &str, max: u32) -> Result<u32, &'static str> {
+    let numbers: Vec<&str> = if counts.contains(',') {
+        counts.split(',').collect()
+    } else {
+        counts
+            .char_indices()
+            .map(|(i, c)| &counts[i..i + c.len_utf8()])
+            .collect()
+    };
+    let mut bits = 0;
+    for number in numbers.into_iter().filter(|n| !n.is_empty()) {
+        match number.parse::<u32>() {
+            Ok(n) if n <= max => bits |= 1 << n,
+            _ => return Err("neighbour counts go from 0 to the neighbourhood size"),
+        }
+    }
+    Ok(bits)
+}
+
+impl FromStr for Rule3d {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason| ParseRuleError {
+            rule: s.to_string(),
+            reason,
+        };
+        let (counts, neighbourhood) = match s.trim() {
+            rule if rule.ends_with(['V', 'v']) => {
+                (&rule[..rule.len() - 1], Neighbourhood3d::VonNeumann)
+            }
+            rule => (rule, Neighbourhood3d::Moore),
+        };
+        let max = neighbourhood.offsets().len() as u32;
+
+        let (mut birth, mut survival) = (None, None);
+        for part in counts.split('/') {
+            let mut chars = part.chars();
+            let kind = chars.next().map(|c| c.to_ascii_uppercase());
+            let slot = match kind {
+                Some('B') => &mut birth,
+                Some('S') => &mut survival,
+                _ => return Err(error("every part needs a B or S prefix")),
+            };
+            if slot.is_some() {
+                return Err(error("repeated part"));
+            }
+            *slot = Some(neighbour_counts(chars.as_str(), max).map_err(error)?);
+        }
+        Ok(Rule3d {
+            birth: birth.unwrap_or_default(),
+            survival: survival.unwrap_or_default(),
+            neighbourhood,
+        })
+    }
+}
+
+impl Display for Rule3d {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let counts = |bits: u32| -> String {
+            let counts: Vec<String> = (0..=26)
+                .filter(|n| bits >> n & 1 == 1)
+                .map(|n| n.to_string())
+                .collect();
+            if bits >> 10 == 0 {
+                counts.concat()
+            } else {
+                counts.join(",")
+            }
+        };
+        write!(f, "B{}/S{}", counts(self.birth), counts(self.survival))?;
+        if self.neighbourhood == Neighbourhood3d::VonNeumann {
+            write!(f, "V")?;
+        }
+        Ok(())
+    }
+}
+
+// A size * size * size grid, the 3D counterpart of Gol. States are ordered
+// x first, then y, then z.
+pub trait Gol3d {
+    fn from_slice(size: usize, vec: &[bool]) -> Self
+    where
+        Self: Sized,
+    {
+        let states: Vec<u8> = vec.iter().map(|alive| *alive as u8).collect();
+        Self::from_states(size, &states)
+    }
+    fn from_states(size: usize, states: &[u8]) -> Self
+    where
+        Self: Sized;
+    fn to_states(&self) -> Vec<u8>;
+    fn rule(&self) -> &Rule3d;
+    fn set_rule(&mut self, rule: Rule3d);
+    fn size(&self) -> usize;
+    fn to_next(&self) -> Box<dyn Gol3d>;
+}
+
+// Layer z of the grid as a 2D board, for the print and image outputs.
+pub fn layer<T: Gol>(game: &dyn Gol3d, z: usize) -> T {
+    let size = game.size();
+    let states = game.to_states();
+    T::from_states(size, &states[z * size * size..(z + 1) * size * size])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_impls;
+
+    // cpu
+    #[test]
+    fn cpu_neighbourhoods() {
+        neighbourhoods_tester::<cpu::GameState>();
+    }
+
+    #[test]
+    fn cpu_life_4555() {
+        life_4555_tester::<cpu::GameState>();
+    }
+
+    // compute
+    #[test]
+    fn compute_neighbourhoods() {
+        neighbourhoods_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn compute_life_4555() {
+        life_4555_tester::<compute::GameState>();
+    }
+
+    #[test]
+    fn parses_3d_rules() {
+        assert_eq!("B5/S45".parse(), Ok(Rule3d::default()));
+        let rule: Rule3d = "B5,12/S4,5,26".parse().unwrap();
+        assert_eq!(rule.to_string(), "B5,12/S4,5,26");
+        assert_eq!("b1/s2v".parse::<Rule3d>().unwrap().to_string(), "B1/S2V");
+        assert!("B7/S2V".parse::<Rule3d>().is_err());
+        assert!("B5,27/S".parse::<Rule3d>().is_err());
+        assert_eq!("B27/S".parse::<Rule3d>().unwrap().birth, 1 << 2 | 1 << 7);
+    }
+
+    fn neighbourhoods_tester<T: Gol3d>() {
+        // a lone cell gives birth to exactly its neighbours
+        let size = 6;
+        let mut start = vec![false; size * size * size];
+        start[(3 * size + 3) * size + 3] = true;
+        for rule in ["B1/S", "B1/SV"] {
+            let rule: Rule3d = rule.parse().unwrap();
+            let mut state = T::from_slice(size, &start);
+            state.set_rule(rule.clone());
+            let mut expected = vec![0; size * size * size];
+            for (dx, dy, dz) in rule.neighbourhood.offsets() {
+                expected[(((3 + dz) * 6 + 3 + dy) * 6 + 3 + dx) as usize] = 1;
+            }
+            assert_eq!(state.to_next().to_states(), expected, "{}", rule);
+        }
+    }
+
+    fn life_4555_tester<T: Gol3d>() {
+        let size = 8;
+        let mut states: Vec<u8> = (0..size * size * size)
+            .map(|i| (i * 7 % 11 < 4) as u8)
+            .collect();
+        let rule = Rule3d::default();
+        let mut state: Box<dyn Gol3d> = Box::new(T::from_states(size, &states));
+
+        for _ in 0..4 {
+            state = state.to_next();
+            states = step(size, &rule, &states);
+            assert_eq!(state.to_states(), states);
+        }
+
+        let top: game_impls::cpu::GameState = layer(state.as_ref(), 0);
+        assert_eq!(top.to_states(), states[..size * size]);
+    }
+
+    // one generation counting every neighbour separately
+    fn step(size: usize, rule: &Rule3d, states: &[u8]) -> Vec<u8> {
+        let wrap = |c: usize, d: i32| (c as i32 + d).rem_euclid(size as i32) as usize;
+        (0..states.len())
+            .map(|i| {
+                let (x, y, z) = (i % size, i / size % size, i / (size * size));
+                let total = rule
+                    .neighbourhood
+                    .offsets()
+                    .into_iter()
+                    .filter(|(dx, dy, dz)| {
+                        states[(wrap(z, *dz) * size + wrap(y, *dy)) * size + wrap(x, *dx)] == 1
+                    })
+                    .count();
+                rule.next(states[i], total as u32)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::Subbuffer,
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    descriptor_set::{layout::DescriptorSetLayout, PersistentDescriptorSet, WriteDescriptorSet},
+    image::{view::ImageView, Image},
+    pipeline::{
+        compute::ComputePipelineCreateInfo, layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+};
+
+use super::{Gol3d, Rule3d};
+use crate::VulkanContext;
+
+// The grid is a size * size * size 3D image.
+pub struct GameState {
+    size: usize,
+    context: Arc<VulkanContext>,
+    grid: Arc<Image>,
+    compute_pipeline: Arc<ComputePipeline>,
+    descriptor_set_layout: Arc<DescriptorSetLayout>,
+    params_buffer: Subbuffer<[u32]>,
+    rule: Rule3d,
+}
+
+impl GameState {
+    pub fn from_random(context: Arc<VulkanContext>, size: usize) -> GameState {
+        let extent = size as u32;
+        let grid = context.image_from_iter(
+            [extent, extent, extent],
+            (0..size * size * size).map(|_| rand::random::<u8>() & 1),
+        );
+
+        Self::from_image(context, grid, size)
+    }
+
+    fn from_image(context: Arc<VulkanContext>, grid: Arc<Image>, size: usize) -> GameState {
+        let rule = Rule3d::default();
+        let params_buffer = Self::params_buffer(&context, size, &rule);
+        let (compute_pipeline, descriptor_set_layout) = Self::create_pipeline(context.clone());
+
+        GameState {
+            size,
+            context,
+            grid,
+            compute_pipeline,
+            descriptor_set_layout,
+            params_buffer,
+            rule,
+        }
+    }
+
+    // Laid out like grid_params in the shader.
+    fn params_buffer(context: &VulkanContext, size: usize, rule: &Rule3d) -> Subbuffer<[u32]> {
+        context.uniform_buffer_from_iter(
+            [
+                size as u32,
+                rule.birth,
+                rule.survival,
+                rule.neighbourhood.offsets().len() as u32,
+            ]
+            .into_iter(),
+        )
+    }
+
+    fn create_pipeline(
+        context: Arc<VulkanContext>,
+    ) -> (Arc<ComputePipeline>, Arc<DescriptorSetLayout>) {
+        let shader = cs::load(context.device.clone()).expect("failed to create shader module");
+        let cs = shader.entry_point("main").unwrap();
+        let stage = PipelineShaderStageCreateInfo::new(cs);
+        let layout = PipelineLayout::new(
+            context.device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(context.device.clone())
+                .unwrap(),
+        )
+        .unwrap();
+
+        let descriptor_set_layout = layout.set_layouts().first().unwrap().clone();
+
+        (
+            ComputePipeline::new(
+                context.device.clone(),
+                None,
+                ComputePipelineCreateInfo::stage_layout(stage, layout.clone()),
+            )
+            .expect("failed to create compute pipeline"),
+            descriptor_set_layout,
+        )
+    }
+}
+
+impl Gol3d for GameState {
+    fn from_states(size: usize, states: &[u8]) -> Self
+    where
+        Self: Sized,
+    {
+        let context = Arc::new(VulkanContext::try_create().unwrap());
+        let extent = size as u32;
+        let grid = context.image_from_iter([extent, extent, extent], states.iter().copied());
+
+        Self::from_image(context, grid, size)
+    }
+
+    fn to_states(&self) -> Vec<u8> {
+        let buffer_content = self.context.buffer_from_image(&self.grid);
+        let binding = buffer_content.read().unwrap();
+        binding.to_vec()
+    }
+
+    fn rule(&self) -> &Rule3d {
+        &self.rule
+    }
+
+    fn set_rule(&mut self, rule: Rule3d) {
+        self.params_buffer = Self::params_buffer(&self.context, self.size, &rule);
+        self.rule = rule;
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn to_next(&self) -> Box<dyn Gol3d> {
+        let extent = self.size as u32;
+        let next_grid = self.context.uninitialized_image([extent, extent, extent]);
+
+        let view_previous = ImageView::new_default(self.grid.clone()).unwrap();
+        let view_next = ImageView::new_default(next_grid.clone()).unwrap();
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            &self.context.descriptor_set_allocator,
+            self.descriptor_set_layout.clone(),
+            [
+                WriteDescriptorSet::buffer(0, self.params_buffer.clone()),
+                WriteDescriptorSet::image_view(1, view_previous),
+                WriteDescriptorSet::image_view(2, view_next),
+            ],
+            [],
+        )
+        .unwrap();
+
+        let mut command_buffer_builder = AutoCommandBufferBuilder::primary(
+            &self.context.command_buffer_allocator,
+            self.context.compute_queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        let groups = extent.div_ceil(4);
+        command_buffer_builder
+            .bind_pipeline_compute(self.compute_pipeline.clone())
+            .unwrap()
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                self.compute_pipeline.layout().clone(),
+                0,
+                descriptor_set,
+            )
+            .unwrap()
+            .dispatch([groups, groups, groups])
+            .unwrap();
+
+        self.context
+            .submit_compute(command_buffer_builder.build().unwrap());
+
+        Box::new(GameState {
+            size: self.size,
+            context: self.context.clone(),
+            grid: next_grid,
+            compute_pipeline: self.compute_pipeline.clone(),
+            descriptor_set_layout: self.descriptor_set_layout.clone(),
+            params_buffer: self.params_buffer.clone(),
+            rule: self.rule.clone(),
+        })
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 4, local_size_y = 4, local_size_z = 4) in;
+
+            layout(set = 0, binding = 0) uniform grid_params {
+                uint size;
+                // bit n set when n live neighbours give a birth or survival
+                uint birth;
+                uint survival;
+                // 26 for the Moore neighbourhood, 6 for von Neumann
+                uint neighbours;
+            } params;
+
+            layout(set = 0, binding = 1, r8ui) uniform readonly uimage3D src;
+
+            layout(set = 0, binding = 2, r8ui) uniform writeonly uimage3D dest;
+
+            void main() {
+                ivec3 cell = ivec3(gl_GlobalInvocationID);
+                int size = int(params.size);
+                if(cell.x >= size || cell.y >= size || cell.z >= size)
+                {
+                    return;
+                }
+
+                uint total = 0;
+                for(int dz = -1; dz <= 1; dz++)
+                {
+                    for(int dy = -1; dy <= 1; dy++)
+                    {
+                        for(int dx = -1; dx <= 1; dx++)
+                        {
+                            int distance = abs(dx) + abs(dy) + abs(dz);
+                            if(distance == 0 || (params.neighbours == 6 && distance > 1))
+                            {
+                                continue;
+                            }
+                            ivec3 neighbour = ((cell + ivec3(dx, dy, dz)) % ivec3(size) + size) % ivec3(size);
+                            total += uint(imageLoad(src, neighbour).x == 1);
+                        }
+                    }
+                }
+
+                uint state = imageLoad(src, cell).x;
+                uint counts = state == 1 ? params.survival : params.birth;
+                imageStore(dest, cell, uvec4(counts >> total & 1));
+            }
+        ",
+    }
+}
@@ -0,0 +1,79 @@
+use super::{Gol3d, Rule3d};
+use ndarray::prelude::*;
+use ndarray::Array;
+
+pub struct GameState {
+    // indexed [z, y, x]
+    state: Array<u8, Ix3>,
+    // (z, y, x) offsets of the neighbourhood with size added to stay positive
+    neighbor_offsets: Vec<[usize; 3]>,
+    rule: Rule3d,
+}
+
+impl Gol3d for GameState {
+    fn from_states(size: usize, states: &[u8]) -> GameState {
+        debug_assert!(size * size * size == states.len());
+        let state = Array::from_shape_vec((size, size, size), states.to_vec()).unwrap();
+        let rule = Rule3d::default();
+        GameState {
+            state,
+            neighbor_offsets: Self::gen_neighbor_offsets(size, &rule),
+            rule,
+        }
+    }
+
+    fn to_states(&self) -> Vec<u8> {
+        self.state.iter().copied().collect()
+    }
+
+    fn rule(&self) -> &Rule3d {
+        &self.rule
+    }
+
+    fn set_rule(&mut self, rule: Rule3d) {
+        self.neighbor_offsets = Self::gen_neighbor_offsets(self.size(), &rule);
+        self.rule = rule;
+    }
+
+    fn size(&self) -> usize {
+        self.state.dim().0
+    }
+
+    fn to_next(&self) -> Box<dyn Gol3d> {
+        let size = self.size();
+        let next = Array::from_shape_fn(self.state.raw_dim(), |(z, y, x)| {
+            let total = self
+                .neighbor_offsets
+                .iter()
+                .filter(|[dz, dy, dx]| {
+                    self.state[[(z + dz) % size, (y + dy) % size, (x + dx) % size]] == 1
+                })
+                .count();
+            self.rule.next(self.state[[z, y, x]], total as u32)
+        });
+
+        Box::new(GameState {
+            state: next,
+            neighbor_offsets: self.neighbor_offsets.clone(),
+            rule: self.rule.clone(),
+        })
+    }
+}
+
+impl GameState {
+    pub fn from_random(size: usize) -> Box<dyn Gol3d> {
+        let states: Vec<u8> = (0..size * size * size)
+            .map(|_| rand::random::<u8>() & 1)
+            .collect();
+        Box::new(GameState::from_states(size, &states))
+    }
+
+    fn gen_neighbor_offsets(size: usize, rule: &Rule3d) -> Vec<[usize; 3]> {
+        let offset = |d: i32| (size as i32 + d) as usize;
+        rule.neighbourhood
+            .offsets()
+            .into_iter()
+            .map(|(dx, dy, dz)| [offset(dz), offset(dy), offset(dx)])
+            .collect()
+    }
+}
@@ -1,5 +1,6 @@
 use game_impls::cpu;
 use life_rust::game_impls::compute;
+use life_rust::image::gif::{GifOptions, Recorder};
 use life_rust::Gol;
 use life_rust::{game_impls, VulkanContext};
 use std::env;
@@ -22,6 +23,13 @@ fn main() {
         }
     }
 
+    // optionally record the run as an animated GIF
+    let mut recorder = if args.len() > 2 {
+        Some(Recorder::new(GifOptions::default()))
+    } else {
+        None
+    };
+
     let vulkan_context: Arc<VulkanContext>;
     let mut back: Box<dyn Gol>;
     let mut front: Box<dyn Gol>;
@@ -43,10 +51,16 @@ fn main() {
 
     back.print();
     println!("------ <start>");
-    for i in 0..32 {
+    if let Some(recorder) = recorder.as_mut() {
+        recorder.capture(back.as_ref());
+    }
+    for _ in 0..32 {
         front = back.to_next();
         front.print();
-        println!("------ {}", i);
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.capture(front.as_ref());
+        }
+        println!("------ {}", front.generation());
         io::stdout().flush().unwrap();
 
         let ten_millis = time::Duration::from_millis(100);
@@ -54,4 +68,9 @@ fn main() {
 
         back = front;
     }
+
+    if let Some(recorder) = recorder {
+        recorder.save(&args[2]).unwrap();
+        println!("Saved {} frames to {}", recorder.frame_count(), args[2]);
+    }
 }
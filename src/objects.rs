@@ -0,0 +1,410 @@
+use crate::analysis::{self, Outcome};
+use crate::game_impls::cpu;
+use crate::patterns::{Orientation, Pattern};
+use crate::rules::Rule;
+use crate::Gol;
+use std::collections::HashMap;
+
+// Room left around an object when it is run in isolation.
+const ISOLATION_PADDING: usize = 16;
+// Objects that don't settle within this many generations are left unclassified.
+const MAX_GENERATIONS: usize = 512;
+
+// Column values use the first 32 digits, 'y' zero runs go up to 'z'.
+const WECHSLER_DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+// Names for common B3/S23 objects, keyed by apgcode.
+const NAMES: &[(&str, &str)] = &[
+    ("xs4_33", "block"),
+    ("xs4_252", "tub"),
+    ("xs5_253", "boat"),
+    ("xs6_696", "beehive"),
+    ("xs6_356", "ship"),
+    ("xs6_25a4", "barge"),
+    ("xs6_bd", "snake"),
+    ("xs7_2596", "loaf"),
+    ("xs7_178c", "eater 1"),
+    ("xs7_25ac", "long boat"),
+    ("xs8_6996", "pond"),
+    ("xs8_35ac", "long ship"),
+    ("xp2_7", "blinker"),
+    ("xp2_7e", "toad"),
+    ("xp2_318c", "beacon"),
+    ("xp3_co9nas0san9oczgoldlo0oldlogz1047210127401", "pulsar"),
+    ("xp15_4r4z4r4", "pentadecathlon"),
+    ("xq4_153", "glider"),
+    ("xq4_6frc", "lightweight spaceship"),
+    ("xq4_27dee6", "middleweight spaceship"),
+    ("xq4_27deee6", "heavyweight spaceship"),
+];
+
+pub fn common_name(apgcode: &str) -> Option<&'static str> {
+    NAMES
+        .iter()
+        .find(|(code, _)| *code == apgcode)
+        .map(|(_, name)| *name)
+}
+
+// Cell coordinates that are not bound to a board.
+pub type Cell = (isize, isize);
+
+// A group of live cells that belong together, with its classification.
+#[derive(Clone, Debug)]
+pub struct Object {
+    // board coordinates of the live cells
+    pub cells: Vec<(usize, usize)>,
+    pub apgcode: String,
+    pub name: Option<&'static str>,
+}
+
+impl Object {
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+// Split a size * size board into groups of live cells. Cells no more than
+// distance apart in both axes are in the same group, so distance 1 is plain
+// 8-connectivity. Groups are connected across the board edges like the games.
+pub fn separate(size: usize, cells: &[bool], distance: usize) -> Vec<Vec<(usize, usize)>> {
+    components(size, cells, distance)
+        .into_iter()
+        .map(|group| group.into_iter().map(|(cell, _)| cell).collect())
+        .collect()
+}
+
+// Groups as board coordinates paired with coordinates that keep stepping
+// past the board edges, so a group straddling an edge stays in one piece.
+fn components(size: usize, cells: &[bool], distance: usize) -> Vec<Vec<((usize, usize), Cell)>> {
+    debug_assert!(size * size == cells.len());
+    let reach = distance.min(size / 2) as isize;
+    let mut visited = vec![false; cells.len()];
+    let mut groups = Vec::new();
+
+    for start in 0..cells.len() {
+        if !cells[start] || visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let origin = ((start % size) as isize, (start / size) as isize);
+        let mut group = Vec::new();
+        let mut pending = vec![(start, origin)];
+        while let Some((i, (ux, uy))) = pending.pop() {
+            let (x, y) = (i % size, i / size);
+            group.push(((x, y), (ux, uy)));
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    let nx = (x as isize + dx).rem_euclid(size as isize) as usize;
+                    let ny = (y as isize + dy).rem_euclid(size as isize) as usize;
+                    let n = ny * size + nx;
+                    if cells[n] && !visited[n] {
+                        visited[n] = true;
+                        pending.push((n, (ux + dx, uy + dy)));
+                    }
+                }
+            }
+        }
+        group.sort_unstable_by_key(|((x, y), _)| (*y, *x));
+        groups.push(group);
+    }
+    groups
+}
+
+// Separate the game's board into objects and classify each of them.
+pub fn objects(game: &dyn Gol, distance: usize) -> Vec<Object> {
+    let mut classifier = Classifier::new(game.rule().clone());
+    classifier.objects(game.size(), &game.to_vec(), distance)
+}
+
+// Classifies objects by running them in isolation under a rule, B3/S23 by
+// default, remembering the results so repeated objects are only run once.
+#[derive(Default)]
+pub struct Classifier {
+    rule: Rule,
+    cache: HashMap<Pattern, String>,
+}
+
+impl Classifier {
+    pub fn new(rule: Rule) -> Classifier {
+        Classifier {
+            rule,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn objects(&mut self, size: usize, cells: &[bool], distance: usize) -> Vec<Object> {
+        components(size, cells, distance)
+            .into_iter()
+            .map(|group| {
+                let (cells, unwrapped): (Vec<_>, Vec<_>) = group.into_iter().unzip();
+                let apgcode = self.apgcode(&unwrapped);
+                // the names are those of B3/S23 objects
+                let name = if self.rule == Rule::life() {
+                    common_name(&apgcode)
+                } else {
+                    None
+                };
+                Object {
+                    cells,
+                    name,
+                    apgcode,
+                }
+            })
+            .collect()
+    }
+
+    // apgcode of a single object given as live cell coordinates.
+    pub fn apgcode(&mut self, cells: &[Cell]) -> String {
+        let pattern = Pattern::from_cells(cells.iter().copied());
+        if let Some(code) = self.cache.get(&pattern) {
+            return code.clone();
+        }
+        let code = apgcode(&pattern, &self.rule);
+        self.cache.insert(pattern, code.clone());
+        code
+    }
+}
+
+// Extended Wechsler encoding of a cropped pattern: strips of 5 rows, one
+// base 32 digit per column, runs of zeros shortened and strips split by 'z'.
+pub fn wechsler(pattern: &Pattern) -> String {
+    let strips = pattern.height.div_ceil(5);
+
+    let mut columns = vec![vec![0u8; pattern.width]; strips];
+    for (x, y) in pattern.live_cells() {
+        columns[y / 5][x] |= 1 << (y % 5);
+    }
+
+    let encoded: Vec<String> = columns
+        .iter()
+        .map(|strip| {
+            let digits: Vec<u8> = strip.iter().map(|v| WECHSLER_DIGITS[*v as usize]).collect();
+            let len = digits.iter().rposition(|d| *d != b'0').map_or(0, |i| i + 1);
+            compress_zeros(&digits[..len])
+        })
+        .collect();
+    encoded.join("z")
+}
+
+fn compress_zeros(digits: &[u8]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+    while i < digits.len() {
+        if digits[i] != b'0' {
+            out.push(digits[i] as char);
+            i += 1;
+            continue;
+        }
+        let run = digits[i..].iter().take_while(|d| **d == b'0').count();
+        let mut left = run;
+        while left > 0 {
+            let used = left.min(39);
+            match used {
+                1 => out.push('0'),
+                2 => out.push('w'),
+                3 => out.push('x'),
+                _ => {
+                    out.push('y');
+                    out.push(WECHSLER_DIGITS[used - 4] as char);
+                }
+            }
+            left -= used;
+        }
+        i += run;
+    }
+    out
+}
+
+// Shortest, then alphabetically first, encoding over the given phases and
+// all their orientations.
+fn canonical(phases: &[Pattern]) -> String {
+    phases
+        .iter()
+        .flat_map(|phase| Orientation::ALL.map(|orientation| phase.oriented(orientation)))
+        .map(|pattern| wechsler(&pattern))
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .unwrap_or_default()
+}
+
+// Run the cropped pattern in isolation and build its apgcode.
+fn apgcode(pattern: &Pattern, rule: &Rule) -> String {
+    // even, so Margolus blocks fit
+    let size = (pattern.width.max(pattern.height) + 2 * ISOLATION_PADDING).next_multiple_of(2);
+    let board = pattern.board(
+        size,
+        ISOLATION_PADDING,
+        ISOLATION_PADDING,
+        Orientation::Identity,
+    );
+    let mut game = cpu::GameState::from_slice(size, &board);
+    if game.set_rule(rule.clone()).is_err() {
+        return "zz_UNKNOWN".to_string();
+    }
+
+    let outcome = analysis::classify(&game, MAX_GENERATIONS);
+    let (prefix, period, preperiod) = match outcome {
+        Outcome::Still { preperiod } => ("xs", 1, preperiod),
+        Outcome::Oscillator { period, preperiod } => ("xp", period, preperiod),
+        Outcome::Spaceship {
+            period, preperiod, ..
+        } => ("xq", period, preperiod),
+        Outcome::Dies { .. } => return "zz_DIES".to_string(),
+        Outcome::Unresolved { .. } => return "zz_UNKNOWN".to_string(),
+    };
+
+    // collect every phase of the cycle, starting once it has been reached
+    let mut state: Box<dyn Gol> = Box::new(game);
+    for _ in 0..preperiod {
+        state = state.to_next();
+    }
+    let mut phases = Vec::with_capacity(period);
+    for _ in 0..period {
+        let phase = analysis::normalize(size, &state.to_vec())
+            .cells
+            .into_iter()
+            .map(|(y, x)| (x as isize, y as isize));
+        phases.push(Pattern::from_cells(phase));
+        state = state.to_next();
+    }
+
+    if prefix == "xs" {
+        format!("xs{}_{}", phases[0].population(), canonical(&phases))
+    } else {
+        format!("{}{}_{}", prefix, period, canonical(&phases))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::margolus::Margolus;
+
+    fn board(size: usize, live: &[(usize, usize)]) -> Vec<bool> {
+        let mut cells = vec![false; size * size];
+        for (x, y) in live {
+            cells[y * size + x] = true;
+        }
+        cells
+    }
+
+    #[test]
+    fn separates_and_names_objects() {
+        // block, blinker and a glider wrapped around the corner
+        let cells = board(
+            16,
+            &[
+                (5, 5),
+                (6, 5),
+                (5, 6),
+                (6, 6),
+                (10, 10),
+                (11, 10),
+                (12, 10),
+                (0, 15),
+                (1, 0),
+                (15, 1),
+                (0, 1),
+                (1, 1),
+            ],
+        );
+        let mut names: Vec<_> = Classifier::default()
+            .objects(16, &cells, 1)
+            .iter()
+            .map(|o| (o.apgcode.clone(), o.name.unwrap(), o.population()))
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                ("xp2_7".to_string(), "blinker", 3),
+                ("xq4_153".to_string(), "glider", 5),
+                ("xs4_33".to_string(), "block", 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn distance_joins_nearby_cells() {
+        // two blocks with a one cell gap between them
+        let cells = board(
+            8,
+            &[
+                (0, 0),
+                (1, 0),
+                (0, 1),
+                (1, 1),
+                (3, 0),
+                (4, 0),
+                (3, 1),
+                (4, 1),
+            ],
+        );
+        assert_eq!(separate(8, &cells, 1).len(), 2);
+        assert_eq!(separate(8, &cells, 2).len(), 1);
+    }
+
+    #[test]
+    fn zero_runs() {
+        assert_eq!(compress_zeros(b"1001"), "1w1");
+        assert_eq!(compress_zeros(b"10001"), "1x1");
+        assert_eq!(compress_zeros(b"1000001"), "1y11");
+    }
+
+    #[test]
+    fn classifies_under_the_game_rule() {
+        // a block and a blinker
+        let cells = board(
+            16,
+            &[(2, 2), (3, 2), (2, 3), (3, 3), (9, 9), (10, 9), (11, 9)],
+        );
+        let codes = |rule: &str| {
+            let mut game = cpu::GameState::from_slice(16, &cells);
+            game.set_rule(rule.parse().unwrap()).unwrap();
+            let mut codes: Vec<_> = objects(&game, 1)
+                .into_iter()
+                .map(|o| (o.apgcode, o.name))
+                .collect();
+            codes.sort();
+            codes
+        };
+        assert_eq!(
+            codes("B3/S23"),
+            [
+                ("xp2_7".to_string(), Some("blinker")),
+                ("xs4_33".to_string(), Some("block"))
+            ]
+        );
+        // HighLife shares the codes but not the names
+        assert_eq!(
+            codes("B36/S23"),
+            [("xp2_7".to_string(), None), ("xs4_33".to_string(), None)]
+        );
+        // both die out under B3/S1
+        assert_eq!(
+            codes("B3/S1"),
+            [("zz_DIES".to_string(), None), ("zz_DIES".to_string(), None)]
+        );
+    }
+
+    #[test]
+    fn isolation_boards_fit_margolus_blocks() {
+        // an odd sized object still gets an even board to run on
+        let mut classifier = Classifier::new(Rule::Margolus(Margolus::tron()));
+        let code = classifier.apgcode(&[(0, 0), (1, 0), (2, 0)]);
+        assert!(!code.is_empty());
+    }
+
+    #[test]
+    fn marked_history_cells_are_alive() {
+        // a block made of plain, marked and starting cells
+        let mut states = vec![0; 16 * 16];
+        states[2 * 16 + 2..2 * 16 + 4].copy_from_slice(&[1, 3]);
+        states[3 * 16 + 2..3 * 16 + 4].copy_from_slice(&[5, 1]);
+        let mut game = cpu::GameState::from_states(16, &states);
+        game.set_rule("LifeHistory".parse().unwrap()).unwrap();
+        let found = objects(&game, 1);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].apgcode, "xs4_33");
+        assert_eq!(found[0].population(), 4);
+    }
+}
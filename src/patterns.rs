@@ -0,0 +1,727 @@
+use crate::{Gol, Region};
+
+pub mod rle;
+
+// How a pattern is turned before it is placed. Rotations are clockwise with
+// y growing downwards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    // mirrored left to right
+    FlipHorizontal,
+    // mirrored top to bottom
+    FlipVertical,
+    // mirrored along the top left to bottom right diagonal
+    FlipDiagonal,
+    // mirrored along the top right to bottom left diagonal
+    FlipAntiDiagonal,
+}
+
+impl Orientation {
+    pub const ALL: [Orientation; 8] = [
+        Orientation::Identity,
+        Orientation::Rotate90,
+        Orientation::Rotate180,
+        Orientation::Rotate270,
+        Orientation::FlipHorizontal,
+        Orientation::FlipVertical,
+        Orientation::FlipDiagonal,
+        Orientation::FlipAntiDiagonal,
+    ];
+
+    fn swaps_axes(self) -> bool {
+        matches!(
+            self,
+            Orientation::Rotate90
+                | Orientation::Rotate270
+                | Orientation::FlipDiagonal
+                | Orientation::FlipAntiDiagonal
+        )
+    }
+
+    // Where cell x, y of a width * height pattern ends up.
+    fn map(self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Orientation::Identity => (x, y),
+            Orientation::Rotate90 => (height - 1 - y, x),
+            Orientation::Rotate180 => (width - 1 - x, height - 1 - y),
+            Orientation::Rotate270 => (y, width - 1 - x),
+            Orientation::FlipHorizontal => (width - 1 - x, y),
+            Orientation::FlipVertical => (x, height - 1 - y),
+            Orientation::FlipDiagonal => (y, x),
+            Orientation::FlipAntiDiagonal => (height - 1 - y, width - 1 - x),
+        }
+    }
+}
+
+// How pasted cells are combined with the cells already on the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PasteMode {
+    // only ever adds live cells
+    Or,
+    // keeps cells that are alive in both
+    And,
+    // toggles the cells the pattern has alive
+    Xor,
+    // replaces the board cells, dead ones included
+    Copy,
+}
+
+impl PasteMode {
+    pub fn combine(self, board: bool, pasted: bool) -> bool {
+        match self {
+            PasteMode::Or => board || pasted,
+            PasteMode::And => board && pasted,
+            PasteMode::Xor => board != pasted,
+            PasteMode::Copy => pasted,
+        }
+    }
+
+    // Combine with a cell of a board that may have more than two states.
+    // Cells whose liveness doesn't change keep their state.
+    pub fn combine_state(self, state: u8, pasted: bool) -> u8 {
+        let alive = state == 1;
+        match self.combine(alive, pasted) {
+            combined if combined == alive && self != PasteMode::Copy => state,
+            combined => combined as u8,
+        }
+    }
+}
+
+// A rectangle of cells, row major like the boards.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<bool>,
+    // generations until the pattern repeats, possibly moved. None when it
+    // isn't known or the pattern never repeats.
+    pub period: Option<usize>,
+}
+
+impl Pattern {
+    // Plain text rows, 'O' or '*' for live cells. Short rows are padded with
+    // dead cells.
+    pub fn from_rows(rows: &[&str]) -> Pattern {
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        let height = rows.len();
+        let mut cells = vec![false; width * height];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                cells[y * width + x] = c == 'O' || c == '*';
+            }
+        }
+        Pattern {
+            width,
+            height,
+            cells,
+            period: None,
+        }
+    }
+
+    // A whole size * size board as a pattern.
+    pub fn from_board(size: usize, cells: &[bool]) -> Pattern {
+        debug_assert!(size * size == cells.len());
+        Pattern {
+            width: size,
+            height: size,
+            cells: cells.to_vec(),
+            period: None,
+        }
+    }
+
+    pub fn from_game(game: &dyn Gol) -> Pattern {
+        Pattern::from_board(game.size(), &game.to_vec())
+    }
+
+    // The smallest pattern holding the given live cells.
+    pub fn from_cells(cells: impl IntoIterator<Item = (isize, isize)>) -> Pattern {
+        let cells: Vec<(isize, isize)> = cells.into_iter().collect();
+        let min_x = cells.iter().map(|c| c.0).min().unwrap_or(0);
+        let min_y = cells.iter().map(|c| c.1).min().unwrap_or(0);
+        let width = cells
+            .iter()
+            .map(|c| (c.0 - min_x + 1) as usize)
+            .max()
+            .unwrap_or(0);
+        let height = cells
+            .iter()
+            .map(|c| (c.1 - min_y + 1) as usize)
+            .max()
+            .unwrap_or(0);
+        let mut pattern = Pattern::empty(width, height);
+        for (x, y) in cells {
+            pattern.cells[(y - min_y) as usize * width + (x - min_x) as usize] = true;
+        }
+        pattern
+    }
+
+    pub fn empty(width: usize, height: usize) -> Pattern {
+        Pattern {
+            width,
+            height,
+            cells: vec![false; width * height],
+            period: None,
+        }
+    }
+
+    pub fn with_period(mut self, period: usize) -> Pattern {
+        self.period = Some(period);
+        self
+    }
+
+    pub fn is_alive(&self, x: usize, y: usize) -> bool {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|alive| **alive).count()
+    }
+
+    // (x, y) of every live cell, row by row.
+    pub fn live_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, alive)| **alive)
+            .map(|(i, _)| (i % self.width, i / self.width))
+    }
+
+    pub fn oriented(&self, orientation: Orientation) -> Pattern {
+        let (width, height) = if orientation.swaps_axes() {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        let mut cells = vec![false; width * height];
+        for (x, y) in self.live_cells() {
+            let (x, y) = orientation.map(x, y, self.width, self.height);
+            cells[y * width + x] = true;
+        }
+        Pattern {
+            width,
+            height,
+            cells,
+            period: self.period,
+        }
+    }
+
+    pub fn rotate_90(&self) -> Pattern {
+        self.oriented(Orientation::Rotate90)
+    }
+
+    pub fn rotate_180(&self) -> Pattern {
+        self.oriented(Orientation::Rotate180)
+    }
+
+    pub fn rotate_270(&self) -> Pattern {
+        self.oriented(Orientation::Rotate270)
+    }
+
+    pub fn flip_horizontal(&self) -> Pattern {
+        self.oriented(Orientation::FlipHorizontal)
+    }
+
+    pub fn flip_vertical(&self) -> Pattern {
+        self.oriented(Orientation::FlipVertical)
+    }
+
+    pub fn flip_diagonal(&self) -> Pattern {
+        self.oriented(Orientation::FlipDiagonal)
+    }
+
+    // Move every cell by dx, dy. Cells pushed over an edge come back on the
+    // opposite side, the same way boards wrap.
+    pub fn translate(&self, dx: isize, dy: isize) -> Pattern {
+        let mut moved = Pattern::empty(self.width, self.height);
+        moved.period = self.period;
+        for (x, y) in self.live_cells() {
+            let x = (x as isize + dx).rem_euclid(self.width as isize) as usize;
+            let y = (y as isize + dy).rem_euclid(self.height as isize) as usize;
+            moved.cells[y * self.width + x] = true;
+        }
+        moved
+    }
+
+    // Smallest rectangle holding every live cell, None if there are none.
+    pub fn bounding_box(&self) -> Option<Region> {
+        let mut cells = self.live_cells();
+        let (x, y) = cells.next()?;
+        let (mut min_x, mut max_x, mut max_y) = (x, x, y);
+        for (x, y) in cells {
+            min_x = min_x.min(x);
+            max_x = max_x.max(x);
+            max_y = y;
+        }
+        Some(Region {
+            x: min_x,
+            y,
+            width: max_x - min_x + 1,
+            height: max_y - y + 1,
+        })
+    }
+
+    pub fn crop(&self) -> Pattern {
+        let Some(region) = self.bounding_box() else {
+            return Pattern::empty(0, 0);
+        };
+        self.region(region)
+    }
+
+    // The cells inside a region, wrapping around the edges.
+    pub fn region(&self, region: Region) -> Pattern {
+        let mut cut = Pattern::empty(region.width, region.height);
+        cut.period = self.period;
+        for y in 0..region.height {
+            for x in 0..region.width {
+                let (sx, sy) = ((region.x + x) % self.width, (region.y + y) % self.height);
+                cut.cells[y * region.width + x] = self.is_alive(sx, sy);
+            }
+        }
+        cut
+    }
+
+    // Grow to width * height with the pattern's top left corner at x, y.
+    pub fn pad(&self, width: usize, height: usize, x: usize, y: usize) -> Pattern {
+        assert!(
+            x + self.width <= width && y + self.height <= height,
+            "pattern does not fit"
+        );
+        let mut padded = Pattern::empty(width, height);
+        padded.period = self.period;
+        for (px, py) in self.live_cells() {
+            padded.cells[(y + py) * width + x + px] = true;
+        }
+        padded
+    }
+
+    // A square pattern as a game of the same size.
+    pub fn game<T: Gol>(&self) -> T {
+        assert!(self.width == self.height, "boards are square");
+        T::from_slice(self.width, &self.cells)
+    }
+
+    // Set the live cells of the pattern on a size * size board with its top
+    // left corner at x, y, wrapping around the edges. Cells the pattern has
+    // dead are left as they were.
+    pub fn stamp(
+        &self,
+        size: usize,
+        board: &mut [bool],
+        x: usize,
+        y: usize,
+        orientation: Orientation,
+    ) {
+        debug_assert!(size * size == board.len());
+        let oriented = self.oriented(orientation);
+        for (px, py) in oriented.live_cells() {
+            board[((y + py) % size) * size + (x + px) % size] = true;
+        }
+    }
+
+    // Combine the whole rectangle of the pattern into a size * size board with
+    // its top left corner at x, y, wrapping around the edges.
+    pub fn paste(&self, size: usize, board: &mut [bool], x: usize, y: usize, mode: PasteMode) {
+        debug_assert!(size * size == board.len());
+        let region = Region {
+            x,
+            y,
+            width: self.width,
+            height: self.height,
+        };
+        for py in 0..self.height {
+            for px in 0..self.width {
+                let i = region.index(size, px, py);
+                board[i] = mode.combine(board[i], self.is_alive(px, py));
+            }
+        }
+    }
+
+    // Same as paste for boards of cell states.
+    pub fn paste_states(&self, size: usize, board: &mut [u8], x: usize, y: usize, mode: PasteMode) {
+        debug_assert!(size * size == board.len());
+        let region = Region {
+            x,
+            y,
+            width: self.width,
+            height: self.height,
+        };
+        for py in 0..self.height {
+            for px in 0..self.width {
+                let i = region.index(size, px, py);
+                board[i] = mode.combine_state(board[i], self.is_alive(px, py));
+            }
+        }
+    }
+
+    // An otherwise empty size * size board holding the pattern.
+    pub fn board(&self, size: usize, x: usize, y: usize, orientation: Orientation) -> Vec<bool> {
+        let mut board = vec![false; size * size];
+        self.stamp(size, &mut board, x, y, orientation);
+        board
+    }
+
+    // Copy of the game with the pattern added.
+    pub fn place<T: Gol>(&self, game: &T, x: usize, y: usize, orientation: Orientation) -> T {
+        let size = game.size();
+        let mut states = game.to_states();
+        self.oriented(orientation)
+            .paste_states(size, &mut states, x, y, PasteMode::Or);
+        let mut placed = T::from_states(size, &states);
+        placed
+            .set_rule(game.rule().clone())
+            .expect("the rule already runs on a board of this size");
+        placed
+    }
+}
+
+// Still lifes
+
+pub fn block() -> Pattern {
+    Pattern::from_rows(&["OO", "OO"]).with_period(1)
+}
+
+pub fn tub() -> Pattern {
+    Pattern::from_rows(&[".O.", "O.O", ".O."]).with_period(1)
+}
+
+pub fn beehive() -> Pattern {
+    Pattern::from_rows(&[".OO.", "O..O", ".OO."]).with_period(1)
+}
+
+// Oscillators
+
+pub fn blinker() -> Pattern {
+    Pattern::from_rows(&["OOO"]).with_period(2)
+}
+
+pub fn toad() -> Pattern {
+    Pattern::from_rows(&[".OOO", "OOO."]).with_period(2)
+}
+
+pub fn beacon() -> Pattern {
+    Pattern::from_rows(&["OO..", "OO..", "..OO", "..OO"]).with_period(2)
+}
+
+pub fn pulsar() -> Pattern {
+    Pattern::from_rows(&[
+        "..OOO...OOO..",
+        ".............",
+        "O....O.O....O",
+        "O....O.O....O",
+        "O....O.O....O",
+        "..OOO...OOO..",
+        ".............",
+        "..OOO...OOO..",
+        "O....O.O....O",
+        "O....O.O....O",
+        "O....O.O....O",
+        ".............",
+        "..OOO...OOO..",
+    ])
+    .with_period(3)
+}
+
+pub fn pentadecathlon() -> Pattern {
+    Pattern::from_rows(&["..O....O..", "OO.OOOO.OO", "..O....O.."]).with_period(15)
+}
+
+// Spaceships, the glider heads down and right, the others to the left
+
+pub fn glider() -> Pattern {
+    Pattern::from_rows(&[".O.", "..O", "OOO"]).with_period(4)
+}
+
+pub fn lwss() -> Pattern {
+    Pattern::from_rows(&[".O..O", "O....", "O...O", "OOOO."]).with_period(4)
+}
+
+pub fn mwss() -> Pattern {
+    Pattern::from_rows(&["...O..", ".O...O", "O.....", "O....O", "OOOOO."]).with_period(4)
+}
+
+pub fn hwss() -> Pattern {
+    Pattern::from_rows(&["...OO..", ".O....O", "O......", "O.....O", "OOOOOO."]).with_period(4)
+}
+
+// Patterns that never repeat
+
+// emits a glider down and right every 30 generations
+pub fn gosper_glider_gun() -> Pattern {
+    Pattern::from_rows(&[
+        "........................O...........",
+        "......................O.O...........",
+        "............OO......OO............OO",
+        "...........O...O....OO............OO",
+        "OO........O.....O...OO..............",
+        "OO........O...O.OO....O.O...........",
+        "..........O.....O.......O...........",
+        "...........O...O....................",
+        "............OO......................",
+    ])
+}
+
+// methuselah that settles after 1103 generations on an unbounded plane
+pub fn r_pentomino() -> Pattern {
+    Pattern::from_rows(&[".OO", "OO.", ".O."])
+}
+
+// methuselah that settles after 5206 generations on an unbounded plane
+pub fn acorn() -> Pattern {
+    Pattern::from_rows(&[".O.....", "...O...", "OO..OOO"])
+}
+
+// dies out after 130 generations
+pub fn diehard() -> Pattern {
+    Pattern::from_rows(&["......O.", "OO......", ".O...OOO"])
+}
+
+// Every pattern above by name.
+pub fn library() -> Vec<(&'static str, Pattern)> {
+    vec![
+        ("block", block()),
+        ("tub", tub()),
+        ("beehive", beehive()),
+        ("blinker", blinker()),
+        ("toad", toad()),
+        ("beacon", beacon()),
+        ("pulsar", pulsar()),
+        ("pentadecathlon", pentadecathlon()),
+        ("glider", glider()),
+        ("lightweight spaceship", lwss()),
+        ("middleweight spaceship", mwss()),
+        ("heavyweight spaceship", hwss()),
+        ("gosper glider gun", gosper_glider_gun()),
+        ("r-pentomino", r_pentomino()),
+        ("acorn", acorn()),
+        ("diehard", diehard()),
+    ]
+}
+
+pub fn named(name: &str) -> Option<Pattern> {
+    library()
+        .into_iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, pattern)| pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{self, Outcome};
+    use crate::game_impls::{cpu, cpu_ndarray};
+    use crate::objects::{common_name, Classifier};
+    use crate::rules::{table::RuleTable, Rule};
+    use std::sync::Arc;
+
+    #[test]
+    fn orientations_keep_cells() {
+        let glider = glider();
+        assert_eq!(
+            glider.oriented(Orientation::Rotate90),
+            Pattern::from_rows(&["O..", "O.O", "OO."]).with_period(4)
+        );
+        for orientation in Orientation::ALL {
+            assert_eq!(glider.oriented(orientation).population(), 5);
+        }
+        let quarter = |p: Pattern| p.oriented(Orientation::Rotate90);
+        assert_eq!(quarter(quarter(quarter(quarter(glider.clone())))), glider);
+        assert_eq!(
+            glider
+                .oriented(Orientation::Rotate90)
+                .oriented(Orientation::Rotate270),
+            glider
+        );
+        let hwss = hwss();
+        assert_eq!(hwss.oriented(Orientation::Rotate90).width, hwss.height);
+    }
+
+    #[test]
+    fn transformations() {
+        let r = r_pentomino();
+        assert_eq!(
+            r.flip_horizontal(),
+            Pattern::from_rows(&["OO.", ".OO", ".O."])
+        );
+        assert_eq!(
+            r.flip_vertical(),
+            Pattern::from_rows(&[".O.", "OO.", ".OO"])
+        );
+        assert_eq!(
+            r.flip_diagonal(),
+            Pattern::from_rows(&[".O.", "OOO", "O.."])
+        );
+        assert_eq!(r.rotate_180(), r.flip_horizontal().flip_vertical());
+        assert_eq!(r.rotate_270(), r.rotate_90().rotate_180());
+
+        assert_eq!(
+            blinker().translate(-1, 0),
+            Pattern::from_rows(&["OOO"]).with_period(2)
+        );
+        assert_eq!(
+            Pattern::from_rows(&["O..", "...", "..."]).translate(-1, 2),
+            Pattern::from_rows(&["...", "...", "..O"])
+        );
+
+        let padded = glider().pad(8, 6, 4, 2);
+        assert_eq!(
+            padded.bounding_box(),
+            Some(Region {
+                x: 4,
+                y: 2,
+                width: 3,
+                height: 3
+            })
+        );
+        assert_eq!(padded.crop(), glider());
+        assert_eq!(Pattern::empty(4, 4).crop(), Pattern::empty(0, 0));
+    }
+
+    #[test]
+    fn rotated_board_runs_the_same() {
+        let board = glider().board(12, 2, 3, Orientation::Identity);
+        let game: cpu::GameState = Pattern::from_board(12, &board).rotate_90().game();
+        let next = Pattern::from_game(game.to_next().as_ref()).rotate_270();
+        let expected = cpu::GameState::from_slice(12, &board).to_next();
+        assert_eq!(next, Pattern::from_game(expected.as_ref()));
+    }
+
+    #[test]
+    fn copy_and_paste_between_backends() {
+        let block = block().board(16, 2, 2, Orientation::Identity);
+        let source = cpu_ndarray::GameState::from_slice(16, &block);
+        let copied = source.copy_region(Region {
+            x: 1,
+            y: 1,
+            width: 4,
+            height: 4,
+        });
+        assert_eq!(copied, block_with_border());
+
+        // a glider aimed at the block, pasted across the wrap
+        let target =
+            cpu::GameState::from_slice(16, &glider().board(16, 14, 14, Orientation::Identity));
+        let pasted = target.paste(&copied, 8, 8, PasteMode::Or);
+        let cells = pasted.to_vec();
+        assert_eq!(cells.iter().filter(|alive| **alive).count(), 9);
+        assert!(cells[9 * 16 + 9] && cells[10 * 16 + 10]);
+
+        let erased = pasted.paste(&Pattern::empty(5, 5), 13, 13, PasteMode::Copy);
+        assert_eq!(
+            Pattern::from_game(erased.as_ref()).crop(),
+            Pattern::from_rows(&["OO", "OO"])
+        );
+        let toggled = erased.paste(&copied, 8, 8, PasteMode::Xor);
+        assert!(!toggled.to_vec().contains(&true));
+        let masked = pasted.paste(&copied, 8, 8, PasteMode::And);
+        assert_eq!(masked.to_vec(), pasted.to_vec());
+        let masked = pasted.paste(&Pattern::empty(5, 5), 13, 13, PasteMode::And);
+        assert_eq!(masked.to_vec(), erased.to_vec());
+    }
+
+    fn block_with_border() -> Pattern {
+        Pattern::from_rows(&["....", ".OO.", ".OO.", "...."])
+    }
+
+    #[test]
+    fn library_periods() {
+        let mut classifier = Classifier::default();
+        for (name, pattern) in library() {
+            let Some(period) = pattern.period else {
+                continue;
+            };
+            let game =
+                cpu::GameState::from_slice(40, &pattern.board(40, 12, 12, Orientation::Identity));
+            let outcome = analysis::classify(&game, period * 2);
+            assert_eq!(outcome.period(), Some(period), "{}", name);
+            assert!(!matches!(outcome, Outcome::Unresolved { .. }), "{}", name);
+
+            let cells: Vec<_> = pattern
+                .live_cells()
+                .map(|(x, y)| (x as isize, y as isize))
+                .collect();
+            let code = classifier.apgcode(&cells);
+            assert_eq!(common_name(&code), Some(name));
+        }
+    }
+
+    #[test]
+    fn diehard_dies() {
+        let game =
+            cpu::GameState::from_slice(64, &diehard().board(64, 28, 30, Orientation::Identity));
+        assert_eq!(
+            analysis::classify(&game, 200),
+            Outcome::Dies { generation: 130 }
+        );
+    }
+
+    #[test]
+    fn settling_outcomes() {
+        let game = cpu::GameState::from_slice(16, &block().board(16, 3, 3, Orientation::Identity));
+        assert_eq!(
+            analysis::classify(&game, 10),
+            Outcome::Still { preperiod: 0 }
+        );
+
+        // three cells of a block fill in the fourth
+        let corner = Pattern::from_rows(&["OO", "O."]);
+        let game = cpu::GameState::from_slice(16, &corner.board(16, 3, 3, Orientation::Identity));
+        assert_eq!(
+            analysis::classify(&game, 10),
+            Outcome::Still { preperiod: 1 }
+        );
+
+        let game =
+            cpu::GameState::from_slice(64, &r_pentomino().board(64, 30, 30, Orientation::Identity));
+        assert_eq!(
+            analysis::classify(&game, 20),
+            Outcome::Unresolved { generations: 20 }
+        );
+    }
+
+    #[test]
+    fn every_state_is_classified() {
+        // a Wireworld wire without electrons has no cells in state 1
+        let mut states = vec![0; 16 * 16];
+        states[16 * 4 + 2..16 * 4 + 9].fill(3);
+        let mut wire = cpu::GameState::from_states(16, &states);
+        wire.set_rule(Rule::Table(Arc::new(RuleTable::wireworld())))
+            .unwrap();
+        assert_eq!(
+            analysis::classify(&wire, 10),
+            Outcome::Still { preperiod: 0 }
+        );
+
+        // a dying Brian's Brain cell is still part of the pattern
+        let mut brain = cpu::GameState::from_states(
+            16,
+            &states.iter().map(|state| state / 3 * 2).collect::<Vec<_>>(),
+        );
+        brain.set_rule("/2/3".parse().unwrap()).unwrap();
+        assert_eq!(
+            analysis::classify(&brain, 10),
+            Outcome::Dies { generation: 1 }
+        );
+
+        // second order boards repeat only when the generation before does too
+        let mut blinker_board =
+            cpu::GameState::from_slice(16, &blinker().board(16, 5, 5, Orientation::Identity));
+        blinker_board
+            .set_rule(Rule::SecondOrder("B3/S23".parse().unwrap()))
+            .unwrap();
+        assert_eq!(
+            analysis::classify(&blinker_board, 40),
+            Outcome::Oscillator {
+                period: 6,
+                preperiod: 0
+            }
+        );
+    }
+}
@@ -0,0 +1,349 @@
+use super::Pattern;
+use crate::rules::{BoardSizeError, Rule};
+use crate::Gol;
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+// Golly keeps the lines of a pattern's cells shorter than this.
+const LINE_LENGTH: usize = 70;
+
+// Run length encoded pattern as Golly reads and writes it. Two state
+// patterns use b for dead and o for live cells, patterns with more states
+// use . for 0, A to X for 1 to 24 and pA to yO for the states after that.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rle {
+    pub width: usize,
+    pub height: usize,
+    // None when the header leaves it out
+    pub rule: Option<Rule>,
+    // a state per cell, row major
+    pub states: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseRleError {
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+impl Display for ParseRleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid RLE on line {}: {}", self.line, self.reason)
+    }
+}
+
+impl Error for ParseRleError {}
+
+impl Rle {
+    // The whole board with its rule.
+    pub fn from_game(game: &dyn Gol) -> Rle {
+        Rle {
+            width: game.size(),
+            height: game.size(),
+            rule: Some(game.rule().clone()),
+            states: game.to_states(),
+        }
+    }
+
+    pub fn from_pattern(pattern: &Pattern) -> Rle {
+        Rle {
+            width: pattern.width,
+            height: pattern.height,
+            rule: None,
+            states: pattern.cells.iter().map(|alive| *alive as u8).collect(),
+        }
+    }
+
+    // Cells in state 1 are the live ones, or the odd states for history rules.
+    pub fn to_pattern(&self) -> Pattern {
+        let rule = self.rule.clone().unwrap_or_default();
+        Pattern {
+            width: self.width,
+            height: self.height,
+            cells: self.states.iter().map(|state| rule.alive(*state)).collect(),
+            period: None,
+        }
+    }
+
+    // size * size board with the pattern's top left corner at x, y, wrapping
+    // around the edges, running the pattern's rule when it has one. Parts
+    // that don't fit on the board are dropped.
+    pub fn game<T: Gol>(&self, size: usize, x: usize, y: usize) -> Result<T, BoardSizeError> {
+        let mut states = vec![0; size * size];
+        for row in 0..self.height.min(size) {
+            for column in 0..self.width.min(size) {
+                states[((y + row) % size) * size + (x + column) % size] =
+                    self.states[row * self.width + column];
+            }
+        }
+        let mut game = T::from_states(size, &states);
+        if let Some(rule) = &self.rule {
+            game.set_rule(rule.clone())?;
+        }
+        Ok(game)
+    }
+
+    fn multistate(&self) -> bool {
+        self.rule.as_ref().is_some_and(|rule| rule.states() > 2)
+            || self.states.iter().any(|state| *state > 1)
+    }
+}
+
+// Cell letters to states, None for anything else.
+fn state_for(prefix: Option<char>, c: char) -> Option<u8> {
+    match (prefix, c) {
+        (None, 'b' | '.') => Some(0),
+        (None, 'o') => Some(1),
+        (_, 'A'..='X') => {
+            let high = prefix.map_or(0, |prefix| prefix as u32 - 'p' as u32 + 1);
+            u8::try_from(high * 24 + c as u32 - 'A' as u32 + 1).ok()
+        }
+        _ => None,
+    }
+}
+
+fn symbol(state: u8, multistate: bool) -> String {
+    match (state, multistate) {
+        (0, false) => "b".to_string(),
+        (_, false) => "o".to_string(),
+        (0, true) => ".".to_string(),
+        (_, true) => {
+            let (high, low) = ((state - 1) / 24, (state - 1) % 24);
+            let letter = char::from(b'A' + low);
+            match high {
+                0 => letter.to_string(),
+                _ => format!("{}{}", char::from(b'p' + high - 1), letter),
+            }
+        }
+    }
+}
+
+impl FromStr for Rle {
+    type Err = ParseRleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        let (header_line, header) = lines.next().ok_or(ParseRleError {
+            line: 1,
+            reason: "missing the x = ..., y = ... header",
+        })?;
+        let error = |line, reason| ParseRleError { line, reason };
+
+        // the rule comes last and can have commas of its own
+        let (sizes, rule) = match header.split_once("rule") {
+            Some((sizes, rule)) => {
+                let rule = rule
+                    .trim_start()
+                    .strip_prefix('=')
+                    .ok_or_else(|| error(header_line, "header parts are key = value"))?;
+                let rule = rule
+                    .trim()
+                    .parse::<Rule>()
+                    .map_err(|_| error(header_line, "unknown rule"))?;
+                (sizes, Some(rule))
+            }
+            None => (header, None),
+        };
+        let (mut width, mut height) = (None, None);
+        for part in sizes.split(',').filter(|part| !part.trim().is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| error(header_line, "header parts are key = value"))?;
+            match key.trim() {
+                "x" => width = value.trim().parse::<usize>().ok(),
+                "y" => height = value.trim().parse::<usize>().ok(),
+                _ => {}
+            }
+        }
+        let (width, height) = width
+            .zip(height)
+            .ok_or_else(|| error(header_line, "the header needs x and y sizes"))?;
+
+        let cells = width
+            .checked_mul(height)
+            .ok_or_else(|| error(header_line, "the header's size is too large"))?;
+        let mut states = vec![0; cells];
+        let (mut x, mut y): (usize, usize) = (0, 0);
+        let mut count: Option<usize> = None;
+        let mut prefix = None;
+        'lines: for (line, text) in lines {
+            for c in text.chars() {
+                let run = count.unwrap_or(1);
+                match c {
+                    '0'..='9' if prefix.is_none() => {
+                        let digit = c as usize - '0' as usize;
+                        count = count
+                            .unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|count| count.checked_add(digit))
+                            .map(Some)
+                            .ok_or_else(|| error(line, "run count is too large"))?;
+                        continue;
+                    }
+                    'p'..='y' if prefix.is_none() => {
+                        prefix = Some(c);
+                        continue;
+                    }
+                    '!' => break 'lines,
+                    '$' => {
+                        (x, y) = (0, y.saturating_add(run));
+                    }
+                    c if c.is_whitespace() => continue,
+                    c => {
+                        let state =
+                            state_for(prefix, c).ok_or_else(|| error(line, "unknown cell"))?;
+                        if state != 0 {
+                            if x.saturating_add(run) > width || y >= height {
+                                return Err(error(line, "cells outside the header's size"));
+                            }
+                            states[y * width + x..y * width + x + run].fill(state);
+                        }
+                        x = x.saturating_add(run);
+                    }
+                }
+                (count, prefix) = (None, None);
+            }
+        }
+
+        Ok(Rle {
+            width,
+            height,
+            rule,
+            states,
+        })
+    }
+}
+
+impl Display for Rle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "x = {}, y = {}", self.width, self.height)?;
+        if let Some(rule) = &self.rule {
+            write!(f, ", rule = {}", rule)?;
+        }
+        writeln!(f)?;
+
+        // runs of cells, with the dead ones ending a row and the rows ending
+        // the pattern left out
+        let multistate = self.multistate();
+        let mut runs: Vec<(usize, String)> = Vec::new();
+        let mut rows_ended = 0;
+        for row in self.states.chunks(self.width.max(1)) {
+            let used = row
+                .iter()
+                .rposition(|state| *state != 0)
+                .map_or(0, |x| x + 1);
+            if used > 0 && rows_ended > 0 {
+                runs.push((rows_ended, "$".to_string()));
+                rows_ended = 0;
+            }
+            for cells in row[..used].chunk_by(|a, b| a == b) {
+                runs.push((cells.len(), symbol(cells[0], multistate)));
+            }
+            rows_ended += 1;
+        }
+        runs.push((1, "!".to_string()));
+
+        let mut line = String::new();
+        for (count, symbol) in runs {
+            let run = match count {
+                1 => symbol,
+                _ => format!("{}{}", count, symbol),
+            };
+            if line.len() + run.len() > LINE_LENGTH {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            line.push_str(&run);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_impls::cpu;
+    use crate::patterns;
+    use crate::rules::history::History;
+
+    #[test]
+    fn reads_golly_patterns() {
+        let rle: Rle = "#N Glider\n#C a comment\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!"
+            .parse()
+            .unwrap();
+        assert_eq!(rle.rule, Some(Rule::life()));
+        assert_eq!(rle.to_pattern().cells, patterns::glider().cells);
+
+        // runs over several lines, blank rows and a missing rule
+        let rle: Rle = "x = 12, y = 4\n12o\n3$\n4bo!".parse().unwrap();
+        assert_eq!(rle.rule, None);
+        assert_eq!(rle.states.iter().filter(|state| **state == 1).count(), 13);
+        assert_eq!(rle.states[3 * 12 + 4], 1);
+
+        assert_eq!(
+            "x = 2, y = 1\n3o!".parse::<Rle>(),
+            Err(ParseRleError {
+                line: 2,
+                reason: "cells outside the header's size"
+            })
+        );
+        assert!("x = 2, y = 1\nbZ!".parse::<Rle>().is_err());
+        let bosco: Rle = "x = 1, y = 1, rule = R5,C0,M1,S34..58,B34..45,NM\no!"
+            .parse()
+            .unwrap();
+        assert!(matches!(bosco.rule, Some(Rule::LargerThanLife(_))));
+        assert!("y = 1\no!".parse::<Rle>().is_err());
+
+        // sizes and run counts that overflow
+        assert_eq!(
+            "x = 4294967296, y = 4294967296\no!".parse::<Rle>(),
+            Err(ParseRleError {
+                line: 1,
+                reason: "the header's size is too large"
+            })
+        );
+        assert_eq!(
+            "x = 2, y = 1\n99999999999999999999999o!".parse::<Rle>(),
+            Err(ParseRleError {
+                line: 2,
+                reason: "run count is too large"
+            })
+        );
+        assert!("x = 2, y = 1\n18446744073709551615bo!"
+            .parse::<Rle>()
+            .is_err());
+    }
+
+    #[test]
+    fn multistate_round_trip() {
+        let states: Vec<u8> = (0..8 * 8).map(|i| (i * 5 % 11) as u8 % 7).collect();
+        let mut game = cpu::GameState::from_states(8, &states);
+        game.set_rule("LifeHistory".parse().unwrap()).unwrap();
+
+        let text = Rle::from_game(&game).to_string();
+        assert!(text.starts_with("x = 8, y = 8, rule = LifeHistory\n"));
+        let body: String = text.lines().skip(1).collect();
+        assert!(body.contains('F') && !body.contains('o'));
+        let rle: Rle = text.parse().unwrap();
+        assert_eq!(rle.rule, Some(Rule::History(History::life())));
+        let read: cpu::GameState = rle.game(8, 0, 0).unwrap();
+        assert_eq!(read.to_states(), states);
+
+        // states past X take a prefix, and long patterns wrap their lines
+        let rle = Rle {
+            width: 200,
+            height: 1,
+            rule: None,
+            states: (0..200).map(|i| (i % 2 * (25 + i / 2)) as u8).collect(),
+        };
+        let text = rle.to_string();
+        assert!(text.contains("pA") && text.contains("tD"));
+        assert!(text.lines().all(|line| line.len() <= LINE_LENGTH));
+        assert_eq!(text.parse(), Ok(rle));
+    }
+}
@@ -0,0 +1,439 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+
+pub mod history;
+pub mod isotropic;
+pub mod larger;
+pub mod margolus;
+pub mod schedule;
+pub mod stochastic;
+pub mod table;
+
+use history::History;
+use isotropic::Isotropic;
+use larger::LargerThanLife;
+use margolus::Margolus;
+use stochastic::Stochastic;
+use table::RuleTable;
+
+// Cells around a cell that can affect it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Neighbourhood {
+    // the 8 surrounding cells
+    Moore,
+    // the 4 orthogonally adjacent cells
+    VonNeumann,
+    // 6 cells of a hexagonal grid drawn skewed onto the square one
+    Hexagonal,
+}
+
+impl Neighbourhood {
+    // (x, y) offsets going clockwise from north, which is the order rule
+    // tables list neighbours in.
+    pub fn offsets(self) -> &'static [(i32, i32)] {
+        match self {
+            Neighbourhood::Moore => &[
+                (0, -1),
+                (1, -1),
+                (1, 0),
+                (1, 1),
+                (0, 1),
+                (-1, 1),
+                (-1, 0),
+                (-1, -1),
+            ],
+            Neighbourhood::VonNeumann => &[(0, -1), (1, 0), (0, 1), (-1, 0)],
+            Neighbourhood::Hexagonal => &[(0, -1), (1, 0), (1, 1), (0, 1), (-1, 0), (-1, -1)],
+        }
+    }
+}
+
+// How every backend decides a cell's next state.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Rule {
+    LifeLike(LifeLike),
+    Isotropic(Isotropic),
+    LargerThanLife(LargerThanLife),
+    // any number of states, from a Golly rule table
+    Table(Arc<RuleTable>),
+    // 2x2 blocks that alternate between even and odd offsets
+    Margolus(Margolus),
+    // Life-like rule XORed with the generation before, so it can be run
+    // backwards. Cells are 0 or 1 and boards keep two generations.
+    SecondOrder(LifeLike),
+    // Life-like rule whose births and survivals happen with a chance
+    Stochastic(Stochastic),
+    // Life-like rule keeping envelope and marked cells in extra states
+    History(History),
+}
+
+impl Rule {
+    pub fn life() -> Rule {
+        Rule::LifeLike(LifeLike::life())
+    }
+
+    // Hexagonal rules are drawn with skewed rows.
+    pub fn hexagonal(&self) -> bool {
+        match self {
+            Rule::LifeLike(rule) | Rule::SecondOrder(rule) => {
+                rule.neighbourhood == Neighbourhood::Hexagonal
+            }
+            Rule::Stochastic(rule) => rule.rule.neighbourhood == Neighbourhood::Hexagonal,
+            Rule::History(rule) => rule.rule.neighbourhood == Neighbourhood::Hexagonal,
+            Rule::Table(table) => table.neighbourhood == Neighbourhood::Hexagonal,
+            _ => false,
+        }
+    }
+
+    pub fn states(&self) -> u8 {
+        match self {
+            Rule::LifeLike(rule) => rule.states,
+            Rule::Isotropic(rule) => rule.states,
+            Rule::LargerThanLife(rule) => rule.states,
+            Rule::Table(table) => table.states,
+            Rule::Margolus(_) | Rule::SecondOrder(_) => 2,
+            Rule::Stochastic(rule) => rule.rule.states,
+            Rule::History(_) => history::STATES,
+        }
+    }
+
+    // Margolus blocks only fit on boards of an even size.
+    pub fn check_size(&self, size: usize) -> Result<(), BoardSizeError> {
+        match self {
+            Rule::Margolus(_) if !size.is_multiple_of(2) => Err(BoardSizeError {
+                size,
+                reason: "Margolus rules need an even board size",
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    // Whether a cell in this state counts as alive outside the rule, in
+    // exported and copied patterns and in object searches.
+    pub fn alive(&self, state: u8) -> bool {
+        match self {
+            Rule::History(_) => History::alive(state),
+            _ => state == 1,
+        }
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::life()
+    }
+}
+
+impl FromStr for Rule {
+    type Err = ParseRuleError;
+
+    // Larger than Life rules are the ones starting with a range, Margolus
+    // rules start with MS, history rules end with History, and isotropic
+    // rules have letters or a '-' after the counts.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hensel = s
+            .split('/')
+            .any(|part| part.chars().skip(1).any(|c| "ceaiknjqrytwz-".contains(c)));
+        let history = s.trim_end().to_ascii_lowercase().ends_with("history");
+        if history {
+            s.parse().map(Rule::History)
+        } else if s.trim_start().starts_with(['M', 'm']) {
+            s.parse().map(Rule::Margolus)
+        } else if s.trim_start().starts_with(['R', 'r']) {
+            s.parse().map(Rule::LargerThanLife)
+        } else if hensel {
+            s.parse().map(Rule::Isotropic)
+        } else {
+            s.parse().map(Rule::LifeLike)
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rule::LifeLike(rule) => rule.fmt(f),
+            Rule::Isotropic(rule) => rule.fmt(f),
+            Rule::LargerThanLife(rule) => rule.fmt(f),
+            Rule::Table(table) => write!(f, "{}", table.name),
+            Rule::Margolus(rule) => rule.fmt(f),
+            Rule::SecondOrder(rule) => write!(f, "{} (second order)", rule),
+            Rule::Stochastic(rule) => rule.fmt(f),
+            Rule::History(rule) => rule.fmt(f),
+        }
+    }
+}
+
+// Outer totalistic rule, on the 8 surrounding cells unless an H or V suffix
+// picks the hexagonal or von Neumann neighbourhood. Cells in state 1 are
+// alive and are the only ones counted as neighbours. With more than 2 states
+// this is a Generations rule: a live cell that doesn't survive goes through
+// the dying states 2, 3, ... before it is dead (0) again, and only dead cells
+// can be born.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LifeLike {
+    // bit n is set when n live neighbours bring a dead cell to life
+    pub birth: u32,
+    // bit n is set when a live cell with n live neighbours stays alive
+    pub survival: u32,
+    pub states: u8,
+    pub neighbourhood: Neighbourhood,
+}
+
+impl LifeLike {
+    // B3/S23
+    pub fn life() -> LifeLike {
+        LifeLike {
+            birth: 1 << 3,
+            survival: 1 << 2 | 1 << 3,
+            states: 2,
+            neighbourhood: Neighbourhood::Moore,
+        }
+    }
+
+    pub fn next(&self, state: u8, live_neighbours: u32) -> u8 {
+        match state {
+            0 => (self.birth >> live_neighbours & 1) as u8,
+            1 if self.survival >> live_neighbours & 1 == 1 => 1,
+            _ => ((state as u32 + 1) % self.states as u32) as u8,
+        }
+    }
+
+    // Alive when exactly one of the rule and the previous generation say so.
+    // The same step run from the next generation gives back the previous one.
+    pub fn next_second_order(&self, previous: u8, state: u8, live_neighbours: u32) -> u8 {
+        (self.next(state, live_neighbours) == 1) as u8 ^ (previous == 1) as u8
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseRuleError {
+    pub rule: String,
+    pub reason: &'static str,
+}
+
+impl Display for ParseRuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid rule {:?}: {}", self.rule, self.reason)
+    }
+}
+
+impl Error for ParseRuleError {}
+
+// A rule that can't run on a board of this size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoardSizeError {
+    pub size: usize,
+    pub reason: &'static str,
+}
+
+impl Display for BoardSizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "board size {}: {}", self.size, self.reason)
+    }
+}
+
+impl Error for BoardSizeError {}
+
+fn neighbour_counts(digits: &str) -> Result<u32, &'static str> {
+    let mut counts = 0;
+    for c in digits.chars() {
+        match c.to_digit(10) {
+            Some(n) if n <= 8 => counts |= 1 << n,
+            _ => return Err("neighbour counts are digits from 0 to 8"),
+        }
+    }
+    Ok(counts)
+}
+
+// Accepts B3/S23 style rules with the parts in any order, the older S/B form
+// (23/3), and Generations rules as S/B/C (345/2/4) or B2/S345/C4, any of them
+// ending in H or V for the hexagonal or von Neumann neighbourhood.
+impl FromStr for LifeLike {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason| ParseRuleError {
+            rule: s.to_string(),
+            reason,
+        };
+        let (counts, neighbourhood) = match s.trim() {
+            rule if rule.ends_with(['H', 'h']) => {
+                (&rule[..rule.len() - 1], Neighbourhood::Hexagonal)
+            }
+            rule if rule.ends_with(['V', 'v']) => {
+                (&rule[..rule.len() - 1], Neighbourhood::VonNeumann)
+            }
+            rule => (rule, Neighbourhood::Moore),
+        };
+        let parts: Vec<&str> = counts.split('/').collect();
+        let (mut birth, mut survival, mut states) = (None, None, None);
+
+        let prefixed = parts
+            .iter()
+            .any(|part| part.starts_with(|c: char| c.is_ascii_alphabetic()));
+        if prefixed {
+            for part in &parts {
+                let mut chars = part.chars();
+                let kind = chars.next().map(|c| c.to_ascii_uppercase());
+                let rest = chars.as_str();
+                let slot = match kind {
+                    Some('B') => &mut birth,
+                    Some('S') => &mut survival,
+                    Some('C') | Some('G') => &mut states,
+                    _ => return Err(error("every part needs a B, S or C prefix")),
+                };
+                if slot.is_some() {
+                    return Err(error("repeated part"));
+                }
+                *slot = Some(rest);
+            }
+        } else {
+            match parts[..] {
+                [s, b] => (survival, birth) = (Some(s), Some(b)),
+                [s, b, c] => (survival, birth, states) = (Some(s), Some(b), Some(c)),
+                _ => return Err(error("expected S/B or S/B/C")),
+            }
+        }
+
+        let states = match states {
+            Some(c) => c
+                .parse::<u8>()
+                .ok()
+                .filter(|c| *c >= 2)
+                .ok_or_else(|| error("state count is a number from 2 to 255"))?,
+            None => 2,
+        };
+        let birth = neighbour_counts(birth.unwrap_or_default()).map_err(error)?;
+        let survival = neighbour_counts(survival.unwrap_or_default()).map_err(error)?;
+        if (birth | survival) >> (neighbourhood.offsets().len() + 1) != 0 {
+            return Err(error("more neighbours than the neighbourhood has"));
+        }
+        Ok(LifeLike {
+            birth,
+            survival,
+            states,
+            neighbourhood,
+        })
+    }
+}
+
+impl Display for LifeLike {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = |counts: u32| -> String {
+            (0..=8)
+                .filter(|n| counts >> n & 1 == 1)
+                .map(|n| char::from(b'0' + n as u8))
+                .collect()
+        };
+        write!(f, "B{}/S{}", digits(self.birth), digits(self.survival))?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        match self.neighbourhood {
+            Neighbourhood::Hexagonal => write!(f, "H"),
+            Neighbourhood::VonNeumann => write!(f, "V"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rule_notations() {
+        assert_eq!("B3/S23".parse(), Ok(Rule::life()));
+        assert_eq!("s23/b3".parse(), Ok(Rule::life()));
+        assert_eq!("23/3".parse(), Ok(Rule::life()));
+
+        let brians_brain: LifeLike = "/2/3".parse().unwrap();
+        assert_eq!(
+            brians_brain,
+            LifeLike {
+                birth: 1 << 2,
+                survival: 0,
+                states: 3,
+                neighbourhood: Neighbourhood::Moore,
+            }
+        );
+        assert_eq!(brians_brain.to_string(), "B2/S/C3");
+
+        let star_wars: LifeLike = "345/2/4".parse().unwrap();
+        assert_eq!(star_wars, "B2/S345/C4".parse().unwrap());
+        assert_eq!(star_wars.to_string(), "B2/S345/C4");
+
+        assert!("B9/S23".parse::<Rule>().is_err());
+        assert!("B3/S23/C1".parse::<Rule>().is_err());
+        assert!("B3/X23".parse::<Rule>().is_err());
+
+        let hex: Rule = "B2/S34H".parse().unwrap();
+        assert!(hex.hexagonal());
+        assert_eq!(hex.to_string(), "B2/S34H");
+        assert_eq!("b2/s3v".parse::<Rule>().unwrap().to_string(), "B2/S3V");
+        assert!("B2/S57H".parse::<Rule>().is_err());
+
+        let bosco: Rule = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        assert!(matches!(bosco, Rule::LargerThanLife(_)));
+        assert!(matches!("B2-a/S12".parse(), Ok(Rule::Isotropic(_))));
+        assert_eq!(bosco.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+
+        let critters: Rule = "MS,D15;14;13;3;11;5;6;1;7;9;10;2;12;4;8;0".parse().unwrap();
+        assert_eq!(critters, Rule::Margolus(Margolus::critters()));
+        assert_eq!(
+            "MS,D15,14,13,3,11,5,6,1,7,9,10,2,12,4,8,0".parse(),
+            Ok(critters)
+        );
+        assert_eq!(
+            Rule::Margolus(Margolus::sand()).to_string(),
+            "MS,D0;4;8;12;4;12;12;13;8;12;12;14;12;13;14;15"
+        );
+        assert!("MS,D0;1;2".parse::<Rule>().is_err());
+        assert!("MS,D16,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15"
+            .parse::<Rule>()
+            .is_err());
+
+        let life_history: Rule = "LifeHistory".parse().unwrap();
+        assert_eq!(life_history, Rule::History(History::life()));
+        assert_eq!(life_history.states(), 7);
+        assert_eq!("B3/S23History".parse(), Ok(life_history));
+        let hex_history: Rule = "B2/S34HHistory".parse().unwrap();
+        assert!(hex_history.hexagonal());
+        assert_eq!(hex_history.to_string(), "B2/S34HHistory");
+        assert!("B2/S345/C4History".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn history_keeps_envelope_and_marks() {
+        let rule = History::life();
+        assert_eq!(rule.next(history::OFF, 3), history::ON);
+        assert_eq!(rule.next(history::ON, 1), history::HISTORY);
+        assert_eq!(rule.next(history::HISTORY, 3), history::ON);
+        assert_eq!(rule.next(history::HISTORY, 2), history::HISTORY);
+        assert_eq!(rule.next(history::MARKED_OFF, 3), history::MARKED_ON);
+        assert_eq!(rule.next(history::MARKED_ON, 2), history::MARKED_ON);
+        assert_eq!(rule.next(history::MARKED_ON, 4), history::MARKED_OFF);
+        assert_eq!(rule.next(history::START_ON, 0), history::MARKED_OFF);
+        assert_eq!(rule.next(history::BOUNDARY, 3), history::BOUNDARY);
+        assert_eq!(History::mark(history::HISTORY), history::MARKED_OFF);
+        assert_eq!(
+            History::envelope(&[0, 1, 2, 3, 4, 5, 6]),
+            [false, true, true, true, false, true, false]
+        );
+    }
+
+    #[test]
+    fn dying_states_count_up() {
+        let star_wars: LifeLike = "345/2/4".parse().unwrap();
+        assert_eq!(star_wars.next(0, 2), 1);
+        assert_eq!(star_wars.next(0, 3), 0);
+        assert_eq!(star_wars.next(1, 4), 1);
+        assert_eq!(star_wars.next(1, 2), 2);
+        assert_eq!(star_wars.next(2, 2), 3);
+        assert_eq!(star_wars.next(3, 2), 0);
+        assert_eq!(LifeLike::life().next(1, 1), 0);
+    }
+}
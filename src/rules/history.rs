@@ -0,0 +1,109 @@
+use super::{LifeLike, ParseRuleError};
+use std::fmt::Display;
+use std::str::FromStr;
+
+// Golly's LifeHistory states on top of a two state Life-like rule. Odd states
+// are alive and count as neighbours. Cells that die leave history behind,
+// so states 1 and 2 make up the envelope of everything that has been alive,
+// and marked cells keep their mark through births and deaths.
+pub const OFF: u8 = 0;
+pub const ON: u8 = 1;
+// dead, but alive at some point
+pub const HISTORY: u8 = 2;
+pub const MARKED_ON: u8 = 3;
+pub const MARKED_OFF: u8 = 4;
+// alive and part of the starting pattern, marked off once it dies
+pub const START_ON: u8 = 5;
+// never comes alive
+pub const BOUNDARY: u8 = 6;
+
+pub const STATES: u8 = 7;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct History {
+    pub rule: LifeLike,
+}
+
+impl History {
+    pub fn life() -> History {
+        History {
+            rule: LifeLike::life(),
+        }
+    }
+
+    pub fn alive(state: u8) -> bool {
+        state % 2 == 1
+    }
+
+    pub fn next(&self, state: u8, live_neighbours: u32) -> u8 {
+        let counts = if History::alive(state) {
+            self.rule.survival
+        } else {
+            self.rule.birth
+        };
+        let alive = counts >> live_neighbours & 1 == 1;
+        match (state, alive) {
+            (ON | MARKED_ON | START_ON, true) | (BOUNDARY, _) => state,
+            (ON, false) => HISTORY,
+            (MARKED_ON | START_ON, false) => MARKED_OFF,
+            (MARKED_OFF, true) => MARKED_ON,
+            (_, true) => ON,
+            (_, false) => state,
+        }
+    }
+
+    // The same cell with a mark, alive or not like it was.
+    pub fn mark(state: u8) -> u8 {
+        match state {
+            ON | START_ON => MARKED_ON,
+            OFF | HISTORY => MARKED_OFF,
+            _ => state,
+        }
+    }
+
+    // Cells alive now or left as history. Marked cells keep their mark when
+    // they die instead of turning into history.
+    pub fn envelope(states: &[u8]) -> Vec<bool> {
+        states
+            .iter()
+            .map(|state| History::alive(*state) || *state == HISTORY)
+            .collect()
+    }
+}
+
+// A Life-like rule followed by History, or LifeHistory for B3/S23.
+impl FromStr for History {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason| ParseRuleError {
+            rule: s.to_string(),
+            reason,
+        };
+        let rule = s.trim();
+        let split = rule
+            .len()
+            .checked_sub("history".len())
+            .filter(|split| rule.is_char_boundary(*split))
+            .filter(|split| rule[*split..].eq_ignore_ascii_case("history"))
+            .ok_or_else(|| error("history rules end with History"))?;
+        let rule = match &rule[..split] {
+            life if life.eq_ignore_ascii_case("life") => LifeLike::life(),
+            rule => rule.parse().map_err(|_| error("invalid Life-like rule"))?,
+        };
+        if rule.states != 2 {
+            return Err(error("history rules have two states underneath"));
+        }
+        Ok(History { rule })
+    }
+}
+
+impl Display for History {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.rule == LifeLike::life() {
+            write!(f, "LifeHistory")
+        } else {
+            write!(f, "{}History", self.rule)
+        }
+    }
+}
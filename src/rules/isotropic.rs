@@ -0,0 +1,299 @@
+use super::ParseRuleError;
+use std::fmt::Display;
+use std::str::FromStr;
+
+// Letters for each neighbour count, in the order Golly writes them. Counts
+// above 4 use the letters of 8 - count for the inverted configurations.
+const LETTERS: [&str; 5] = ["", "ce", "ceaikn", "ceaiknjqry", "ceaiknjqrytwz"];
+
+// One configuration for each letter of counts 0 to 4, with the neighbours as
+// bits of a 3x3 index: 1 2 4 on the row above, 8 and 32 beside the cell, which
+// is 16 itself, and 64 128 256 below.
+const CONFIGURATIONS: [&[usize]; 5] = [
+    &[0],
+    &[1, 2],
+    &[5, 10, 3, 40, 33, 68],
+    &[69, 42, 11, 7, 98, 13, 14, 70, 41, 97],
+    &[325, 170, 15, 45, 99, 71, 106, 102, 43, 101, 105, 78, 108],
+];
+
+const CENTRE: usize = 16;
+const NEIGHBOURS: usize = 511 & !CENTRE;
+
+fn letters(count: usize) -> &'static str {
+    LETTERS[count.min(8 - count)]
+}
+
+// Where bit k of a 3x3 index goes under each of the 8 symmetries of the square.
+fn symmetries() -> Vec<[usize; 9]> {
+    let mut symmetries = Vec::new();
+    for reflect in [false, true] {
+        for turns in 0..4 {
+            let mut map = [0; 9];
+            for (k, to) in map.iter_mut().enumerate() {
+                let (mut x, mut y) = (k as i32 % 3 - 1, k as i32 / 3 - 1);
+                if reflect {
+                    x = -x;
+                }
+                for _ in 0..turns {
+                    (x, y) = (-y, x);
+                }
+                *to = ((y + 1) * 3 + x + 1) as usize;
+            }
+            symmetries.push(map);
+        }
+    }
+    symmetries
+}
+
+fn transform(index: usize, map: &[usize; 9]) -> usize {
+    (0..9)
+        .filter(|k| index >> k & 1 == 1)
+        .map(|k| 1 << map[k])
+        .sum()
+}
+
+// Letter index within letters(count) of every neighbour configuration.
+fn classify() -> [u8; 512] {
+    let symmetries = symmetries();
+    let mut classes = [0; 512];
+    for (count, configurations) in CONFIGURATIONS.iter().enumerate() {
+        for (letter, configuration) in configurations.iter().enumerate() {
+            for map in &symmetries {
+                let index = transform(*configuration, map);
+                classes[index] = letter as u8;
+                // 4 has its own letters for the inverted configurations
+                if count < 4 {
+                    classes[!index & NEIGHBOURS] = letter as u8;
+                }
+            }
+        }
+    }
+    classes
+}
+
+// Isotropic non-totalistic rule in Hensel notation, B2-a/S12. Each neighbour
+// count can be limited to the configurations listed after it, or to those not
+// listed after a '-'. Only cells in state 1 are alive, more states make it a
+// Generations rule like LifeLike.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Isotropic {
+    // bit l of entry n is set when letter l of count n gives a birth, counts
+    // without letters use bit 0
+    pub birth: [u16; 9],
+    pub survival: [u16; 9],
+    pub states: u8,
+    // whether a cell is alive next for every 3x3 index
+    table: Vec<bool>,
+}
+
+impl Isotropic {
+    pub fn new(birth: [u16; 9], survival: [u16; 9], states: u8) -> Isotropic {
+        let classes = classify();
+        let table = (0..512)
+            .map(|index: usize| {
+                let counts = if index & CENTRE == 0 {
+                    &birth
+                } else {
+                    &survival
+                };
+                let count = (index & NEIGHBOURS).count_ones() as usize;
+                counts[count] >> classes[index & NEIGHBOURS] & 1 == 1
+            })
+            .collect();
+        Isotropic {
+            birth,
+            survival,
+            states,
+            table,
+        }
+    }
+
+    // The 512 entry lookup table indexed by the 3x3 index with only cells in
+    // state 1 set, including the cell itself.
+    pub fn table(&self) -> &[bool] {
+        &self.table
+    }
+
+    pub fn next(&self, state: u8, index: usize) -> u8 {
+        match state {
+            0 | 1 if self.table[index] => 1,
+            0 => 0,
+            _ => ((state as u32 + 1) % self.states as u32) as u8,
+        }
+    }
+}
+
+fn configurations(part: &str) -> Result<[u16; 9], &'static str> {
+    let mut counts = [0; 9];
+    let mut chars = part.chars().peekable();
+    while let Some(c) = chars.next() {
+        let count = match c.to_digit(10) {
+            Some(n) if n <= 8 => n as usize,
+            _ => return Err("neighbour counts are digits from 0 to 8"),
+        };
+        let all = (1 << letters(count).len().max(1)) - 1;
+        let negated = chars.next_if_eq(&'-').is_some();
+        let mut listed = 0;
+        while let Some(letter) = chars.next_if(|c| c.is_ascii_lowercase()) {
+            match letters(count).find(letter) {
+                Some(l) => listed |= 1 << l,
+                None => return Err("letter not used with its neighbour count"),
+            }
+        }
+        counts[count] = match (negated, listed) {
+            (true, 0) => return Err("'-' needs letters after it"),
+            (true, _) => all & !listed,
+            (false, 0) => all,
+            (false, _) => listed,
+        };
+    }
+    Ok(counts)
+}
+
+// Hensel notation with the parts in any order: B, S and an optional C for the
+// number of states.
+impl FromStr for Isotropic {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason| ParseRuleError {
+            rule: s.to_string(),
+            reason,
+        };
+        let (mut birth, mut survival, mut states) = (None, None, None);
+        for part in s.trim().split('/') {
+            let mut chars = part.chars();
+            let kind = chars.next().map(|c| c.to_ascii_uppercase());
+            let rest = chars.as_str();
+            let slot = match kind {
+                Some('B') => &mut birth,
+                Some('S') => &mut survival,
+                Some('C') | Some('G') => &mut states,
+                _ => return Err(error("every part needs a B, S or C prefix")),
+            };
+            if slot.is_some() {
+                return Err(error("repeated part"));
+            }
+            *slot = Some(rest);
+        }
+
+        let states = match states {
+            Some(c) => c
+                .parse::<u8>()
+                .ok()
+                .filter(|c| *c >= 2)
+                .ok_or_else(|| error("state count is a number from 2 to 255"))?,
+            None => 2,
+        };
+        Ok(Isotropic::new(
+            configurations(birth.unwrap_or_default()).map_err(error)?,
+            configurations(survival.unwrap_or_default()).map_err(error)?,
+            states,
+        ))
+    }
+}
+
+impl Display for Isotropic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the shorter of the listed letters or the ones left out
+        let notation = |counts: &[u16; 9]| -> String {
+            let mut out = String::new();
+            for (count, listed) in counts.iter().enumerate() {
+                let letters = letters(count);
+                let all = (1 << letters.len().max(1)) - 1;
+                if *listed == 0 {
+                    continue;
+                }
+                out.push(char::from(b'0' + count as u8));
+                if *listed == all {
+                    continue;
+                }
+                let (minus, shown) = if listed.count_ones() * 2 <= letters.len() as u32 {
+                    (false, *listed)
+                } else {
+                    (true, all & !listed)
+                };
+                if minus {
+                    out.push('-');
+                }
+                out.extend(
+                    letters
+                        .chars()
+                        .enumerate()
+                        .filter_map(|(l, letter)| (shown >> l & 1 == 1).then_some(letter)),
+                );
+            }
+            out
+        };
+        write!(
+            f,
+            "B{}/S{}",
+            notation(&self.birth),
+            notation(&self.survival)
+        )?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn letters_cover_every_configuration() {
+        let symmetries = symmetries();
+        for (count, configurations) in CONFIGURATIONS.iter().enumerate().skip(1) {
+            let mut seen = HashSet::new();
+            for configuration in configurations.iter() {
+                assert_eq!(configuration.count_ones() as usize, count);
+                let orbit: HashSet<usize> = symmetries
+                    .iter()
+                    .map(|map| transform(*configuration, map))
+                    .collect();
+                assert!(orbit.is_disjoint(&seen));
+                seen.extend(orbit);
+            }
+            // 8 choose count
+            let total = (0..512)
+                .filter(|index| index & CENTRE == 0 && index.count_ones() == count as u32)
+                .count();
+            assert_eq!(seen.len(), total);
+        }
+    }
+
+    #[test]
+    fn parses_hensel_notation() {
+        for rule in [
+            "B2-a/S12",
+            "B3-cnqy/S23-a4itz",
+            "B2ci3/S1e2-kn/C3",
+            "B3/S23",
+        ] {
+            assert_eq!(rule.parse::<Isotropic>().unwrap().to_string(), rule);
+        }
+        assert_eq!(
+            "b3-cnqy/s23-a4zti".parse::<Isotropic>().unwrap(),
+            "B3aeijkr/S23ceijknqry4itz".parse().unwrap()
+        );
+        assert!("B2x/S23".parse::<Isotropic>().is_err());
+        assert!("B1a/S23".parse::<Isotropic>().is_err());
+        assert!("B3-/S23".parse::<Isotropic>().is_err());
+
+        // totalistic rules give the same table as counting
+        let life: Isotropic = "B3/S23".parse().unwrap();
+        for index in 0..512 {
+            let neighbours = (index & NEIGHBOURS).count_ones();
+            let alive = if index & CENTRE == 0 {
+                neighbours == 3
+            } else {
+                neighbours == 2 || neighbours == 3
+            };
+            assert_eq!(life.table()[index], alive);
+        }
+    }
+}
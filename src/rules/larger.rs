@@ -0,0 +1,227 @@
+use super::ParseRuleError;
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+// Golly allows ranges up to 500.
+const MAX_RANGE: u32 = 500;
+
+// Shape of a range R neighbourhood.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Shape {
+    // the (2R + 1) * (2R + 1) square
+    Moore,
+    // cells at most R steps away moving orthogonally, a diamond
+    VonNeumann,
+    // cells within a distance of R
+    Circular,
+}
+
+impl Shape {
+    fn letter(self) -> char {
+        match self {
+            Shape::Moore => 'M',
+            Shape::VonNeumann => 'N',
+            Shape::Circular => 'C',
+        }
+    }
+
+    // Half width of the row dy cells above or below the centre.
+    fn half_width(self, range: i32, dy: i32) -> i32 {
+        match self {
+            Shape::Moore => range,
+            Shape::VonNeumann => range - dy.abs(),
+            Shape::Circular => (0..=range)
+                .take_while(|dx| dx * dx + dy * dy <= range * range)
+                .last()
+                .unwrap(),
+        }
+    }
+}
+
+// Rectangle of offsets from a cell, inclusive on all sides.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub top: i32,
+    pub bottom: i32,
+    pub left: i32,
+    pub right: i32,
+}
+
+// Larger than Life: a Life-like rule where the live cells counted come from
+// a neighbourhood of range R and births and survivals are given as ranges of
+// counts. Like Generations rules, more than 2 states adds dying states.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LargerThanLife {
+    pub range: u32,
+    pub states: u8,
+    // whether the cell itself is counted
+    pub middle: bool,
+    pub survival: RangeInclusive<u32>,
+    pub birth: RangeInclusive<u32>,
+    pub shape: Shape,
+}
+
+impl LargerThanLife {
+    pub fn next(&self, state: u8, live_neighbours: u32) -> u8 {
+        match state {
+            0 => self.birth.contains(&live_neighbours) as u8,
+            1 if self.survival.contains(&live_neighbours) => 1,
+            _ => ((state as u32 + 1) % self.states as u32) as u8,
+        }
+    }
+
+    // The neighbourhood as few rectangles as possible, with rows of the same
+    // width merged. Moore needs one, so it can be summed in constant time.
+    pub fn spans(&self) -> Vec<Span> {
+        let range = self.range as i32;
+        let mut spans: Vec<Span> = Vec::new();
+        for dy in -range..=range {
+            let half_width = self.shape.half_width(range, dy);
+            match spans.last_mut() {
+                Some(span) if span.right == half_width => span.bottom = dy,
+                _ => spans.push(Span {
+                    top: dy,
+                    bottom: dy,
+                    left: -half_width,
+                    right: half_width,
+                }),
+            }
+        }
+        spans
+    }
+}
+
+// Golly's notation, R5,C0,M1,S34..58,B34..45,NM. C0 and C2 both mean 2
+// states, the neighbourhood is M, N or C and defaults to M.
+impl FromStr for LargerThanLife {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason| ParseRuleError {
+            rule: s.to_string(),
+            reason,
+        };
+        let number = |digits: &str| {
+            digits
+                .parse::<u32>()
+                .map_err(|_| error("expected a number"))
+        };
+        let counts = |part: &str| -> Result<RangeInclusive<u32>, ParseRuleError> {
+            match part.split_once("..") {
+                Some((min, max)) => Ok(number(min)?..=number(max)?),
+                None => Ok(number(part)?..=number(part)?),
+            }
+        };
+
+        let (mut range, mut states, mut middle) = (None, 2, false);
+        let (mut survival, mut birth, mut shape) = (None, None, Shape::Moore);
+        for part in s.trim().split(',') {
+            let mut chars = part.chars();
+            let kind = chars.next().map(|c| c.to_ascii_uppercase());
+            let rest = chars.as_str();
+            match kind {
+                Some('R') => range = Some(number(rest)?),
+                Some('C') => {
+                    states = match number(rest)? {
+                        0..=2 => 2,
+                        c => u8::try_from(c).map_err(|_| error("at most 255 states"))?,
+                    }
+                }
+                Some('M') => middle = number(rest)? == 1,
+                Some('S') => survival = Some(counts(rest)?),
+                Some('B') => birth = Some(counts(rest)?),
+                Some('N') => {
+                    shape = match rest.to_ascii_uppercase().as_str() {
+                        "M" => Shape::Moore,
+                        "N" => Shape::VonNeumann,
+                        "C" => Shape::Circular,
+                        _ => return Err(error("neighbourhood is NM, NN or NC")),
+                    }
+                }
+                _ => return Err(error("every part needs an R, C, M, S, B or N prefix")),
+            }
+        }
+
+        let range = range.ok_or_else(|| error("missing range"))?;
+        if range == 0 || range > MAX_RANGE {
+            return Err(error("range is a number from 1 to 500"));
+        }
+        Ok(LargerThanLife {
+            range,
+            states,
+            middle,
+            survival: survival.ok_or_else(|| error("missing survival counts"))?,
+            birth: birth.ok_or_else(|| error("missing birth counts"))?,
+            shape,
+        })
+    }
+}
+
+impl Display for LargerThanLife {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "R{},C{},M{},S{}..{},B{}..{},N{}",
+            self.range,
+            if self.states > 2 { self.states } else { 0 },
+            self.middle as u8,
+            self.survival.start(),
+            self.survival.end(),
+            self.birth.start(),
+            self.birth.end(),
+            self.shape.letter()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_larger_than_life() {
+        let bosco: LargerThanLife = "R5,C0,M1,S34..58,B34..45,NM".parse().unwrap();
+        assert_eq!(
+            bosco,
+            LargerThanLife {
+                range: 5,
+                states: 2,
+                middle: true,
+                survival: 34..=58,
+                birth: 34..=45,
+                shape: Shape::Moore,
+            }
+        );
+        assert_eq!(bosco.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+        assert_eq!(
+            bosco.spans(),
+            vec![Span {
+                top: -5,
+                bottom: 5,
+                left: -5,
+                right: 5
+            }]
+        );
+
+        let diamond: LargerThanLife = "R2,C3,M0,S2..3,B3,NN".parse().unwrap();
+        assert_eq!(diamond.to_string(), "R2,C3,M0,S2..3,B3..3,NN");
+        assert_eq!(diamond.spans().len(), 5);
+
+        assert!("R0,C0,M1,S1..2,B1..2,NM".parse::<LargerThanLife>().is_err());
+        assert!("R2,C0,M1,S1..2".parse::<LargerThanLife>().is_err());
+        assert!("R2,C0,M1,S1..2,B1..2,NX".parse::<LargerThanLife>().is_err());
+    }
+
+    #[test]
+    fn circular_spans() {
+        let rule: LargerThanLife = "R3,C0,M0,S1,B1,NC".parse().unwrap();
+        let cells: i32 = rule
+            .spans()
+            .iter()
+            .map(|span| (span.bottom - span.top + 1) * (span.right - span.left + 1))
+            .sum();
+        // lattice points with x^2 + y^2 <= 9
+        assert_eq!(cells, 29);
+    }
+}
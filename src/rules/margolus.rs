@@ -0,0 +1,92 @@
+use super::ParseRuleError;
+use std::fmt::Display;
+use std::str::FromStr;
+
+// Block rule on the Margolus neighbourhood. The board is cut into 2x2 blocks
+// that each turn into a new block on their own, with the blocks starting at
+// even coordinates on even generations and at odd ones on odd generations.
+// Blocks are numbered by their live cells: 1 top left, 2 top right, 4 bottom
+// left and 8 bottom right. Boards need an even size for the blocks to fit.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Margolus {
+    // the block each block turns into
+    pub blocks: [u8; 16],
+}
+
+impl Margolus {
+    // Everything but blocks of 2 is inverted, and blocks of 3 turn around.
+    pub fn critters() -> Margolus {
+        Margolus {
+            blocks: [15, 14, 13, 3, 11, 5, 6, 1, 7, 9, 10, 2, 12, 4, 8, 0],
+        }
+    }
+
+    // Empty and full blocks are inverted.
+    pub fn tron() -> Margolus {
+        Margolus {
+            blocks: [15, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 0],
+        }
+    }
+
+    // Single cells are balls crossing their block, two meeting head on turn.
+    pub fn billiard_ball_machine() -> Margolus {
+        Margolus {
+            blocks: [0, 8, 4, 3, 2, 5, 9, 7, 1, 6, 10, 11, 12, 13, 14, 15],
+        }
+    }
+
+    // Grains fall, and slide off grains below them.
+    pub fn sand() -> Margolus {
+        Margolus {
+            blocks: [0, 4, 8, 12, 4, 12, 12, 13, 8, 12, 12, 14, 12, 13, 14, 15],
+        }
+    }
+
+    pub fn next(&self, block: u8) -> u8 {
+        self.blocks[block as usize & 15]
+    }
+
+    // Where the block holding a cell starts along one axis of a board that
+    // wraps at size, and the cell's position in the block, 0 or 1.
+    pub fn block_start(coordinate: usize, size: usize, generation: i64) -> (usize, usize) {
+        let position = (coordinate + generation.rem_euclid(2) as usize) % 2;
+        ((coordinate + size - position) % size, position)
+    }
+}
+
+// MCell's notation, MS,D followed by the 16 blocks separated by ';'. Commas
+// are read as separators too.
+impl FromStr for Margolus {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason| ParseRuleError {
+            rule: s.to_string(),
+            reason,
+        };
+        let rule = s.trim();
+        let blocks = rule
+            .get(..4)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("MS,D"))
+            .map(|_| &rule[4..])
+            .ok_or_else(|| error("Margolus rules start with MS,D"))?;
+
+        let blocks: Vec<u8> = blocks
+            .split([';', ','])
+            .map(|block| block.trim().parse::<u8>().ok().filter(|block| *block < 16))
+            .collect::<Option<_>>()
+            .ok_or_else(|| error("blocks are numbers from 0 to 15"))?;
+        Ok(Margolus {
+            blocks: blocks
+                .try_into()
+                .map_err(|_| error("there are 16 blocks"))?,
+        })
+    }
+}
+
+impl Display for Margolus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let blocks: Vec<String> = self.blocks.iter().map(|block| block.to_string()).collect();
+        write!(f, "MS,D{}", blocks.join(";"))
+    }
+}
@@ -0,0 +1,62 @@
+use super::{BoardSizeError, Rule};
+use crate::Gol;
+use std::collections::BTreeMap;
+
+// Rules that change with the generation. From each change on, its rules take
+// turns by generation, so [B3/S23, B36/S125] runs B3/S23 on even generations
+// and B36/S125 on odd ones.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    changes: BTreeMap<i64, Vec<Rule>>,
+}
+
+impl Schedule {
+    pub fn new(rule: Rule) -> Schedule {
+        Schedule::cycle(vec![rule])
+    }
+
+    pub fn cycle(rules: Vec<Rule>) -> Schedule {
+        assert!(!rules.is_empty(), "a schedule needs at least one rule");
+        Schedule {
+            changes: BTreeMap::from([(i64::MIN, rules)]),
+        }
+    }
+
+    // Switches to the rule from generation on.
+    pub fn change_at(self, generation: i64, rule: Rule) -> Schedule {
+        self.cycle_at(generation, vec![rule])
+    }
+
+    pub fn cycle_at(mut self, generation: i64, rules: Vec<Rule>) -> Schedule {
+        assert!(!rules.is_empty(), "a schedule needs at least one rule");
+        self.changes.insert(generation, rules);
+        self
+    }
+
+    // The rule that turns generation into the next one.
+    pub fn rule_at(&self, generation: i64) -> &Rule {
+        let (_, rules) = self.changes.range(..=generation).next_back().unwrap();
+        &rules[generation.rem_euclid(rules.len() as i64) as usize]
+    }
+
+    // One generation with the rule for the game's generation, set first when
+    // the game has another one.
+    pub fn step(&self, mut game: Box<dyn Gol + '_>) -> Result<Box<dyn Gol>, BoardSizeError> {
+        let rule = self.rule_at(game.generation());
+        if game.rule() != rule {
+            game.set_rule(rule.clone())?;
+        }
+        Ok(game.to_next())
+    }
+
+    pub fn run(
+        &self,
+        mut game: Box<dyn Gol>,
+        generations: usize,
+    ) -> Result<Box<dyn Gol>, BoardSizeError> {
+        for _ in 0..generations {
+            game = self.step(game)?;
+        }
+        Ok(game)
+    }
+}
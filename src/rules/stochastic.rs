@@ -0,0 +1,134 @@
+use super::LifeLike;
+use std::fmt::Display;
+
+// lowbias32 by Chris Wellons. The shaders have the same function, so every
+// backend draws the same numbers.
+pub fn hash(x: u32) -> u32 {
+    let mut x = x;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846ca68b);
+    x ^= x >> 16;
+    x
+}
+
+// Random number for a cell, index y * size + x, in a generation. The same
+// seed, generation and cell always give the same number.
+pub fn random(seed: u32, generation: u32, cell: u32) -> u32 {
+    hash(hash(hash(seed) ^ generation) ^ cell)
+}
+
+// Chance out of 2^24, which shaders compare against exactly like the CPU.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Probability(u32);
+
+impl Probability {
+    const ONE: u32 = 1 << 24;
+
+    pub fn new(p: f64) -> Probability {
+        Probability((p.clamp(0.0, 1.0) * Self::ONE as f64).round() as u32)
+    }
+
+    pub fn value(self) -> f64 {
+        self.0 as f64 / Self::ONE as f64
+    }
+
+    pub fn fraction(self) -> u32 {
+        self.0
+    }
+
+    // Whether something this likely happens for a number from random.
+    pub fn happens(self, random: u32) -> bool {
+        random >> 8 < self.0
+    }
+}
+
+// Life-like rule where the births and survivals it gives only happen with a
+// chance. A live cell that misses its survival dies like it would without
+// the neighbours for it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Stochastic {
+    pub rule: LifeLike,
+    pub birth: Probability,
+    pub survival: Probability,
+    pub seed: u32,
+}
+
+impl Stochastic {
+    pub fn next(&self, state: u8, live_neighbours: u32, random: u32) -> u8 {
+        let next = self.rule.next(state, live_neighbours);
+        let chance = match (state, next) {
+            (0, 1) => self.birth,
+            (1, 1) => self.survival,
+            _ => return next,
+        };
+        match (chance.happens(random), state) {
+            (true, _) => 1,
+            (false, 0) => 0,
+            (false, _) => (2 % self.rule.states as u32) as u8,
+        }
+    }
+}
+
+impl Display for Stochastic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (births {}, survivals {}, seed {})",
+            self.rule,
+            self.birth.value(),
+            self.survival.value(),
+            self.seed
+        )
+    }
+}
+
+// A seeded fraction of the cells flipped after every generation: live cells
+// die and every other cell comes alive. Give it a different seed from a
+// stochastic rule to keep the two independent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Noise {
+    pub fraction: Probability,
+    pub seed: u32,
+}
+
+impl Noise {
+    pub fn new(fraction: f64, seed: u32) -> Noise {
+        Noise {
+            fraction: Probability::new(fraction),
+            seed,
+        }
+    }
+
+    // generation is the one the cell's state was computed from
+    pub fn apply(&self, state: u8, generation: i64, cell: usize) -> u8 {
+        if self
+            .fraction
+            .happens(random(self.seed, generation as u32, cell as u32))
+        {
+            (state != 1) as u8
+        } else {
+            state
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chances_match_their_probability() {
+        let draws = 100_000;
+        for p in [0.0, 0.1, 0.5, 0.97, 1.0] {
+            let chance = Probability::new(p);
+            let hits = (0..draws)
+                .filter(|cell| chance.happens(random(7, 3, *cell)))
+                .count();
+            assert!((hits as f64 / draws as f64 - p).abs() < 0.01, "{}", p);
+        }
+        assert_eq!(Probability::new(1.0).fraction(), 1 << 24);
+        assert_eq!(Probability::new(0.25).value(), 0.25);
+    }
+}
@@ -0,0 +1,586 @@
+use super::Neighbourhood;
+use crate::image::Rgb;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::Display;
+use std::io;
+use std::path::Path;
+
+// Golly's own WireWorld.rule. 0 is empty, 1 an electron head, 2 its tail and
+// 3 a conductor.
+const WIREWORLD: &str = "@RULE WireWorld
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+var d={0,1,2,3}
+var e={0,1,2,3}
+var f={0,1,2,3}
+var g={0,1,2,3}
+var h={0,1,2,3}
+var i={0,2,3}
+var j={0,2,3}
+var k={0,2,3}
+var l={0,2,3}
+var m={0,2,3}
+var n={0,2,3}
+var o={0,2,3}
+# head becomes tail, tail becomes conductor
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+# conductor becomes a head next to one or two heads
+3,1,i,j,k,l,m,n,o,1
+3,1,1,i,j,k,l,m,n,1
+@COLORS
+0 48 48 48
+1 0 128 255
+2 255 255 255
+3 255 128 0
+";
+
+// Set of states as a 256 bit mask.
+type States = [u64; 4];
+
+fn single(state: u8) -> States {
+    let mut set = [0; 4];
+    set[state as usize / 64] |= 1 << (state % 64);
+    set
+}
+
+fn contains(set: &States, state: u8) -> bool {
+    set[state as usize / 64] >> (state % 64) & 1 == 1
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseTableError {
+    // 1 based, 0 when the problem isn't on a single line
+    pub line: usize,
+    pub reason: String,
+}
+
+impl Display for ParseTableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rule table line {}: {}", self.line, self.reason)
+    }
+}
+
+impl Error for ParseTableError {}
+
+fn error(line: usize, reason: impl Into<String>) -> ParseTableError {
+    ParseTableError {
+        line,
+        reason: reason.into(),
+    }
+}
+
+// One entry of a transition line before variables are expanded.
+#[derive(Clone, Debug)]
+enum Entry {
+    State(u8),
+    Var(String),
+}
+
+// A Golly rule table compiled into bit masks. Transitions are tried in
+// order and the first one matching a cell and its neighbours gives the next
+// state. Cells no transition matches keep their state.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RuleTable {
+    pub name: String,
+    pub states: u8,
+    pub neighbourhood: Neighbourhood,
+    // from @COLORS, Golly's default colours for states it leaves out
+    pub colors: Vec<Rgb>,
+    // transitions left after expanding bound variables and symmetries
+    outputs: Vec<u8>,
+    // 32 bit words needed for one bit per transition
+    words: usize,
+    // word w of ((position * states + state) * words + w) has bit t set when
+    // transition 32 * w + t accepts state at position. Position 0 is the cell
+    // itself, the neighbours follow in Neighbourhood::offsets order.
+    masks: Vec<u32>,
+}
+
+impl RuleTable {
+    pub fn wireworld() -> RuleTable {
+        RuleTable::parse(WIREWORLD).unwrap()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<RuleTable> {
+        RuleTable::parse(&std::fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    // Parse the @RULE, @TABLE and @COLORS sections of a .rule file. Other
+    // sections are skipped.
+    pub fn parse(text: &str) -> Result<RuleTable, ParseTableError> {
+        let mut name = String::new();
+        let mut section = "";
+        let mut has_table = false;
+        let mut states: Option<usize> = None;
+        let mut neighbourhood = Neighbourhood::Moore;
+        let mut symmetries = "none".to_string();
+        let mut vars: HashMap<String, Vec<u8>> = HashMap::new();
+        let mut lines: Vec<(usize, Vec<Entry>)> = Vec::new();
+        let mut color_lines: Vec<(usize, Vec<u8>)> = Vec::new();
+
+        for (i, raw) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let line = raw.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('@') {
+                let mut words = header.split_whitespace();
+                section = words.next().unwrap_or("");
+                if section == "RULE" {
+                    name = words.collect::<Vec<_>>().join(" ");
+                }
+                has_table |= section == "TABLE";
+                continue;
+            }
+
+            match section {
+                "TABLE" => {
+                    if let Some((key, value)) = line.split_once(':') {
+                        let value = value.trim();
+                        match key.trim() {
+                            "n_states" | "num_states" => {
+                                // Golly allows 256, but state counts are u8 in every rule
+                                let n = value
+                                    .parse()
+                                    .ok()
+                                    .filter(|n| (2..=255).contains(n))
+                                    .ok_or_else(|| {
+                                        error(
+                                            line_no,
+                                            "n_states is 2 to 255, 256 states aren't supported",
+                                        )
+                                    })?;
+                                states = Some(n);
+                            }
+                            "neighborhood" | "neighbourhood" => {
+                                neighbourhood = match value {
+                                    "Moore" => Neighbourhood::Moore,
+                                    "vonNeumann" => Neighbourhood::VonNeumann,
+                                    "hexagonal" => Neighbourhood::Hexagonal,
+                                    _ => {
+                                        return Err(error(
+                                            line_no,
+                                            format!("unsupported neighborhood {}", value),
+                                        ))
+                                    }
+                                }
+                            }
+                            "symmetries" => symmetries = value.to_string(),
+                            _ => return Err(error(line_no, format!("unknown key {}", key))),
+                        }
+                    } else if let Some(definition) = line.strip_prefix("var ") {
+                        let (var, values) = parse_var(definition, &vars)
+                            .ok_or_else(|| error(line_no, "expected var name={a,b,...}"))?;
+                        vars.insert(var, values);
+                    } else {
+                        let entries = parse_transition(line, states.unwrap_or(2), &vars)
+                            .map_err(|reason| error(line_no, reason))?;
+                        lines.push((line_no, entries));
+                    }
+                }
+                "COLORS" => {
+                    let numbers: Option<Vec<u8>> =
+                        line.split_whitespace().map(|n| n.parse().ok()).collect();
+                    match numbers {
+                        Some(numbers) if numbers.len() == 4 || numbers.len() == 6 => {
+                            color_lines.push((line_no, numbers))
+                        }
+                        _ => return Err(error(line_no, "expected state r g b or a gradient")),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if !has_table {
+            return Err(error(0, "no @TABLE section"));
+        }
+        let states = states.ok_or_else(|| error(0, "n_states is missing"))?;
+        let positions = neighbourhood.offsets().len() + 1;
+        let permutations = symmetry_permutations(&symmetries, positions - 1)
+            .ok_or_else(|| error(0, format!("unsupported symmetries {}", symmetries)))?;
+        let permute = symmetries == "permute";
+
+        // every transition as a set of accepted states per position
+        let mut transitions: Vec<(Vec<States>, u8)> = Vec::new();
+        for (line_no, entries) in &lines {
+            if entries.len() != positions + 1 {
+                return Err(error(
+                    *line_no,
+                    format!("expected {} entries", positions + 1),
+                ));
+            }
+            for entry in entries {
+                let too_big = match entry {
+                    Entry::State(state) => *state as usize >= states,
+                    Entry::Var(var) => vars[var].iter().any(|s| *s as usize >= states),
+                };
+                if too_big {
+                    return Err(error(*line_no, "state out of range"));
+                }
+            }
+            let expanded = expand(entries, &vars).map_err(|reason| error(*line_no, reason))?;
+            for (inputs, output) in expanded {
+                let mut seen = HashSet::new();
+                let variants = if permute {
+                    unique_permutations(&inputs[1..])
+                } else {
+                    permutations
+                        .iter()
+                        .map(|p| p.iter().map(|i| inputs[i + 1]).collect())
+                        .collect()
+                };
+                for neighbours in variants {
+                    let mut variant = vec![inputs[0]];
+                    variant.extend(neighbours);
+                    if seen.insert(variant.clone()) {
+                        transitions.push((variant, output));
+                    }
+                }
+            }
+        }
+
+        let words = transitions.len().div_ceil(32).max(1);
+        let mut masks = vec![0u32; positions * states * words];
+        for (t, (inputs, _)) in transitions.iter().enumerate() {
+            for (position, set) in inputs.iter().enumerate() {
+                for state in 0..states {
+                    if contains(set, state as u8) {
+                        masks[(position * states + state) * words + t / 32] |= 1 << (t % 32);
+                    }
+                }
+            }
+        }
+
+        let mut colors = default_colors(states);
+        for (line_no, numbers) in color_lines {
+            if numbers.len() == 4 {
+                let state = numbers[0] as usize;
+                if state >= states {
+                    return Err(error(line_no, "state out of range"));
+                }
+                colors[state] = [numbers[1], numbers[2], numbers[3]];
+            } else {
+                colors = gradient(
+                    states,
+                    [numbers[0], numbers[1], numbers[2]],
+                    [numbers[3], numbers[4], numbers[5]],
+                    colors[0],
+                );
+            }
+        }
+
+        Ok(RuleTable {
+            name,
+            states: states as u8,
+            neighbourhood,
+            colors,
+            outputs: transitions.iter().map(|(_, output)| *output).collect(),
+            words,
+            masks,
+        })
+    }
+
+    // Transitions left after expanding variables and symmetries.
+    pub fn transitions(&self) -> usize {
+        self.outputs.len()
+    }
+
+    // Next state from the cell's own state followed by its neighbours'.
+    pub fn next(&self, cells: &[u8]) -> u8 {
+        debug_assert!(cells.len() == self.neighbourhood.offsets().len() + 1);
+        let states = self.states as usize;
+        if cells.iter().any(|state| *state as usize >= states) {
+            return cells[0];
+        }
+        for word in 0..self.words {
+            let mut matches = !0u32;
+            for (position, state) in cells.iter().enumerate() {
+                matches &= self.masks[(position * states + *state as usize) * self.words + word];
+            }
+            if matches != 0 {
+                return self.outputs[word * 32 + matches.trailing_zeros() as usize];
+            }
+        }
+        cells[0]
+    }
+
+    pub(crate) fn words(&self) -> usize {
+        self.words
+    }
+
+    // The masks followed by the outputs, the layout the compute shader reads.
+    pub(crate) fn to_gpu_words(&self) -> Vec<u32> {
+        let mut gpu_words = self.masks.clone();
+        gpu_words.extend(self.outputs.iter().map(|output| *output as u32));
+        gpu_words
+    }
+}
+
+// "name={1,2,b}" where b is an earlier variable.
+fn parse_var(definition: &str, vars: &HashMap<String, Vec<u8>>) -> Option<(String, Vec<u8>)> {
+    let (name, values) = definition.split_once('=')?;
+    let values = values.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let mut states = Vec::new();
+    for value in values.split(',').map(str::trim) {
+        match value.parse::<u8>() {
+            Ok(state) => states.push(state),
+            Err(_) => states.extend(vars.get(value)?),
+        }
+    }
+    Some((name.trim().to_string(), states))
+}
+
+fn parse_transition(
+    line: &str,
+    states: usize,
+    vars: &HashMap<String, Vec<u8>>,
+) -> Result<Vec<Entry>, String> {
+    let tokens: Vec<String> = if line.contains(',') {
+        line.split(',').map(|t| t.trim().to_string()).collect()
+    } else if states <= 10 {
+        // tables with few states may leave out the commas
+        line.chars()
+            .filter(|c| !c.is_whitespace())
+            .map(String::from)
+            .collect()
+    } else {
+        line.split_whitespace().map(String::from).collect()
+    };
+    tokens
+        .into_iter()
+        .map(|token| match token.parse::<u8>() {
+            Ok(state) => Ok(Entry::State(state)),
+            Err(_) if vars.contains_key(&token) => Ok(Entry::Var(token)),
+            Err(_) => Err(format!("unknown variable {}", token)),
+        })
+        .collect()
+}
+
+// Variables used more than once in a transition, or in its output, take the
+// same value everywhere, so the transition is repeated for each of their
+// values. Other variables accept any of their values.
+fn expand(
+    entries: &[Entry],
+    vars: &HashMap<String, Vec<u8>>,
+) -> Result<Vec<(Vec<States>, u8)>, String> {
+    let (inputs, output) = entries.split_at(entries.len() - 1);
+    let mut uses: HashMap<&str, usize> = HashMap::new();
+    for entry in inputs {
+        if let Entry::Var(var) = entry {
+            *uses.entry(var.as_str()).or_default() += 1;
+        }
+    }
+    let mut bound: Vec<&str> = uses
+        .iter()
+        .filter(|(_, count)| **count > 1)
+        .map(|(var, _)| *var)
+        .collect();
+    if let Entry::Var(var) = &output[0] {
+        if !uses.contains_key(var.as_str()) {
+            return Err(format!("output variable {} is not an input", var));
+        }
+        if !bound.contains(&var.as_str()) {
+            bound.push(var);
+        }
+    }
+    bound.sort_unstable();
+
+    let mut expanded = Vec::new();
+    let mut choice = vec![0; bound.len()];
+    loop {
+        let value = |var: &str| {
+            bound
+                .iter()
+                .position(|b| *b == var)
+                .map(|i| vars[var][choice[i]])
+        };
+        let sets = inputs
+            .iter()
+            .map(|entry| match entry {
+                Entry::State(state) => single(*state),
+                Entry::Var(var) => match value(var) {
+                    Some(state) => single(state),
+                    None => vars[var].iter().fold([0; 4], |mut set, state| {
+                        set[*state as usize / 64] |= 1 << (state % 64);
+                        set
+                    }),
+                },
+            })
+            .collect();
+        let next = match &output[0] {
+            Entry::State(state) => *state,
+            Entry::Var(var) => value(var).unwrap(),
+        };
+        expanded.push((sets, next));
+
+        // step to the next combination of bound values
+        let mut i = 0;
+        loop {
+            if i == bound.len() {
+                return Ok(expanded);
+            }
+            choice[i] += 1;
+            if choice[i] < vars[bound[i]].len() {
+                break;
+            }
+            choice[i] = 0;
+            i += 1;
+        }
+    }
+}
+
+// Orders of the neighbours a symmetry treats as the same, each giving the
+// position every neighbour is taken from. "permute" is handled separately.
+fn symmetry_permutations(symmetries: &str, neighbours: usize) -> Option<Vec<Vec<usize>>> {
+    let identity: Vec<usize> = (0..neighbours).collect();
+    if symmetries == "none" || symmetries == "permute" {
+        return Some(vec![identity]);
+    }
+    let (rotations, reflect) = match symmetries.strip_prefix("rotate") {
+        Some(rest) => {
+            let (count, reflect) = match rest.strip_suffix("reflect") {
+                Some(count) => (count, true),
+                None => (rest, false),
+            };
+            (count.parse::<usize>().ok()?, reflect)
+        }
+        None if symmetries == "reflect_horizontal" => (1, true),
+        None => return None,
+    };
+    if rotations == 0 || !neighbours.is_multiple_of(rotations) {
+        return None;
+    }
+
+    let step = neighbours / rotations;
+    let mut permutations = Vec::new();
+    for r in 0..rotations {
+        let rotated: Vec<usize> = (0..neighbours)
+            .map(|i| (i + r * step) % neighbours)
+            .collect();
+        if reflect {
+            // mirrored left to right, north stays where it is
+            let mirrored = (0..neighbours)
+                .map(|i| rotated[(neighbours - i) % neighbours])
+                .collect();
+            permutations.push(mirrored);
+        }
+        permutations.push(rotated);
+    }
+    Some(permutations)
+}
+
+// Every distinct ordering of the given sets.
+fn unique_permutations(items: &[States]) -> Vec<Vec<States>> {
+    let mut distinct: Vec<(States, usize)> = Vec::new();
+    for item in items {
+        match distinct.iter_mut().find(|(set, _)| set == item) {
+            Some((_, count)) => *count += 1,
+            None => distinct.push((*item, 1)),
+        }
+    }
+
+    fn fill(
+        distinct: &mut [(States, usize)],
+        current: &mut Vec<States>,
+        len: usize,
+        out: &mut Vec<Vec<States>>,
+    ) {
+        if current.len() == len {
+            out.push(current.clone());
+            return;
+        }
+        for i in 0..distinct.len() {
+            if distinct[i].1 == 0 {
+                continue;
+            }
+            distinct[i].1 -= 1;
+            current.push(distinct[i].0);
+            fill(distinct, current, len, out);
+            current.pop();
+            distinct[i].1 += 1;
+        }
+    }
+
+    let mut out = Vec::new();
+    fill(&mut distinct, &mut Vec::new(), items.len(), &mut out);
+    out
+}
+
+// Golly colours states it isn't told about with a red to yellow gradient.
+fn default_colors(states: usize) -> Vec<Rgb> {
+    gradient(states, [255, 0, 0], [255, 255, 0], [48, 48, 48])
+}
+
+fn gradient(states: usize, from: Rgb, to: Rgb, dead: Rgb) -> Vec<Rgb> {
+    let mut colors = vec![dead];
+    let steps = states.saturating_sub(2).max(1) as i32;
+    for state in 1..states {
+        let t = (state - 1) as i32;
+        let channel =
+            |c: usize| (from[c] as i32 + (to[c] as i32 - from[c] as i32) * t / steps) as u8;
+        colors.push([channel(0), channel(1), channel(2)]);
+    }
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wireworld_table() {
+        let table = RuleTable::wireworld();
+        assert_eq!(table.name, "WireWorld");
+        assert_eq!(table.states, 4);
+        // 1 + 1 + 8 + 28 orderings of the neighbours
+        assert_eq!(table.transitions(), 38);
+        assert_eq!(table.colors[1], [0, 128, 255]);
+
+        // conductor next to one head, two heads and three heads
+        assert_eq!(table.next(&[3, 1, 0, 0, 0, 0, 0, 0, 0]), 1);
+        assert_eq!(table.next(&[3, 0, 0, 1, 0, 0, 3, 1, 0]), 1);
+        assert_eq!(table.next(&[3, 1, 1, 0, 0, 0, 0, 0, 1]), 3);
+        assert_eq!(table.next(&[1, 3, 3, 3, 3, 3, 3, 3, 3]), 2);
+        assert_eq!(table.next(&[0, 1, 1, 1, 0, 0, 0, 0, 0]), 0);
+    }
+
+    #[test]
+    fn bound_variables_and_symmetries() {
+        let table = RuleTable::parse(
+            "@RULE Test
+            @TABLE
+            n_states:3
+            neighborhood:vonNeumann
+            symmetries:rotate4
+            var a={1,2}
+            var b={0,1,2}
+            # copy a pair of equal neighbours going clockwise
+            0,a,a,b,0,a
+            ",
+        )
+        .unwrap();
+        assert_eq!(table.transitions(), 8);
+        assert_eq!(table.next(&[0, 2, 2, 1, 0]), 2);
+        assert_eq!(table.next(&[0, 0, 1, 1, 2]), 1);
+        assert_eq!(table.next(&[0, 1, 2, 0, 0]), 0);
+
+        let compact = RuleTable::parse("@TABLE\nn_states:2\nneighborhood:vonNeumann\n010001");
+        assert_eq!(compact.unwrap().next(&[0, 1, 0, 0, 0]), 1);
+
+        let missing = RuleTable::parse("@TABLE\nn_states:2\n0,x,0,0,0,0,0,0,0,1");
+        assert_eq!(missing.unwrap_err().line, 3);
+
+        let too_many = RuleTable::parse("@TABLE\nn_states:256\n0,0,0,0,0,1");
+        assert!(too_many.unwrap_err().to_string().contains("256 states"));
+    }
+}
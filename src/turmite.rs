@@ -0,0 +1,500 @@
+use crate::rules::{BoardSizeError, ParseRuleError};
+use crate::Gol;
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+// Turn relative to the way an ant is facing, with Golly's numbers for them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Turn {
+    Straight = 1,
+    Right = 2,
+    UTurn = 4,
+    Left = 8,
+}
+
+impl Turn {
+    fn quarter_turns(self) -> u8 {
+        match self {
+            Turn::Straight => 0,
+            Turn::Right => 1,
+            Turn::UTurn => 2,
+            Turn::Left => 3,
+        }
+    }
+
+    fn from_number(number: u32) -> Option<Turn> {
+        [Turn::Straight, Turn::Right, Turn::UTurn, Turn::Left]
+            .into_iter()
+            .find(|turn| *turn as u32 == number)
+    }
+
+    fn from_letter(letter: char) -> Option<Turn> {
+        match letter.to_ascii_uppercase() {
+            'N' => Some(Turn::Straight),
+            'R' => Some(Turn::Right),
+            'U' => Some(Turn::UTurn),
+            'L' => Some(Turn::Left),
+            _ => None,
+        }
+    }
+
+    fn letter(self) -> char {
+        match self {
+            Turn::Straight => 'N',
+            Turn::Right => 'R',
+            Turn::UTurn => 'U',
+            Turn::Left => 'L',
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    const CLOCKWISE: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    fn turned(self, turn: Turn) -> Direction {
+        Direction::CLOCKWISE[(self as usize + turn.quarter_turns() as usize) % 4]
+    }
+
+    // (x, y) step, y grows going south like board rows do
+    fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::East => (1, 0),
+            Direction::South => (0, 1),
+            Direction::West => (-1, 0),
+        }
+    }
+}
+
+// What an ant does on a cell: the color it leaves there, how it turns before
+// stepping off and the state it takes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Transition {
+    pub write: u8,
+    pub turn: Turn,
+    pub next: u8,
+}
+
+// Turmite rule, a transition for every ant state and cell color. Langton's
+// ant is the single state rule RL.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Turmite {
+    pub states: u8,
+    pub colors: u8,
+    // indexed by state * colors + color
+    transitions: Vec<Transition>,
+}
+
+impl Turmite {
+    pub fn new(states: u8, colors: u8, transitions: Vec<Transition>) -> Turmite {
+        assert_eq!(transitions.len(), states as usize * colors as usize);
+        Turmite {
+            states,
+            colors,
+            transitions,
+        }
+    }
+
+    pub fn langtons_ant() -> Turmite {
+        "RL".parse().unwrap()
+    }
+
+    pub fn transition(&self, state: u8, color: u8) -> Transition {
+        self.transitions[state as usize * self.colors as usize + color as usize]
+    }
+
+    // One state, each color turning into the next one: the rules written as
+    // a turn per color.
+    fn letters(&self) -> Option<String> {
+        let cycles = self
+            .transitions
+            .iter()
+            .enumerate()
+            .all(|(color, transition)| {
+                transition.write as usize == (color + 1) % self.colors as usize
+                    && transition.next == 0
+            });
+        (self.states == 1 && cycles).then(|| {
+            self.transitions
+                .iter()
+                .map(|transition| transition.turn.letter())
+                .collect()
+        })
+    }
+}
+
+// The standard notation, {{{1, 2, 0}, {0, 8, 0}}}: for each state the write,
+// turn and next state triple of each color. Turns are 1 for none, 2 right, 4
+// back and 8 left.
+fn parse_triples(s: &str) -> Result<Vec<Vec<[u32; 3]>>, &'static str> {
+    let mut states = Vec::new();
+    let mut colors = Vec::new();
+    let mut triple = Vec::new();
+    let mut number = String::new();
+    let mut depth = 0;
+    for c in s.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() && depth == 3 {
+            number.push(c);
+            continue;
+        }
+        if !number.is_empty() {
+            triple.push(number.parse::<u32>().map_err(|_| "number out of range")?);
+            number.clear();
+        }
+        match (c, depth) {
+            ('{', 0..=2) => depth += 1,
+            ('}', 3) => {
+                let values: [u32; 3] = std::mem::take(&mut triple)
+                    .try_into()
+                    .map_err(|_| "transitions are triples")?;
+                colors.push(values);
+                depth -= 1;
+            }
+            ('}', 2) => {
+                states.push(std::mem::take(&mut colors));
+                depth -= 1;
+            }
+            ('}', 1) => depth -= 1,
+            (',', 1..=3) => {}
+            _ => return Err("expected nested {{{write, turn, next}, ...}}"),
+        }
+    }
+    if depth != 0 || states.is_empty() {
+        return Err("unbalanced braces");
+    }
+    Ok(states)
+}
+
+// Either a turn letter (L, R, N or U) for each color of a single state ant,
+// or the standard notation.
+impl FromStr for Turmite {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason| ParseRuleError {
+            rule: s.to_string(),
+            reason,
+        };
+        let rule = s.trim();
+        if !rule.starts_with('{') {
+            let turns: Vec<Turn> = rule
+                .chars()
+                .map(Turn::from_letter)
+                .collect::<Option<_>>()
+                .ok_or_else(|| error("turns are the letters L, R, N and U"))?;
+            if !(2..=255).contains(&turns.len()) {
+                return Err(error("ants need 2 to 255 colors"));
+            }
+            let colors = turns.len();
+            let transitions = turns
+                .into_iter()
+                .enumerate()
+                .map(|(color, turn)| Transition {
+                    write: ((color + 1) % colors) as u8,
+                    turn,
+                    next: 0,
+                })
+                .collect();
+            return Ok(Turmite::new(1, colors as u8, transitions));
+        }
+
+        let states = parse_triples(rule).map_err(error)?;
+        let colors = states[0].len();
+        if !(2..=255).contains(&colors) || states.len() > 255 {
+            return Err(error("turmites have 1 to 255 states and 2 to 255 colors"));
+        }
+        if states.iter().any(|state| state.len() != colors) {
+            return Err(error("every state needs a transition for each color"));
+        }
+        let transitions = states
+            .iter()
+            .flatten()
+            .map(|[write, turn, next]| {
+                match (
+                    (*write as usize) < colors,
+                    Turn::from_number(*turn),
+                    (*next as usize) < states.len(),
+                ) {
+                    (true, Some(turn), true) => Ok(Transition {
+                        write: *write as u8,
+                        turn,
+                        next: *next as u8,
+                    }),
+                    _ => Err(error("transition to a missing color, turn or state")),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Turmite::new(states.len() as u8, colors as u8, transitions))
+    }
+}
+
+impl Display for Turmite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(letters) = self.letters() {
+            return write!(f, "{}", letters);
+        }
+        let states: Vec<String> = self
+            .transitions
+            .chunks(self.colors as usize)
+            .map(|colors| {
+                let triples: Vec<String> = colors
+                    .iter()
+                    .map(|t| format!("{{{}, {}, {}}}", t.write, t.turn as u8, t.next))
+                    .collect();
+                format!("{{{}}}", triples.join(", "))
+            })
+            .collect();
+        write!(f, "{{{}}}", states.join(", "))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Ant {
+    pub x: usize,
+    pub y: usize,
+    pub direction: Direction,
+    pub state: u8,
+}
+
+#[derive(Debug)]
+pub struct AntError {
+    pub ant: Ant,
+    pub reason: &'static str,
+}
+
+impl Display for AntError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid ant {:?}: {}", self.ant, self.reason)
+    }
+}
+
+impl Error for AntError {}
+
+// Ants walking a size * size board of colors that wraps around its edges like
+// the Life boards.
+pub struct Turmites {
+    size: usize,
+    cells: Vec<u8>,
+    ants: Vec<Ant>,
+    rule: Turmite,
+}
+
+impl Turmites {
+    // An empty board with one ant in the middle facing north.
+    pub fn new(size: usize, rule: Turmite) -> Result<Turmites, BoardSizeError> {
+        if size == 0 {
+            return Err(BoardSizeError {
+                size,
+                reason: "turmites need a cell to start on",
+            });
+        }
+        let ant = Ant {
+            x: size / 2,
+            y: size / 2,
+            direction: Direction::North,
+            state: 0,
+        };
+        Ok(Turmites {
+            size,
+            cells: vec![0; size * size],
+            ants: vec![ant],
+            rule,
+        })
+    }
+
+    // Cell states of the board as colors, wrapped into the rule's colors.
+    pub fn from_board(
+        board: &dyn Gol,
+        rule: Turmite,
+        ants: Vec<Ant>,
+    ) -> Result<Turmites, AntError> {
+        let cells = board
+            .to_states()
+            .iter()
+            .map(|state| state % rule.colors)
+            .collect();
+        let mut turmites = Turmites {
+            size: board.size(),
+            cells,
+            ants: Vec::new(),
+            rule,
+        };
+        for ant in ants {
+            turmites.add_ant(ant)?;
+        }
+        Ok(turmites)
+    }
+
+    pub fn rule(&self) -> &Turmite {
+        &self.rule
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn cells(&self) -> &[u8] {
+        &self.cells
+    }
+
+    pub fn ants(&self) -> &[Ant] {
+        &self.ants
+    }
+
+    pub fn add_ant(&mut self, ant: Ant) -> Result<(), AntError> {
+        let error = |reason| Err(AntError { ant, reason });
+        if ant.x >= self.size || ant.y >= self.size {
+            return error("ants have to start on the board");
+        }
+        if ant.state >= self.rule.states {
+            return error("the rule has no such ant state");
+        }
+        self.ants.push(ant);
+        Ok(())
+    }
+
+    // Every ant moves once, in the order they were added.
+    pub fn step(&mut self) {
+        let size = self.size as i32;
+        for ant in &mut self.ants {
+            let cell = &mut self.cells[ant.y * self.size + ant.x];
+            let transition = self.rule.transition(ant.state, *cell);
+            *cell = transition.write;
+            ant.direction = ant.direction.turned(transition.turn);
+            ant.state = transition.next;
+            let (dx, dy) = ant.direction.offset();
+            ant.x = (ant.x as i32 + dx).rem_euclid(size) as usize;
+            ant.y = (ant.y as i32 + dy).rem_euclid(size) as usize;
+        }
+    }
+
+    pub fn run(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.step();
+        }
+    }
+
+    // The colors as cell states, for the print and image outputs.
+    pub fn board<T: Gol>(&self) -> T {
+        T::from_states(self.size, &self.cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_impls;
+
+    #[test]
+    fn langtons_ant_first_steps() {
+        let mut ants = Turmites::new(8, Turmite::langtons_ant()).unwrap();
+        ants.run(5);
+        let board: game_impls::cpu::GameState = ants.board();
+        board.print();
+        // right, right, right onto its first cell, which it clears turning left
+        assert_eq!(
+            board.to_text(),
+            "00000000\n\
+             00000000\n\
+             00000000\n\
+             00000000\n\
+             00000100\n\
+             00001100\n\
+             00000000\n\
+             00000000\n"
+        );
+        assert_eq!(
+            ants.ants(),
+            &[Ant {
+                x: 3,
+                y: 4,
+                direction: Direction::West,
+                state: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn langtons_ant_builds_a_highway() {
+        // the highway repeats every 104 steps two cells further diagonally
+        let mut ants = Turmites::new(128, Turmite::langtons_ant()).unwrap();
+        ants.run(11000);
+        let before = ants.ants()[0];
+        ants.run(104);
+        let after = ants.ants()[0];
+        assert_eq!(after.direction, before.direction);
+        assert_eq!(after.x.abs_diff(before.x), 2);
+        assert_eq!(after.y.abs_diff(before.y), 2);
+    }
+
+    #[test]
+    fn parses_turmites() {
+        let ant: Turmite = "{{{1, 2, 0}, {0, 8, 0}}}".parse().unwrap();
+        assert_eq!(ant, Turmite::langtons_ant());
+        assert_eq!(ant.to_string(), "RL");
+
+        let llrr: Turmite = "llrr".parse().unwrap();
+        assert_eq!((llrr.states, llrr.colors), (1, 4));
+        assert_eq!(llrr.to_string(), "LLRR");
+
+        // Fibonacci spiral
+        let spiral = "{{{1, 8, 1}, {1, 8, 1}}, {{1, 2, 1}, {0, 1, 0}}}";
+        let turmite: Turmite = spiral.parse().unwrap();
+        assert_eq!((turmite.states, turmite.colors), (2, 2));
+        assert_eq!(
+            turmite.transition(1, 1),
+            Transition {
+                write: 0,
+                turn: Turn::Straight,
+                next: 0,
+            }
+        );
+        assert_eq!(turmite.to_string(), spiral);
+
+        assert!("R".parse::<Turmite>().is_err());
+        assert!("RX".parse::<Turmite>().is_err());
+        assert!("{{{1, 2, 0}, {0, 8}}}".parse::<Turmite>().is_err());
+        assert!("{{{1, 3, 0}, {0, 8, 0}}}".parse::<Turmite>().is_err());
+        assert!("{{{1, 2, 1}, {0, 8, 0}}}".parse::<Turmite>().is_err());
+        assert!("{{{1, 2, 0}, {0, 8, 0}}".parse::<Turmite>().is_err());
+    }
+
+    #[test]
+    fn ants_share_the_board() {
+        let board = game_impls::cpu::GameState::from_states(6, &[1; 36]);
+        let ant = Ant {
+            x: 0,
+            y: 0,
+            direction: Direction::East,
+            state: 0,
+        };
+        let mut ants = Turmites::from_board(&board, Turmite::langtons_ant(), vec![ant]).unwrap();
+        ants.add_ant(Ant { x: 5, ..ant }).unwrap();
+        ants.step();
+        // both turn left off a live cell, the first wrapping to the bottom row
+        assert_eq!(ants.cells()[0], 0);
+        assert_eq!(ants.cells()[5], 0);
+        assert_eq!((ants.ants()[0].x, ants.ants()[0].y), (0, 5));
+        assert_eq!((ants.ants()[1].x, ants.ants()[1].y), (5, 5));
+        assert_eq!(ants.ants()[0].direction, Direction::North);
+
+        assert!(ants.add_ant(Ant { x: 6, ..ant }).is_err());
+        assert!(ants.add_ant(Ant { state: 1, ..ant }).is_err());
+        assert!(Turmites::new(0, Turmite::langtons_ant()).is_err());
+        assert_eq!(ants.ants().len(), 2);
+    }
+}